@@ -0,0 +1,20 @@
+//! Timing shared by the benchmarks, which run with `cargo bench` and print their numbers instead of asserting on
+//! them. The best of several runs is reported because it is the least disturbed by whatever else the machine does.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+pub fn best_of<R>(runs: usize, mut run: impl FnMut() -> R) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(run());
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+pub fn report(name: &str, time: Duration) {
+    println!("{:<44} {:>9.2} ms", name, time.as_secs_f64() * 1000.0);
+}
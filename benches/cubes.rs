@@ -0,0 +1,59 @@
+//! Cost of `Cube::ray_intersect`, alone and in a frame made of nothing but cubes, traced on one thread.
+
+mod common;
+
+use nalgebra_glm::Vec3;
+use proyecto3gc::camera::Camera;
+use proyecto3gc::color::Color;
+use proyecto3gc::cube::Cube;
+use proyecto3gc::environment::{EnvMap, Environment, SkyGradient};
+use proyecto3gc::framebuffer::Framebuffer;
+use proyecto3gc::light::Light;
+use proyecto3gc::material::Material;
+use proyecto3gc::object::{Object, Visibility};
+use proyecto3gc::ray_intersect::RayIntersect;
+use proyecto3gc::renderer::render;
+use proyecto3gc::render_settings::RenderSettings;
+use proyecto3gc::sampling::hash_index;
+use proyecto3gc::scene::Scene;
+
+const RAYS: u32 = 1_000_000;
+const RUNS: usize = 5;
+
+fn cube_field() -> Scene {
+    let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+    let stone = Material::new(Color::new(160, 150, 140), 20.0, [0.8, 0.2, 0.1, 0.0], 0.0, Color::black(), false);
+    for x in -10..10 {
+        for z in -10..10 {
+            let height = (hash_index((x + 10) as u32 * 20 + (z + 10) as u32, 0) * 3.0).floor();
+            for y in 0..=height as i32 {
+                let cube = Cube::new(Vec3::new(x as f32, y as f32, z as f32), 1.0, stone);
+                scene.objects.push(Object::Cube(cube, Visibility::default()));
+            }
+        }
+    }
+    scene.lights.push(Light::white(Vec3::new(6.0, 12.0, 8.0)));
+    scene
+}
+
+fn main() {
+    let cube = Cube::new(Vec3::new(0.2, -0.1, 0.3), 1.5, Material::sand());
+    let rays: Vec<(Vec3, Vec3)> = (0..RAYS)
+        .map(|seed| {
+            let point = |axis: u32| Vec3::new(hash_index(seed, axis), hash_index(seed, axis + 1), hash_index(seed, axis + 2));
+            let origin = (point(0) * 2.0 - Vec3::new(1.0, 1.0, 1.0)).normalize() * 4.0;
+            (origin, (point(3) - Vec3::new(0.5, 0.5, 0.5) - origin).normalize())
+        })
+        .collect();
+    let time = common::best_of(RUNS, || rays.iter().filter(|(origin, direction)| cube.ray_intersect(origin, direction).is_intersecting).count());
+    common::report(&format!("{} rays at one cube", RAYS), time);
+
+    let scene = cube_field();
+    let camera = Camera::new(Vec3::new(0.0, 9.0, 16.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let settings = RenderSettings::default();
+    let time = common::best_of(RUNS, || {
+        let mut framebuffer = Framebuffer::new(200, 150);
+        render(&mut framebuffer, &scene, &camera, &settings, None)
+    });
+    common::report(&format!("200x150 frame of {} cubes", scene.objects.len()), time);
+}
@@ -0,0 +1,73 @@
+//! Single-threaded cost of the shadow and skylight loops, which trace against sample sets generated once per frame.
+//! Each is timed against what it replaced: one shadow ray per light, or sample directions generated inside the loop.
+
+mod common;
+
+use nalgebra_glm::Vec3;
+use proyecto3gc::color::Color;
+use proyecto3gc::cube::Cube;
+use proyecto3gc::environment::{EnvMap, Environment, SkyGradient};
+use proyecto3gc::light::Light;
+use proyecto3gc::material::Material;
+use proyecto3gc::object::{Object, Visibility};
+use proyecto3gc::ray_intersect::Intersect;
+use proyecto3gc::renderer::{cast_shadow, cast_shadow_sample, skylight};
+use proyecto3gc::sampling::{hammersley, hash_index, sphere_samples};
+use proyecto3gc::scene::Scene;
+
+const POINTS: u32 = 20_000;
+const SAMPLES: usize = 16;
+const LIGHT_RADIUS: f32 = 0.25;
+const RUNS: usize = 5;
+
+fn occluded_floor() -> Scene {
+    let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+    for x in -4..4 {
+        for z in -4..4 {
+            if (x + z) % 3 == 0 {
+                let cube = Cube::new(Vec3::new(x as f32 + 0.5, 0.5, z as f32 + 0.5), 0.8, Material::sand());
+                scene.objects.push(Object::Cube(cube, Visibility::default()));
+            }
+        }
+    }
+    scene
+}
+
+fn main() {
+    let scene = occluded_floor();
+    let light = Light::white(Vec3::new(2.0, 6.0, 3.0));
+    let hits: Vec<Intersect> = (0..POINTS)
+        .map(|seed| {
+            let point = Vec3::new(hash_index(seed, 0) * 8.0 - 4.0, 0.0, hash_index(seed, 1) * 8.0 - 4.0);
+            Intersect::new(point, Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand())
+        })
+        .collect();
+    let shadow_offsets = sphere_samples(SAMPLES);
+    let hard_offset = sphere_samples(1);
+    let sky_samples = hammersley(SAMPLES);
+
+    let shadows = |shadow: &dyn Fn(&Intersect) -> f32| common::best_of(RUNS, || hits.iter().map(shadow).sum::<f32>());
+    common::report(
+        "hard shadow, single ray",
+        shadows(&|hit| cast_shadow_sample(hit, &light.position, &scene, None)),
+    );
+    common::report(
+        "hard shadow, one-sample set",
+        shadows(&|hit| cast_shadow(hit, &light, LIGHT_RADIUS, 0.0, &scene, &hard_offset, None)),
+    );
+    common::report(
+        &format!("soft shadow, {} shared samples", SAMPLES),
+        shadows(&|hit| cast_shadow(hit, &light, LIGHT_RADIUS, 0.0, &scene, &shadow_offsets, None)),
+    );
+    common::report(
+        &format!("soft shadow, {} samples made per hit", SAMPLES),
+        shadows(&|hit| cast_shadow(hit, &light, LIGHT_RADIUS, 0.0, &scene, &sphere_samples(SAMPLES), None)),
+    );
+
+    let sky = |light: &dyn Fn(&Intersect) -> Color| common::best_of(RUNS, || hits.iter().map(|hit| light(hit).luminance()).sum::<f32>());
+    common::report(&format!("skylight, {} shared samples", SAMPLES), sky(&|hit| skylight(hit, &scene, &sky_samples, None)));
+    common::report(
+        &format!("skylight, {} samples made per hit", SAMPLES),
+        sky(&|hit| skylight(hit, &scene, &hammersley(SAMPLES), None)),
+    );
+}
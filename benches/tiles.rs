@@ -0,0 +1,113 @@
+//! Thread scaling of the tile queue against row scheduling on a frame whose cost sits in one corner: a stack of
+//! glass and mirror cubes top left, over a plain diffuse floor that is cheap everywhere else. Wall-clock times only
+//! mean something with as many cores as threads, so the projected speedups from per-unit costs are printed too.
+
+mod common;
+
+use nalgebra_glm::Vec3;
+use proyecto3gc::camera::Camera;
+use proyecto3gc::color::Color;
+use proyecto3gc::cube::Cube;
+use proyecto3gc::environment::{EnvMap, Environment, SkyGradient};
+use proyecto3gc::framebuffer::Framebuffer;
+use proyecto3gc::light::Light;
+use proyecto3gc::material::Material;
+use proyecto3gc::object::{Object, Visibility};
+use proyecto3gc::render_pool::RenderPool;
+use proyecto3gc::render_settings::RenderSettings;
+use proyecto3gc::renderer::{render_tiled, TILE_SIZE};
+use proyecto3gc::scene::Scene;
+use std::time::Duration;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+const RUNS: usize = 3;
+
+fn heavy_corner_scene() -> Scene {
+    let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+    let floor = Material::new(Color::new(180, 170, 150), 10.0, [0.9, 0.1, 0.0, 0.0], 0.0, Color::black(), false);
+    let glass = Material::new(Color::new(220, 235, 255), 125.0, [0.1, 0.5, 0.1, 0.8], 1.5, Color::black(), false);
+    let mirror = Material::new(Color::new(200, 200, 200), 50.0, [0.2, 0.3, 0.8, 0.0], 0.0, Color::black(), false);
+    for x in -6..=6 {
+        for z in -6..=2 {
+            let cube = Cube::new(Vec3::new(x as f32, -1.0, z as f32), 1.0, floor);
+            scene.objects.push(Object::Cube(cube, Visibility::default()));
+        }
+    }
+    for row in 0..3 {
+        for column in 0..3 {
+            let material = if (row + column) % 2 == 0 { glass } else { mirror };
+            let center = Vec3::new(-4.2 + column as f32, 2.2 + row as f32, 2.0);
+            scene.objects.push(Object::Cube(Cube::new(center, 0.95, material), Visibility::default()));
+        }
+    }
+    scene.lights.push(Light::white(Vec3::new(2.0, 8.0, 6.0)));
+    scene
+}
+
+/// The regions a `tile` schedule hands out, in the order workers pull them.
+fn units(tile: (usize, usize)) -> Vec<(usize, usize, usize, usize)> {
+    let mut units = Vec::new();
+    for y in (0..HEIGHT).step_by(tile.1) {
+        for x in (0..WIDTH).step_by(tile.0) {
+            units.push((x, y, (x + tile.0).min(WIDTH), (y + tile.1).min(HEIGHT)));
+        }
+    }
+    units
+}
+
+/// When the last of `threads` workers finishes if each takes the next unit as soon as it is free, which is what the
+/// shared counter does. It only needs each unit's serial cost, so it shows the balance even on a single core.
+fn makespan(costs: &[Duration], threads: usize) -> Duration {
+    let mut free_at = vec![Duration::ZERO; threads];
+    for cost in costs {
+        let worker = free_at.iter_mut().min().expect("at least one worker");
+        *worker += *cost;
+    }
+    free_at.into_iter().max().unwrap_or_default()
+}
+
+/// Each unit's serial render time, in pull order.
+fn unit_costs(scene: &Scene, camera: &Camera, settings: &RenderSettings, tile: (usize, usize)) -> Vec<Duration> {
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    units(tile)
+        .into_iter()
+        .map(|unit| common::best_of(1, || render_tiled(&mut framebuffer, scene, camera, settings, Some(unit), tile)))
+        .collect()
+}
+
+fn schedules(threads: usize) -> [(&'static str, (usize, usize)); 3] {
+    [
+        ("32x32 tiles", (TILE_SIZE, TILE_SIZE)),
+        ("one row per task", (WIDTH, 1)),
+        ("one band of rows per thread", (WIDTH, HEIGHT.div_ceil(threads))),
+    ]
+}
+
+fn main() {
+    let scene = heavy_corner_scene();
+    let camera = Camera::new(Vec3::new(0.0, 1.5, 9.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let settings = RenderSettings { shadow_samples: 8, ..RenderSettings::default() };
+    let cores = std::thread::available_parallelism().map_or(1, |count| count.get());
+    println!("{}x{} heavy-corner frame, {} cores available", WIDTH, HEIGHT, cores);
+
+    for threads in [1, 2, 4, 8] {
+        let mut pool = RenderPool::new(threads);
+        for (name, tile) in schedules(threads) {
+            let time = common::best_of(RUNS, || {
+                let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+                pool.install(threads, || render_tiled(&mut framebuffer, &scene, &camera, &settings, None, tile))
+            });
+            common::report(&format!("{} threads, {}", threads, name), time);
+        }
+    }
+
+    println!("speedup over one thread if every thread had a core of its own:");
+    for threads in [2, 4, 8, 16] {
+        for (name, tile) in schedules(threads) {
+            let costs = unit_costs(&scene, &camera, &settings, tile);
+            let speedup = costs.iter().sum::<Duration>().as_secs_f64() / makespan(&costs, threads).as_secs_f64();
+            println!("{:<44} {:>9.2}x", format!("{} threads, {}", threads, name), speedup);
+        }
+    }
+}
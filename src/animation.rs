@@ -0,0 +1,96 @@
+use nalgebra_glm::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, translation: Vec3) -> Self {
+        Keyframe { time, translation }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    keyframes: Vec<Keyframe>,
+    pub easing: Easing,
+    pub looping: bool,
+}
+
+impl Animation {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Animation {
+            keyframes,
+            easing: Easing::Linear,
+            looping: false,
+        }
+    }
+
+    pub fn eased(mut self) -> Self {
+        self.easing = Easing::EaseInOut;
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    pub fn translation_at(&self, time: f32) -> Vec3 {
+        let (first, last) = match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Vec3::zeros(),
+        };
+
+        let duration = self.duration();
+        let time = if self.looping && duration > 0.0 {
+            first.time + (time - first.time).rem_euclid(duration)
+        } else {
+            time
+        };
+
+        if time <= first.time {
+            return first.translation;
+        }
+        if time >= last.time {
+            return last.translation;
+        }
+
+        for pair in self.keyframes.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if time <= to.time {
+                let span = to.time - from.time;
+                let t = if span > 0.0 { (time - from.time) / span } else { 1.0 };
+                let t = self.easing.apply(t);
+                return from.translation + (to.translation - from.translation) * t;
+            }
+        }
+
+        last.translation
+    }
+}
@@ -0,0 +1,350 @@
+use nalgebra_glm::Vec3;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::json::Json;
+use crate::render_settings::{PostBlur, RenderChannel, RenderSettings, ShadowMode, SpecularModel};
+use crate::tone_map::ToneMap;
+use crate::color_space::OutputColorSpace;
+use crate::sampling::{ReconstructionFilter, SamplePattern};
+
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub camera: Camera,
+    pub sun_angle: f32,
+    pub light_color_index: usize,
+    pub settings: RenderSettings,
+    pub track_light: bool,
+    pub show_hud: bool,
+}
+
+impl AppState {
+    pub fn new(camera: Camera) -> Self {
+        AppState {
+            camera,
+            sun_angle: 0.0,
+            light_color_index: 0,
+            settings: RenderSettings::default(),
+            track_light: false,
+            show_hud: false,
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, format!("{}\n", self.to_json())).map_err(|err| err.to_string())
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let json = Json::parse(&text)?;
+        self.apply_json(&json)
+    }
+
+    /// Just the camera, in the same shape as the full state so it can be loaded on its own.
+    pub fn camera_json(&self) -> Json {
+        Json::Object(vec![("camera".to_string(), camera_to_json(&self.camera))])
+    }
+
+    pub fn to_json(&self) -> Json {
+        let settings = &self.settings;
+        Json::Object(vec![
+            ("camera".to_string(), camera_to_json(&self.camera)),
+            ("sun_angle".to_string(), Json::Number(self.sun_angle as f64)),
+            ("light_color_index".to_string(), Json::Number(self.light_color_index as f64)),
+            ("track_light".to_string(), Json::Bool(self.track_light)),
+            ("show_hud".to_string(), Json::Bool(self.show_hud)),
+            ("settings".to_string(), Json::Object(vec![
+                ("env_reflection".to_string(), Json::Bool(settings.env_reflection)),
+                ("light_scale".to_string(), Json::Number(settings.light_scale as f64)),
+                ("specular_knee".to_string(), Json::Number(settings.specular_knee as f64)),
+                ("debug_non_finite".to_string(), Json::Bool(settings.debug_non_finite)),
+                ("specular_model".to_string(), Json::String(format!("{:?}", settings.specular_model))),
+                ("shadow_samples".to_string(), Json::Number(settings.shadow_samples as f64)),
+                ("light_radius".to_string(), Json::Number(settings.light_radius as f64)),
+                ("channel".to_string(), Json::String(format!("{:?}", settings.channel))),
+                ("glossy_samples".to_string(), Json::Number(settings.glossy_samples as f64)),
+                ("sky_rotation_speed".to_string(), Json::Number(settings.sky_rotation_speed as f64)),
+                ("exposure".to_string(), Json::Number(settings.exposure as f64)),
+                ("chromatic_aberration".to_string(), Json::Number(settings.chromatic_aberration as f64)),
+                ("motion_blur_samples".to_string(), Json::Number(settings.motion_blur_samples as f64)),
+                ("firefly_clamp".to_string(), settings.firefly_clamp.map_or(Json::Null, |multiple| Json::Number(multiple as f64))),
+                ("analytic_sky".to_string(), Json::Bool(settings.analytic_sky)),
+                ("sky_turbidity".to_string(), Json::Number(settings.sky_turbidity as f64)),
+                ("threads".to_string(), Json::Number(settings.threads as f64)),
+                ("tone_map".to_string(), Json::String(format!("{:?}", settings.tone_map))),
+                ("shadow_mode".to_string(), Json::String(format!("{:?}", settings.shadow_mode))),
+                ("color_space".to_string(), Json::String(format!("{:?}", settings.color_space))),
+                ("light_samples".to_string(), Json::Number(settings.light_samples as f64)),
+                ("camera_shake".to_string(), Json::Number(settings.camera_shake as f64)),
+                ("sorted_transparency".to_string(), Json::Bool(settings.sorted_transparency)),
+                ("contact_hardening".to_string(), Json::Number(settings.contact_hardening as f64)),
+                ("temporal_blend".to_string(), Json::Number(settings.temporal_blend as f64)),
+                ("skylight_samples".to_string(), Json::Number(settings.skylight_samples as f64)),
+                ("frame_budget_ms".to_string(), Json::Number(settings.frame_budget_ms as f64)),
+                ("budget_min_level".to_string(), Json::Number(settings.budget_min_level as f64)),
+                ("budget_max_level".to_string(), Json::Number(settings.budget_max_level as f64)),
+                ("aa_samples".to_string(), Json::Number(settings.aa_samples as f64)),
+                ("sample_pattern".to_string(), Json::String(format!("{:?}", settings.sample_pattern))),
+                ("reconstruction_filter".to_string(), Json::String(format!("{:?}", settings.reconstruction_filter))),
+                ("edge_antialiasing".to_string(), Json::Bool(settings.edge_antialiasing)),
+                ("reflection_clamp".to_string(), settings.reflection_clamp.map_or(Json::Null, |limit| Json::Number(limit as f64))),
+                ("radiance_clamp".to_string(), settings.radiance_clamp.map_or(Json::Null, |limit| Json::Number(limit as f64))),
+                ("post_blur".to_string(), Json::String(format!("{:?}", settings.post_blur))),
+                ("focus_distance".to_string(), Json::Number(settings.focus_distance as f64)),
+                ("reflection_cutoff".to_string(), Json::Number(settings.reflection_cutoff as f64)),
+                ("bilinear_reflections".to_string(), Json::Bool(settings.bilinear_reflections)),
+                ("wind_strength".to_string(), Json::Number(settings.wind_strength as f64)),
+                ("auto_exposure".to_string(), Json::Bool(settings.auto_exposure)),
+                ("exposure_target".to_string(), Json::Number(settings.exposure_target as f64)),
+                ("bloom".to_string(), Json::Bool(settings.bloom)),
+                ("bloom_threshold".to_string(), vec3_to_json(&Vec3::from(settings.bloom_threshold))),
+                ("bloom_tint".to_string(), vec3_to_json(&Vec3::from(settings.bloom_tint.to_unit()))),
+                ("caustics".to_string(), Json::Bool(settings.caustics)),
+                ("caustics_strength".to_string(), Json::Number(settings.caustics_strength as f64)),
+                ("depth_tie_break".to_string(), Json::Bool(settings.depth_tie_break)),
+                ("russian_roulette".to_string(), Json::Bool(settings.russian_roulette)),
+            ])),
+        ])
+    }
+
+    pub fn apply_json(&mut self, json: &Json) -> Result<(), String> {
+        if let Some(camera) = json.get("camera") {
+            if let Some(eye) = camera.get("eye") {
+                self.camera.eye = vec3_from_json(eye)?;
+            }
+            if let Some(center) = camera.get("center") {
+                self.camera.center = vec3_from_json(center)?;
+            }
+            if let Some(up) = camera.get("up") {
+                self.camera.up = vec3_from_json(up)?;
+            }
+            read_f32(camera, "speed", &mut self.camera.speed)?;
+            read_f32(camera, "pixel_aspect", &mut self.camera.pixel_aspect)?;
+            read_f32(camera, "fov", &mut self.camera.fov)?;
+        }
+        read_f32(json, "sun_angle", &mut self.sun_angle)?;
+        read_usize(json, "light_color_index", &mut self.light_color_index)?;
+        read_bool(json, "track_light", &mut self.track_light)?;
+        read_bool(json, "show_hud", &mut self.show_hud)?;
+
+        if let Some(json) = json.get("settings") {
+            let settings = &mut self.settings;
+            read_bool(json, "env_reflection", &mut settings.env_reflection)?;
+            read_f32(json, "light_scale", &mut settings.light_scale)?;
+            read_f32(json, "specular_knee", &mut settings.specular_knee)?;
+            read_bool(json, "debug_non_finite", &mut settings.debug_non_finite)?;
+            if let Some(value) = json.get("specular_model") {
+                settings.specular_model = value
+                    .as_str()
+                    .and_then(SpecularModel::from_name)
+                    .ok_or("invalid specular_model")?;
+            }
+            read_usize(json, "shadow_samples", &mut settings.shadow_samples)?;
+            read_f32(json, "light_radius", &mut settings.light_radius)?;
+            if let Some(value) = json.get("channel") {
+                settings.channel = value
+                    .as_str()
+                    .and_then(RenderChannel::from_name)
+                    .ok_or("invalid channel")?;
+            }
+            read_usize(json, "glossy_samples", &mut settings.glossy_samples)?;
+            read_f32(json, "sky_rotation_speed", &mut settings.sky_rotation_speed)?;
+            read_f32(json, "exposure", &mut settings.exposure)?;
+            read_f32(json, "chromatic_aberration", &mut settings.chromatic_aberration)?;
+            read_usize(json, "motion_blur_samples", &mut settings.motion_blur_samples)?;
+            read_optional_f32(json, "firefly_clamp", &mut settings.firefly_clamp)?;
+            read_bool(json, "analytic_sky", &mut settings.analytic_sky)?;
+            read_f32(json, "sky_turbidity", &mut settings.sky_turbidity)?;
+            read_usize(json, "threads", &mut settings.threads)?;
+            if let Some(value) = json.get("tone_map") {
+                settings.tone_map = value
+                    .as_str()
+                    .and_then(ToneMap::from_name)
+                    .ok_or("invalid tone_map")?;
+            }
+            read_usize(json, "light_samples", &mut settings.light_samples)?;
+            if let Some(value) = json.get("shadow_mode") {
+                settings.shadow_mode = value
+                    .as_str()
+                    .and_then(ShadowMode::from_name)
+                    .ok_or("invalid shadow_mode")?;
+            }
+            if let Some(value) = json.get("color_space") {
+                settings.color_space = value
+                    .as_str()
+                    .and_then(OutputColorSpace::from_name)
+                    .ok_or("invalid color_space")?;
+            }
+            read_f32(json, "camera_shake", &mut settings.camera_shake)?;
+            read_bool(json, "sorted_transparency", &mut settings.sorted_transparency)?;
+            read_f32(json, "contact_hardening", &mut settings.contact_hardening)?;
+            read_f32(json, "temporal_blend", &mut settings.temporal_blend)?;
+            read_usize(json, "skylight_samples", &mut settings.skylight_samples)?;
+            read_f32(json, "frame_budget_ms", &mut settings.frame_budget_ms)?;
+            read_usize(json, "budget_min_level", &mut settings.budget_min_level)?;
+            read_usize(json, "budget_max_level", &mut settings.budget_max_level)?;
+            read_usize(json, "aa_samples", &mut settings.aa_samples)?;
+            if let Some(value) = json.get("sample_pattern") {
+                settings.sample_pattern = value
+                    .as_str()
+                    .and_then(SamplePattern::from_name)
+                    .ok_or("invalid sample_pattern")?;
+            }
+            if let Some(value) = json.get("reconstruction_filter") {
+                settings.reconstruction_filter = value
+                    .as_str()
+                    .and_then(ReconstructionFilter::from_name)
+                    .ok_or("invalid reconstruction_filter")?;
+            }
+            read_bool(json, "edge_antialiasing", &mut settings.edge_antialiasing)?;
+            read_optional_f32(json, "reflection_clamp", &mut settings.reflection_clamp)?;
+            read_optional_f32(json, "radiance_clamp", &mut settings.radiance_clamp)?;
+            if let Some(value) = json.get("post_blur") {
+                settings.post_blur = value
+                    .as_str()
+                    .and_then(PostBlur::from_name)
+                    .ok_or("invalid post_blur")?;
+            }
+            read_f32(json, "focus_distance", &mut settings.focus_distance)?;
+            read_f32(json, "reflection_cutoff", &mut settings.reflection_cutoff)?;
+            read_bool(json, "bilinear_reflections", &mut settings.bilinear_reflections)?;
+            read_f32(json, "wind_strength", &mut settings.wind_strength)?;
+            read_bool(json, "auto_exposure", &mut settings.auto_exposure)?;
+            read_f32(json, "exposure_target", &mut settings.exposure_target)?;
+            read_bool(json, "bloom", &mut settings.bloom)?;
+            if let Some(value) = json.get("bloom_threshold") {
+                settings.bloom_threshold = vec3_from_json(value)?.into();
+            }
+            if let Some(value) = json.get("bloom_tint") {
+                let tint = vec3_from_json(value)?;
+                settings.bloom_tint = Color::from_unit(tint.x, tint.y, tint.z);
+            }
+            read_bool(json, "caustics", &mut settings.caustics)?;
+            read_f32(json, "caustics_strength", &mut settings.caustics_strength)?;
+            read_bool(json, "depth_tie_break", &mut settings.depth_tie_break)?;
+            read_bool(json, "russian_roulette", &mut settings.russian_roulette)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn vec3_to_json(vector: &Vec3) -> Json {
+    Json::Array(vec![
+        Json::Number(vector.x as f64),
+        Json::Number(vector.y as f64),
+        Json::Number(vector.z as f64),
+    ])
+}
+
+fn camera_to_json(camera: &Camera) -> Json {
+    Json::Object(vec![
+        ("eye".to_string(), vec3_to_json(&camera.eye)),
+        ("center".to_string(), vec3_to_json(&camera.center)),
+        ("up".to_string(), vec3_to_json(&camera.up)),
+        ("speed".to_string(), Json::Number(camera.speed as f64)),
+        ("pixel_aspect".to_string(), Json::Number(camera.pixel_aspect as f64)),
+        ("fov".to_string(), Json::Number(camera.fov as f64)),
+    ])
+}
+
+pub fn vec3_from_json(json: &Json) -> Result<Vec3, String> {
+    match json.as_array() {
+        Some([x, y, z]) => match (x.as_f32(), y.as_f32(), z.as_f32()) {
+            (Some(x), Some(y), Some(z)) => Ok(Vec3::new(x, y, z)),
+            _ => Err("vector components must be numbers".to_string()),
+        },
+        _ => Err("expected a [x, y, z] array".to_string()),
+    }
+}
+
+fn read_f32(json: &Json, key: &str, target: &mut f32) -> Result<(), String> {
+    if let Some(value) = json.get(key) {
+        *target = value.as_f32().ok_or_else(|| format!("{} must be a number", key))?;
+    }
+    Ok(())
+}
+
+fn read_usize(json: &Json, key: &str, target: &mut usize) -> Result<(), String> {
+    if let Some(value) = json.get(key) {
+        *target = value.as_usize().ok_or_else(|| format!("{} must be a non-negative number", key))?;
+    }
+    Ok(())
+}
+
+fn read_optional_f32(json: &Json, key: &str, target: &mut Option<f32>) -> Result<(), String> {
+    if let Some(value) = json.get(key) {
+        *target = match value {
+            Json::Null => None,
+            value => Some(value.as_f32().ok_or_else(|| format!("{} must be a number or null", key))?),
+        };
+    }
+    Ok(())
+}
+
+fn read_bool(json: &Json, key: &str, target: &mut bool) -> Result<(), String> {
+    if let Some(value) = json.get(key) {
+        *target = value.as_bool().ok_or_else(|| format!("{} must be true or false", key))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuned_state() -> AppState {
+        let mut state = AppState::new(Camera::new(Vec3::new(1.0, 2.0, 3.0), Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0)));
+        state.camera.fov = 0.9;
+        state.camera.pixel_aspect = 2.0;
+        state.camera.speed = 0.25;
+        state.sun_angle = 1.25;
+        state.light_color_index = 2;
+        state.track_light = true;
+        state.show_hud = true;
+        state.settings = RenderSettings {
+            specular_model: SpecularModel::BlinnPhong,
+            shadow_mode: ShadowMode::Blob,
+            channel: RenderChannel::Depth,
+            tone_map: ToneMap::Aces,
+            color_space: OutputColorSpace::Gamma22,
+            sample_pattern: SamplePattern::Halton,
+            reconstruction_filter: ReconstructionFilter::Mitchell,
+            post_blur: PostBlur::Radial,
+            aa_samples: 9,
+            exposure: 1.75,
+            firefly_clamp: Some(4.0),
+            bloom_threshold: [0.9, 0.8, 0.7],
+            bloom_tint: Color::new(255, 200, 100),
+            depth_tie_break: true,
+            russian_roulette: true,
+            ..RenderSettings::default()
+        };
+        state
+    }
+
+    #[test]
+    fn state_round_trips_through_json_text() {
+        let saved = tuned_state();
+        let text = saved.to_json().to_string();
+        let mut restored = AppState::new(Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0)));
+        restored.apply_json(&Json::parse(&text).unwrap()).unwrap();
+
+        assert_eq!(restored.settings, saved.settings);
+        assert_eq!((restored.camera.eye, restored.camera.center, restored.camera.up), (saved.camera.eye, saved.camera.center, saved.camera.up));
+        assert_eq!((restored.camera.fov, restored.camera.pixel_aspect, restored.camera.speed), (saved.camera.fov, saved.camera.pixel_aspect, saved.camera.speed));
+        assert_eq!((restored.sun_angle, restored.light_color_index), (saved.sun_angle, saved.light_color_index));
+        assert_eq!((restored.track_light, restored.show_hud), (saved.track_light, saved.show_hud));
+        assert_eq!(restored.to_json().to_string(), text);
+    }
+
+    #[test]
+    fn partial_json_keeps_other_fields_and_bad_values_are_rejected() {
+        let mut state = tuned_state();
+        state.apply_json(&Json::parse(r#"{"sun_angle": 0.5}"#).unwrap()).unwrap();
+        assert_eq!(state.sun_angle, 0.5);
+        assert_eq!(state.settings, tuned_state().settings);
+
+        assert!(state.apply_json(&Json::parse(r#"{"settings": {"tone_map": "Filmic"}}"#).unwrap()).is_err());
+        assert!(state.apply_json(&Json::parse(r#"{"camera": {"eye": [1, 2]}}"#).unwrap()).is_err());
+    }
+}
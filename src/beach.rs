@@ -0,0 +1,329 @@
+use nalgebra_glm::Vec3;
+use std::ops::Range;
+use crate::animation::{Animation, Keyframe};
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::instance::Instance;
+use crate::light::Light;
+use crate::material::Material;
+use crate::math::saturate;
+use crate::noise::value_noise;
+use crate::object::{Object, Visibility};
+use crate::plane::{Plane, Shore};
+use crate::scatter::scatter;
+use crate::scene_builder::SceneBuilder;
+use crate::transform::Transform;
+
+const WAVE_AMPLITUDE: f32 = 0.2;
+const WAVE_NUMBER: f32 = 1.0;
+const WATER_GRID_SIZE: usize = 6;
+pub const WATER_RESOLUTIONS: [usize; 3] = [WATER_GRID_SIZE, 12, 24];
+const SAND_HOUSE_SIZE: (usize, usize, usize) = (5, 3, 5);
+const SAND_HOUSE_CAPACITY: usize = SAND_HOUSE_SIZE.0 * (SAND_HOUSE_SIZE.1 + 1) * SAND_HOUSE_SIZE.2;
+pub const BEACH_MONITOR: usize = 0;
+pub const SUN_LIGHT: usize = 2;
+const WATER_PROTOTYPE: usize = 0;
+const LEAF_PROTOTYPE: usize = 1;
+const SAND_PROTOTYPE: usize = 2;
+const LANTERN_POSITION: Vec3 = Vec3::new(-3.5, 5.1, -4.0);
+const LANTERN_COLOR: Color = Color::new(255, 170, 90);
+pub const SCENE_SEED: u32 = 7;
+const LEAF_POSITION_JITTER: f32 = 0.04;
+const LEAF_SCALE_JITTER: f32 = 0.1;
+const WIND_FREQUENCY: f32 = 0.6;
+const FLUTTER_FREQUENCY: f32 = 2.5;
+const FLUTTER_AMOUNT: f32 = 0.3;
+const WIND_SEED: u32 = 11;
+
+/// Square patch of water cubes. `resolution` only sets how finely the patch is divided: cubes are
+/// `size / resolution` wide, so the covered footprint and the height of the water surface never change.
+#[derive(Debug, Clone, Copy)]
+pub struct WaterSurface {
+    /// Corner with the smallest x and z; `y` is the resting height of the top of the water.
+    pub origin: Vec3,
+    pub size: f32,
+    pub resolution: usize,
+}
+
+impl WaterSurface {
+    pub fn cube_size(&self) -> f32 {
+        self.size / self.resolution.max(1) as f32
+    }
+
+    pub fn center(&self) -> Vec3 {
+        self.origin + Vec3::new(self.size * 0.5, 0.0, self.size * 0.5)
+    }
+
+    /// Offset of the water surface from its resting height at world (x, z).
+    pub fn wave_height(&self, x: f32, z: f32, time: f32) -> f32 {
+        (time * 2.0 + (x + z) * WAVE_NUMBER).sin() * WAVE_AMPLITUDE
+    }
+
+    /// Normal of the smooth wave the cubes follow: the cube tops themselves are flat.
+    pub fn wave_normal(&self, x: f32, z: f32, time: f32) -> Vec3 {
+        let slope = (time * 2.0 + (x + z) * WAVE_NUMBER).cos() * WAVE_AMPLITUDE * WAVE_NUMBER;
+        Vec3::new(-slope, 1.0, -slope).normalize()
+    }
+
+    fn cell_center(&self, x: usize, z: usize) -> Vec3 {
+        let cube_size = self.cube_size();
+        self.origin + Vec3::new((x as f32 + 0.5) * cube_size, -0.5 * cube_size, (z as f32 + 0.5) * cube_size)
+    }
+}
+
+pub struct Beach {
+    pub static_objects: Vec<Object>,
+    pub prototypes: Vec<Cube>,
+    pub water: WaterSurface,
+    pub monitor_camera: Camera,
+    animated_objects: Vec<(Object, Animation)>,
+    palm_leaves: Range<usize>,
+    palm_base_y: f32,
+    palm_height: f32,
+    lantern: Material,
+    water_trough_color: Color,
+    water_crest_color: Color,
+}
+
+impl Beach {
+    pub fn new() -> Self {
+        let sand_color = Material::sand();
+        let wet_sand = Material::matte(Color::new(168, 136, 104)).with_roughness(0.4).with_clear_coat(0.6, 0.1);
+        let brown_trunk = Material::matte(Color::new(139, 69, 19)).with_roughness(0.9);
+        let green_leaf = Material::matte(Color::new(34, 139, 34)).with_roughness(0.6);
+        let coconut = Material::matte(Color::new(70, 45, 25)).with_roughness(0.7);
+        let trunk_edges = brown_trunk.with_edges(Color::new(70, 34, 10), 0.05);
+        let leaf_edges = green_leaf.with_edges(Color::new(16, 70, 16), 0.04);
+
+        let light_cube_material = Material::emissive(Color::new(255, 223, 0), 1.0);
+        let lantern = Material::emissive(LANTERN_COLOR, 1.0);
+
+        let trunk_start_y = 5.0;
+        let trunk_cube_size = 0.4;
+        let num_trunk_cubes = 5;
+        let leaf_start_y = trunk_start_y + num_trunk_cubes as f32 * trunk_cube_size;
+        let leaf_positions = [
+            Vec3::new(0.0, leaf_start_y, 0.0),
+            Vec3::new(0.5, leaf_start_y, 0.5),
+            Vec3::new(-0.5, leaf_start_y, 0.5),
+            Vec3::new(0.5, leaf_start_y, -0.5),
+            Vec3::new(-0.5, leaf_start_y, -0.5),
+        ];
+        let coconut_radius = 0.13;
+        let coconut_positions = [
+            Vec3::new(0.3, leaf_start_y - 0.3, 0.1),
+            Vec3::new(-0.15, leaf_start_y - 0.32, 0.28),
+        ];
+
+        let water = WaterSurface {
+            origin: Vec3::new(-0.25, 5.15, -0.25),
+            size: 3.0,
+            resolution: WATER_GRID_SIZE,
+        };
+
+        let mut builder = SceneBuilder::with_capacity(6 + num_trunk_cubes + coconut_positions.len() + leaf_positions.len() + SAND_HOUSE_CAPACITY)
+            .add_object(Object::Plane(make_beach_ground(sand_color, wet_sand, 5.0, water.center(), water.size * 0.5, 1.0), Visibility::default()))
+            .add_object(Object::Cube(Cube::new(Vec3::new(1.0, 5.2, -4.0), 0.5, light_cube_material), Visibility::light_marker()))
+            .add_object(Object::Cube(Cube::new(Vec3::new(4.5, 5.2, 2.0), 0.5, light_cube_material), Visibility::light_marker()))
+            .add_object(Object::Cube(Cube::new(LANTERN_POSITION, 0.15, lantern), Visibility::light_marker()))
+            .add_cube(Vec3::new(3.0, 5.15, -1.5), 0.3, Material::gold())
+            .add_cube(Vec3::new(4.6, 5.4, 4.2), 0.8, Material::matte(Color::new(30, 30, 34)).with_monitor(BEACH_MONITOR));
+
+        for i in 0..num_trunk_cubes {
+            builder = builder.add_cube(Vec3::new(0.0, trunk_start_y + i as f32 * trunk_cube_size, 0.0), trunk_cube_size, trunk_edges);
+        }
+        for position in coconut_positions {
+            builder = builder.add_sphere(position, coconut_radius, coconut);
+        }
+
+        let unit_cube = |material| Cube::new(Vec3::new(0.0, 0.0, 0.0), 1.0, material);
+        let prototypes = vec![unit_cube(Material::water()), unit_cube(leaf_edges), unit_cube(sand_color)];
+
+        let mut leaves: Vec<Object> = leaf_positions
+            .iter()
+            .map(|&pos| Object::Instance(Instance::new(LEAF_PROTOTYPE, Transform::new(pos, 0.5)), Visibility::default()))
+            .collect();
+        scatter(&mut leaves, &prototypes, SCENE_SEED, LEAF_POSITION_JITTER, LEAF_SCALE_JITTER);
+        let leaves_start = builder.len();
+        builder = builder.extend(leaves);
+        let palm_leaves = leaves_start..builder.len();
+
+        let objects = add_sand_house(builder.push_transform(Transform::new(Vec3::new(-4.5, 5.2, -4.0), 0.5)))
+            .pop_transform()
+            .build();
+
+        let animated_objects = vec![(
+            Object::Cube(Cube::new(Vec3::new(0.0, 5.3, 1.25), 0.3, brown_trunk), Visibility::default()),
+            Animation::new(vec![
+                Keyframe::new(0.0, Vec3::new(0.0, 0.0, 0.0)),
+                Keyframe::new(4.0, Vec3::new(2.5, 0.0, 0.0)),
+                Keyframe::new(8.0, Vec3::new(0.0, 0.0, 0.0)),
+            ])
+            .eased()
+            .looping(),
+        )];
+
+        Beach {
+            static_objects: objects,
+            prototypes,
+            water,
+            monitor_camera: Camera::new(Vec3::new(-3.0, 8.0, 3.0), Vec3::new(0.5, 5.0, 0.5), Vec3::new(0.0, 1.0, 0.0)),
+            animated_objects,
+            palm_leaves,
+            palm_base_y: trunk_start_y,
+            palm_height: leaf_start_y - trunk_start_y,
+            lantern,
+            water_trough_color: Color::new(0, 105, 190),
+            water_crest_color: Color::new(180, 235, 255),
+        }
+    }
+
+    pub fn objects_at(&self, elapsed_time: f32, wind_strength: f32) -> Vec<Object> {
+        let capacity = self.static_objects.len() + self.water.resolution * self.water.resolution + self.animated_objects.len();
+        let builder = SceneBuilder::with_capacity(capacity).extend(self.static_objects.iter().enumerate().map(|(index, object)| {
+            let mut object = object.clone();
+            if wind_strength > 0.0 && self.palm_leaves.contains(&index) {
+                let offset = self.leaf_sway(&object, index - self.palm_leaves.start, elapsed_time, wind_strength);
+                object.translate(offset);
+            }
+            object
+        }));
+        add_wave_grid(builder, self.water_trough_color, self.water_crest_color, &self.water, elapsed_time)
+            .extend(self.animated_objects.iter().map(|(object, animation)| {
+                let mut object = object.clone();
+                object.translate(animation.translation_at(elapsed_time));
+                object
+            }))
+            .build()
+    }
+
+    /// Horizontal wind offset for a palm leaf: a gust shared by the whole crown plus a faster per-leaf flutter, both
+    /// smooth noise, scaled by how high the leaf sits above the foot of the trunk. Never exceeds
+    /// `(1 + FLUTTER_AMOUNT) * wind_strength`.
+    fn leaf_sway(&self, leaf: &Object, leaf_index: usize, elapsed_time: f32, wind_strength: f32) -> Vec3 {
+        let height = match leaf.cube_extent(&self.prototypes) {
+            Some((center, _)) => saturate((center.y - self.palm_base_y) / self.palm_height),
+            None => return Vec3::zeros(),
+        };
+        let flutter_seed = WIND_SEED + 2 + 2 * leaf_index as u32;
+        let gust = |axis: u32| {
+            value_noise(elapsed_time * WIND_FREQUENCY, WIND_SEED + axis)
+                + FLUTTER_AMOUNT * value_noise(elapsed_time * FLUTTER_FREQUENCY, flutter_seed + axis)
+        };
+        Vec3::new(gust(0), 0.0, gust(1)) * (wind_strength * height)
+    }
+
+    pub fn lights(&self, sun_position: Vec3, sun_color: Color) -> Vec<Light> {
+        vec![
+            Light::white(Vec3::new(1.0, 5.2, -4.0)),
+            Light::white(Vec3::new(4.5, 5.2, 2.0)),
+            Light::new(sun_position, sun_color),
+            Light::from_emissive(LANTERN_POSITION, &self.lantern),
+        ]
+    }
+}
+
+impl Default for Beach {
+    fn default() -> Self {
+        Beach::new()
+    }
+}
+
+fn add_wave_grid(
+    builder: SceneBuilder,
+    trough_color: Color,
+    crest_color: Color,
+    water: &WaterSurface,
+    elapsed_time: f32
+) -> SceneBuilder {
+    let cube_size = water.cube_size();
+    builder.add_grid(water.resolution, water.resolution, |x, z| {
+        let cell = water.cell_center(x, z);
+        let wave_height = water.wave_height(cell.x, cell.z, elapsed_time);
+        let crest_factor = 0.5 * (wave_height / WAVE_AMPLITUDE + 1.0);
+        let center = cell + Vec3::new(0.0, wave_height, 0.0);
+        Some(Object::Instance(
+            Instance::new(WATER_PROTOTYPE, Transform::new(center, cube_size)).with_diffuse(trough_color.lerp(crest_color, crest_factor)),
+            Visibility::default(),
+        ))
+    })
+}
+
+fn make_beach_ground(
+    sand_material: Material,
+    wet_sand_material: Material,
+    ground_height: f32,
+    water_center: Vec3,
+    water_half_extent: f32,
+    wet_width: f32,
+) -> Plane {
+    Plane::new(Vec3::new(0.0, ground_height, 0.0), Vec3::new(0.0, 1.0, 0.0), sand_material)
+        .with_shore(Shore {
+            wet_material: wet_sand_material,
+            water_center,
+            water_half_extent,
+            wet_width,
+        })
+}
+
+fn add_sand_house(mut builder: SceneBuilder) -> SceneBuilder {
+    let house_width = SAND_HOUSE_SIZE.0;
+    let house_height = SAND_HOUSE_SIZE.1;
+    let house_depth = SAND_HOUSE_SIZE.2;
+    let cube_at = |x: usize, y: usize, z: usize| Vec3::new(x as f32, y as f32, z as f32);
+
+    for y in 0..house_height {
+        builder = builder.add_grid(house_width, house_depth, |x, z| {
+            let is_door = x == 2 && z == 0 && y < 2;
+            let is_window = y == 1 && (x == 1 || x == 3) && (z == 0 || z == house_depth - 1);
+
+            if is_door || is_window {
+                None
+            } else {
+                Some(Object::Instance(Instance::new(SAND_PROTOTYPE, Transform::translation(cube_at(x, y, z))), Visibility::default()))
+            }
+        });
+    }
+
+    builder.add_grid(house_width, house_depth, |x, z| {
+        Some(Object::Instance(Instance::new(SAND_PROTOTYPE, Transform::translation(cube_at(x, house_height, z))), Visibility::default()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_translated_house_is_offset_by_the_translation() {
+        let prototypes = vec![Cube::new(Vec3::zeros(), 1.0, Material::sand()); SAND_PROTOTYPE + 1];
+        let offset = Vec3::new(3.0, -1.5, 7.25);
+        let local = add_sand_house(SceneBuilder::with_capacity(SAND_HOUSE_CAPACITY)).build();
+        let placed = add_sand_house(SceneBuilder::with_capacity(SAND_HOUSE_CAPACITY).push_transform(Transform::translation(offset))).build();
+
+        assert_eq!(local.len(), placed.len());
+        for (local, placed) in local.iter().zip(&placed) {
+            let (local_center, local_half) = local.cube_extent(&prototypes).unwrap();
+            let (placed_center, placed_half) = placed.cube_extent(&prototypes).unwrap();
+            assert!((placed_center - local_center - offset).magnitude() < 1e-5);
+            assert_eq!(placed_half, local_half);
+        }
+    }
+
+    #[test]
+    fn water_covers_the_same_area_at_every_resolution() {
+        for resolution in [1, 3, 6, 12, 25] {
+            let water = WaterSurface { origin: Vec3::new(-3.0, 0.5, 2.0), size: 6.0, resolution };
+            let half = water.cube_size() * 0.5;
+            let first = water.cell_center(0, 0);
+            let last = water.cell_center(resolution - 1, resolution - 1);
+            assert!((first.x - half - water.origin.x).abs() < 1e-4 && (first.z - half - water.origin.z).abs() < 1e-4);
+            assert!((last.x + half - (water.origin.x + water.size)).abs() < 1e-4);
+            assert!((last.z + half - (water.origin.z + water.size)).abs() < 1e-4);
+            let area = (water.cube_size() * resolution as f32).powi(2);
+            assert!((area - 36.0).abs() < 1e-3, "resolution {} covers {}", resolution, area);
+            assert!((first.y + half - water.origin.y).abs() < 1e-5, "cube tops should rest at the water height");
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+use crate::math::mix;
+use crate::render_settings::RenderSettings;
+
+const AVERAGE_WEIGHT: f32 = 0.2;
+const HEADROOM: f32 = 0.6;
+const SETTLE_FRAMES: u32 = 5;
+pub const DEFAULT_FRAME_BUDGET_MS: f32 = 50.0;
+
+/// Sample counts for one rung of the quality ladder, cheapest first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quality {
+    pub shadow_samples: usize,
+    pub glossy_samples: usize,
+    pub skylight_samples: usize,
+}
+
+pub const QUALITY_LEVELS: [Quality; 5] = [
+    Quality { shadow_samples: 1, glossy_samples: 1, skylight_samples: 0 },
+    Quality { shadow_samples: 1, glossy_samples: 8, skylight_samples: 0 },
+    Quality { shadow_samples: 4, glossy_samples: 8, skylight_samples: 8 },
+    Quality { shadow_samples: 16, glossy_samples: 8, skylight_samples: 8 },
+    Quality { shadow_samples: 16, glossy_samples: 16, skylight_samples: 32 },
+];
+
+/// Closed-loop controller that walks `QUALITY_LEVELS` one rung at a time to keep the averaged frame time between
+/// `HEADROOM * target` and `target`.
+pub struct FrameBudget {
+    level: usize,
+    average_ms: Option<f32>,
+    settle: u32,
+}
+
+impl FrameBudget {
+    pub fn new() -> Self {
+        FrameBudget {
+            level: 1,
+            average_ms: None,
+            settle: 0,
+        }
+    }
+
+    /// Folds `frame_time` into the running average, steps the level if it left the target band and writes the
+    /// level's sample counts into `settings`. Does nothing while `settings.frame_budget_ms` is zero.
+    pub fn update(&mut self, settings: &mut RenderSettings, frame_time: Duration) {
+        if settings.frame_budget_ms <= 0.0 {
+            self.average_ms = None;
+            return;
+        }
+
+        let frame_ms = frame_time.as_secs_f32() * 1000.0;
+        let average = match self.average_ms {
+            Some(average) => mix(average, frame_ms, AVERAGE_WEIGHT),
+            None => frame_ms,
+        };
+        self.average_ms = Some(average);
+
+        let max_level = settings.budget_max_level.min(QUALITY_LEVELS.len() - 1);
+        let min_level = settings.budget_min_level.min(max_level);
+        let mut level = self.level.clamp(min_level, max_level);
+        if self.settle > 0 {
+            self.settle -= 1;
+        } else if average > settings.frame_budget_ms && level > min_level {
+            level -= 1;
+        } else if average < settings.frame_budget_ms * HEADROOM && level < max_level {
+            level += 1;
+        }
+        if level != self.level {
+            self.level = level;
+            self.settle = SETTLE_FRAMES;
+        }
+
+        let quality = QUALITY_LEVELS[self.level];
+        settings.shadow_samples = quality.shadow_samples;
+        settings.glossy_samples = quality.glossy_samples;
+        settings.skylight_samples = quality.skylight_samples;
+    }
+}
+
+impl Default for FrameBudget {
+    fn default() -> Self {
+        FrameBudget::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulate(settings: &mut RenderSettings, frames: usize) -> Vec<usize> {
+        let cost_ms = [10.0, 20.0, 40.0, 80.0, 160.0];
+        let mut budget = FrameBudget::new();
+        (0..frames)
+            .map(|_| {
+                let frame_time = Duration::from_secs_f32(cost_ms[budget.level] / 1000.0);
+                budget.update(settings, frame_time);
+                budget.level
+            })
+            .collect()
+    }
+
+    #[test]
+    fn controller_settles_inside_the_target_band() {
+        let mut settings = RenderSettings { frame_budget_ms: 50.0, ..RenderSettings::default() };
+        let levels = simulate(&mut settings, 200);
+        assert!(levels.windows(2).all(|pair| pair[0].abs_diff(pair[1]) <= 1), "levels jumped: {:?}", levels);
+        assert!(levels[150..].iter().all(|&level| level == 2), "did not settle: {:?}", &levels[150..]);
+        assert_eq!(settings.shadow_samples, QUALITY_LEVELS[2].shadow_samples);
+    }
+
+    #[test]
+    fn controller_respects_the_quality_limits() {
+        let mut settings = RenderSettings { frame_budget_ms: 1000.0, budget_max_level: 1, ..RenderSettings::default() };
+        assert!(simulate(&mut settings, 100).iter().all(|&level| level <= 1));
+
+        let mut settings = RenderSettings { frame_budget_ms: 1.0, budget_min_level: 1, ..RenderSettings::default() };
+        assert!(simulate(&mut settings, 100).iter().all(|&level| level >= 1));
+    }
+}
@@ -1,11 +1,21 @@
-use nalgebra_glm::Vec3;
+use nalgebra_glm::{rotate_vec3, Vec3};
 use std::f32::consts::PI;
+use crate::noise::value_noise;
 
+const SHAKE_FREQUENCY: f32 = 3.0;
+pub const FIELD_OF_VIEW: f32 = PI / 3.0;
+
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub eye: Vec3,
     pub center: Vec3,
     pub up: Vec3,
     pub speed: f32, 
+    /// Horizontal magnification of the image; 2.0 draws everything twice as wide, for anamorphic output or display
+    /// pixels half as wide as they are tall.
+    pub pixel_aspect: f32,
+    /// Vertical field of view in radians.
+    pub fov: f32,
 }
 
 impl Camera {
@@ -15,22 +25,99 @@ impl Camera {
             center,
             up,
             speed: 0.1, 
+            pixel_aspect: 1.0,
+            fov: FIELD_OF_VIEW,
         }
     }
 
-    pub fn base_change(&self, vector: &Vec3) -> Vec3 {
-        let forward = (self.center - self.eye).normalize();
+    pub fn shaken(&self, amplitude: f32, time: f32) -> Camera {
+        if amplitude <= 0.0 {
+            return self.clone();
+        }
+        let t = time * SHAKE_FREQUENCY;
+        let offset = |seed: u32| Vec3::new(value_noise(t, seed), value_noise(t, seed + 1), value_noise(t, seed + 2)) * amplitude;
+        Camera {
+            eye: self.eye + offset(0),
+            center: self.center + offset(3),
+            ..self.clone()
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        (self.center - self.eye).normalize()
+    }
+
+    pub fn look_at(&mut self, target: Vec3) {
+        if (target - self.eye).magnitude() > f32::EPSILON {
+            self.center = target;
+        }
+    }
+
+    fn basis(&self) -> (Vec3, Vec3, Vec3) {
+        let forward = self.forward();
         let right = forward.cross(&self.up).normalize();
         let up = right.cross(&forward).normalize();
+        (forward, right, up)
+    }
+
+    pub fn base_change(&self, vector: &Vec3) -> Vec3 {
+        let (forward, right, up) = self.basis();
 
         let rotated = vector.x * right + vector.y * up - vector.z * forward;
 
         rotated.normalize()
     }
 
+    /// Camera-space direction through pixel (x, y) of a `width` x `height` image, before `base_change`.
+    pub fn pixel_direction(&self, x: f32, y: f32, width: f32, height: f32, lens_scale: f32) -> Vec3 {
+        let perspective_scale = (self.fov * 0.5).tan();
+        let screen_x = ((2.0 * x) / width - 1.0) * (width / height) * perspective_scale / self.pixel_aspect;
+        let screen_y = (-(2.0 * y) / height + 1.0) * perspective_scale;
+        Vec3::new(screen_x * lens_scale, screen_y * lens_scale, -1.0).normalize()
+    }
+
+    /// Inverse of `pixel_direction` followed by `base_change`: the pixel a world point lands on, if it is in front
+    /// of the camera.
+    pub fn project(&self, point: &Vec3, width: f32, height: f32) -> Option<(f32, f32)> {
+        let (forward, right, up) = self.basis();
+        let local = point - self.eye;
+        let depth = local.dot(&forward);
+        if depth <= f32::EPSILON {
+            return None;
+        }
+
+        let perspective_scale = (self.fov * 0.5).tan();
+        let screen_x = local.dot(&right) / depth / ((width / height) * perspective_scale / self.pixel_aspect);
+        let screen_y = local.dot(&up) / depth / perspective_scale;
+        Some(((screen_x + 1.0) * width * 0.5, (1.0 - screen_y) * height * 0.5))
+    }
+
+    /// Looks at the center of `bounds` from far enough back along the current view direction that its bounding
+    /// sphere fits inside the vertical field of view.
+    pub fn frame(&mut self, bounds: (Vec3, Vec3)) {
+        let (min, max) = bounds;
+        let center = (min + max) * 0.5;
+        let radius = ((max - min) * 0.5).magnitude().max(f32::EPSILON);
+        let distance = radius / (self.fov * 0.5).sin();
+
+        let forward = self.forward();
+        let forward = if forward.iter().all(|c| c.is_finite()) { forward } else { Vec3::new(0.0, 0.0, -1.0) };
+        self.eye = center - forward * distance;
+        self.center = center;
+    }
+
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
-        let radius_vector = self.eye - self.center;
+        self.orbit_around(self.center, delta_yaw, delta_pitch);
+    }
+
+    /// Swings `eye` around `pivot` at a constant distance and turns `center` with it by the same rotation, so
+    /// whatever was in view relative to the pivot stays in view.
+    pub fn orbit_around(&mut self, pivot: Vec3, delta_yaw: f32, delta_pitch: f32) {
+        let radius_vector = self.eye - pivot;
         let radius = radius_vector.magnitude();
+        if radius <= f32::EPSILON {
+            return;
+        }
 
         let current_yaw = radius_vector.z.atan2(radius_vector.x);
         let radius_xz = (radius_vector.x * radius_vector.x + radius_vector.z * radius_vector.z).sqrt();
@@ -39,17 +126,20 @@ impl Camera {
         let new_yaw = (current_yaw + delta_yaw) % (2.0 * PI);
         let new_pitch = (current_pitch + delta_pitch).clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
 
-        let new_eye = self.center + Vec3::new(
+        let new_eye = pivot + Vec3::new(
             radius * new_yaw.cos() * new_pitch.cos(),
             -radius * new_pitch.sin(),
             radius * new_yaw.sin() * new_pitch.cos()
         );
 
+        let pitch_axis = Vec3::new(-current_yaw.sin(), 0.0, current_yaw.cos());
+        let pitched = rotate_vec3(&(self.center - pivot), current_pitch - new_pitch, &pitch_axis);
+        self.center = pivot + rotate_vec3(&pitched, -delta_yaw, &Vec3::new(0.0, 1.0, 0.0));
         self.eye = new_eye;
     }
 
     pub fn move_camera(&mut self, direction: &str) {
-        let forward = (self.center - self.eye).normalize();
+        let forward = self.forward();
         let right = forward.cross(&self.up).normalize();
 
         match direction {
@@ -73,3 +163,113 @@ impl Camera {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::environment::{EnvMap, Environment, SkyGradient};
+    use crate::material::Material;
+    use crate::object::{Object, Visibility};
+    use crate::scene::Scene;
+
+    fn camera() -> Camera {
+        Camera::new(Vec3::new(0.0, 2.0, 10.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0))
+    }
+
+    #[test]
+    fn look_at_points_forward_at_the_target() {
+        let mut camera = camera();
+        let target = Vec3::new(3.0, -1.0, 2.0);
+        camera.look_at(target);
+        assert!((camera.forward() - (target - camera.eye).normalize()).magnitude() < 1e-6);
+        assert_eq!(camera.up, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn look_at_the_eye_keeps_the_old_target() {
+        let mut camera = camera();
+        let forward = camera.forward();
+        camera.look_at(camera.eye);
+        assert_eq!(camera.forward(), forward);
+    }
+
+    #[test]
+    fn shake_is_a_bounded_offset_from_the_logical_camera() {
+        let camera = camera();
+        let still = camera.shaken(0.0, 3.7);
+        assert_eq!((still.eye, still.center), (camera.eye, camera.center));
+
+        let amplitude = 0.05;
+        let bound = amplitude * 3.0_f32.sqrt() + 1e-6;
+        for frame in 0..600 {
+            let shaken = camera.shaken(amplitude, frame as f32 / 60.0);
+            assert!((shaken.eye - camera.eye).magnitude() <= bound);
+            assert!((shaken.center - camera.center).magnitude() <= bound);
+        }
+    }
+
+    #[test]
+    fn framing_keeps_every_bounds_corner_in_view() {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(-3.0, 0.0, 1.0), 2.0, Material::sand()), Visibility::default()));
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(4.0, 2.5, -6.0), 1.0, Material::sand()), Visibility::default()));
+        let (min, max) = scene.bounding_box();
+        assert_eq!((min, max), (Vec3::new(-4.0, -1.0, -6.5), Vec3::new(4.5, 3.0, 2.0)));
+
+        for fov in [0.5, FIELD_OF_VIEW, 1.6] {
+            let mut camera = Camera { fov, ..camera() };
+            camera.frame((min, max));
+            for corner in 0..8 {
+                let point = Vec3::new(
+                    if corner & 1 == 0 { min.x } else { max.x },
+                    if corner & 2 == 0 { min.y } else { max.y },
+                    if corner & 4 == 0 { min.z } else { max.z },
+                );
+                let (x, y) = camera.project(&point, 800.0, 600.0).expect("corner behind the camera");
+                assert!((0.0..=800.0).contains(&x) && (0.0..=600.0).contains(&y), "fov {}: corner {:?} at ({}, {})", fov, point, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn double_pixel_aspect_doubles_horizontal_extent() {
+        let (width, height) = (640.0, 480.0);
+        let extent = |pixel_aspect: f32| {
+            let camera = Camera { pixel_aspect, ..camera() };
+            let (left, _) = camera.project(&Vec3::new(-0.5, 0.0, 0.0), width, height).unwrap();
+            let (right, _) = camera.project(&Vec3::new(0.5, 0.0, 0.0), width, height).unwrap();
+            let (_, top) = camera.project(&Vec3::new(0.0, 0.5, 0.0), width, height).unwrap();
+            let (_, bottom) = camera.project(&Vec3::new(0.0, -0.5, 0.0), width, height).unwrap();
+            (right - left, bottom - top)
+        };
+        let (narrow, narrow_height) = extent(1.0);
+        let (wide, wide_height) = extent(2.0);
+        assert!((wide - 2.0 * narrow).abs() < 1e-3, "{} vs {}", wide, narrow);
+        assert!((wide_height - narrow_height).abs() < 1e-3);
+    }
+
+    #[test]
+    fn project_inverts_pixel_direction() {
+        let camera = Camera { pixel_aspect: 2.0, ..camera() };
+        for (x, y) in [(10.0, 20.0), (320.0, 240.0), (600.0, 400.0)] {
+            let direction = camera.base_change(&camera.pixel_direction(x, y, 640.0, 480.0, 1.0));
+            let (px, py) = camera.project(&(camera.eye + direction * 7.0), 640.0, 480.0).unwrap();
+            assert!((px - x).abs() < 1e-2 && (py - y).abs() < 1e-2, "({}, {}) came back as ({}, {})", x, y, px, py);
+        }
+    }
+
+    #[test]
+    fn orbiting_a_pivot_keeps_its_distance_and_keeps_it_in_view() {
+        let pivot = Vec3::new(1.0, 0.5, -2.0);
+        let mut camera = camera();
+        camera.look_at(pivot);
+        let distance = (camera.eye - pivot).magnitude();
+        for _ in 0..40 {
+            camera.orbit_around(pivot, 0.3, 0.05);
+            assert!(((camera.eye - pivot).magnitude() - distance).abs() < 1e-3);
+            let (x, y) = camera.project(&pivot, 640.0, 480.0).expect("pivot went behind the camera");
+            assert!((x - 320.0).abs() < 1.0 && (y - 240.0).abs() < 1.0, "pivot drifted to ({}, {})", x, y);
+        }
+    }
+}
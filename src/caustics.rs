@@ -0,0 +1,99 @@
+use nalgebra_glm::Vec3;
+use crate::beach::WaterSurface;
+use crate::optics::refract;
+
+const RAYS_PER_CELL: usize = 4;
+
+/// Sunlight refracted through the water's wave surface and gathered on a sea floor `depth` below it, as energy
+/// density relative to a flat surface: 1.0 is undisturbed light, more is focused, less is spread thin.
+pub struct CausticsMap {
+    origin: Vec3,
+    size: f32,
+    resolution: usize,
+    density: Vec<f32>,
+}
+
+impl CausticsMap {
+    /// Shoots `RAYS_PER_CELL` x `RAYS_PER_CELL` rays per map cell from `to_sun` through the waves at `time`. Each
+    /// landing point is measured against where the same ray lands through a flat surface, so only the focusing
+    /// of the waves shows, not the sideways shift of the whole pattern under a low sun.
+    pub fn trace(water: &WaterSurface, time: f32, to_sun: Vec3, depth: f32, refractive_index: f32, resolution: usize) -> Self {
+        let mut map = CausticsMap {
+            origin: water.origin,
+            size: water.size,
+            resolution,
+            density: vec![0.0; resolution * resolution],
+        };
+        let incident = -to_sun.normalize();
+        let eta = 1.0 / refractive_index;
+        let flat = match refract(&incident, &Vec3::new(0.0, 1.0, 0.0), eta) {
+            Some(direction) => direction * (depth / -direction.y),
+            None => return map,
+        };
+
+        let rays_per_axis = resolution * RAYS_PER_CELL;
+        let spacing = water.size / rays_per_axis as f32;
+        for i in 0..rays_per_axis {
+            for j in 0..rays_per_axis {
+                let x = water.origin.x + (i as f32 + 0.5) * spacing;
+                let z = water.origin.z + (j as f32 + 0.5) * spacing;
+                let height = water.wave_height(x, z, time);
+                let direction = match refract(&incident, &water.wave_normal(x, z, time), eta) {
+                    Some(direction) if direction.y < 0.0 => direction,
+                    _ => continue,
+                };
+                let offset = direction * ((depth + height) / -direction.y) - flat;
+                if let Some(cell) = map.cell(x + offset.x, z + offset.z) {
+                    map.density[cell] += 1.0;
+                }
+            }
+        }
+
+        let rays_per_cell = (RAYS_PER_CELL * RAYS_PER_CELL) as f32;
+        map.density.iter_mut().for_each(|density| *density /= rays_per_cell);
+        map
+    }
+
+    fn cell(&self, x: f32, z: f32) -> Option<usize> {
+        let u = (x - self.origin.x) / self.size;
+        let v = (z - self.origin.z) / self.size;
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return None;
+        }
+        let column = (u * self.resolution as f32) as usize;
+        let row = (v * self.resolution as f32) as usize;
+        Some(row.min(self.resolution - 1) * self.resolution + column.min(self.resolution - 1))
+    }
+
+    /// Density under `point`, looked up by its x and z; `None` outside the water's footprint.
+    pub fn density(&self, point: &Vec3) -> Option<f32> {
+        self.cell(point.x, point.z).map(|cell| self.density[cell])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn water() -> WaterSurface {
+        WaterSurface { origin: Vec3::new(0.0, 0.0, 0.0), size: 12.0, resolution: 12 }
+    }
+
+    #[test]
+    fn waves_focus_sunlight_into_bright_spots() {
+        let map = CausticsMap::trace(&water(), 0.4, Vec3::new(0.2, 1.0, 0.1), 3.0, 1.33, 48);
+        let max = map.density.iter().cloned().fold(0.0, f32::max);
+        let min = map.density.iter().cloned().fold(f32::INFINITY, f32::min);
+        let mean = map.density.iter().sum::<f32>() / map.density.len() as f32;
+        assert!(max > 1.3, "brightest cell only {}", max);
+        assert!(min < 0.7, "darkest cell still {}", min);
+        assert!((mean - 1.0).abs() < 0.15, "refraction should move light around, not create it: mean {}", mean);
+    }
+
+    #[test]
+    fn a_flat_sea_stays_uniform() {
+        let flat = CausticsMap::trace(&water(), 0.4, Vec3::new(0.2, 1.0, 0.1), 0.0, 1.0, 12);
+        assert!(flat.density.iter().all(|&density| (density - 1.0).abs() < 0.5));
+        assert_eq!(flat.density(&Vec3::new(-1.0, 0.0, 5.0)), None);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::timer::Instant;
+
+pub const DEFAULT_RECORDING_FPS: f32 = 30.0;
+
+/// Source of animation time: wall-clock for interactive use, or a fixed `frame / fps` step for recordings so
+/// every frame advances the scene by exactly `1 / fps` however long it took to render.
+pub enum Clock {
+    RealTime { start: Instant },
+    Fixed { frame: u64, fps: f32 },
+}
+
+impl Clock {
+    pub fn real_time() -> Self {
+        Clock::RealTime { start: Instant::now() }
+    }
+
+    pub fn fixed(fps: f32) -> Self {
+        Clock::Fixed { frame: 0, fps }
+    }
+
+    /// Animation time in seconds for the current frame.
+    pub fn now(&self) -> f32 {
+        match self {
+            Clock::RealTime { start } => start.elapsed().as_secs_f32(),
+            Clock::Fixed { frame, fps } => *frame as f32 / fps,
+        }
+    }
+
+    /// Animation time covered by one frame: the real time the last frame took, or exactly `1 / fps`.
+    pub fn frame_duration(&self, real_frame_time: f32) -> f32 {
+        match self {
+            Clock::RealTime { .. } => real_frame_time,
+            Clock::Fixed { fps, .. } => 1.0 / fps,
+        }
+    }
+
+    pub fn advance(&mut self) {
+        if let Clock::Fixed { frame, .. } = self {
+            *frame += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_clock_advances_exactly_one_frame_per_step() {
+        let mut clock = Clock::fixed(24.0);
+        for frame in 0..240 {
+            assert_eq!(clock.now(), frame as f32 / 24.0);
+            assert_eq!(clock.frame_duration(0.5), 1.0 / 24.0);
+            std::thread::sleep(std::time::Duration::from_micros(if frame % 40 == 0 { 2_000 } else { 0 }));
+            clock.advance();
+        }
+        assert!((clock.now() - 10.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn real_time_clock_reports_the_measured_frame_time() {
+        let mut clock = Clock::real_time();
+        clock.advance();
+        assert_eq!(clock.frame_duration(0.125), 0.125);
+        assert!(clock.now() >= 0.0);
+    }
+}
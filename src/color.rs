@@ -1,75 +0,0 @@
-use std::fmt;
-
-#[derive(Debug, Clone, Copy)]
-pub struct Color {
-    r: u8,
-    g: u8,
-    b: u8,
-}
-
-impl Color {
-    pub const fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
-    }
-
-    pub const fn from_hex(hex: u32) -> Self {
-        let r = ((hex >> 16) & 0xFF) as u8;
-        let g = ((hex >> 8) & 0xFF) as u8;
-        let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
-    }
-
-    pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
-    }
-
-    pub fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
-    }
-
-    pub fn red(&self) -> u8 {
-        self.r
-    }
-
-    pub fn green(&self) -> u8 {
-        self.g
-    }
-
-    pub fn blue(&self) -> u8 {
-        self.b
-    }
-}
-
-use std::ops::Add;
-
-impl Add for Color {
-    type Output = Color;
-
-    fn add(self, other: Color) -> Color {
-        Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
-        }
-    }
-}
-
-use std::ops::Mul;
-
-impl Mul<f32> for Color {
-    type Output = Color;
-
-    fn mul(self, scalar: f32) -> Color {
-        Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
-        }
-    }
-}
-
-impl fmt::Display for Color {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
-    }
-}
@@ -0,0 +1,91 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputColorSpace {
+    /// Piecewise sRGB transfer curve; linear 0.5 encodes to about 0.735.
+    Srgb,
+    /// No transfer curve; linear 0.5 stays 0.5.
+    Linear,
+    /// Pure 1/2.2 power; linear 0.5 encodes to about 0.730.
+    Gamma22,
+    /// Rec.709 camera OETF; linear 0.5 encodes to about 0.706.
+    Rec709,
+}
+
+impl OutputColorSpace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Srgb" => Some(OutputColorSpace::Srgb),
+            "Linear" => Some(OutputColorSpace::Linear),
+            "Gamma22" => Some(OutputColorSpace::Gamma22),
+            "Rec709" => Some(OutputColorSpace::Rec709),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            OutputColorSpace::Srgb => OutputColorSpace::Linear,
+            OutputColorSpace::Linear => OutputColorSpace::Gamma22,
+            OutputColorSpace::Gamma22 => OutputColorSpace::Rec709,
+            OutputColorSpace::Rec709 => OutputColorSpace::Srgb,
+        }
+    }
+
+    pub fn encode(self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        [self.encode_channel(r), self.encode_channel(g), self.encode_channel(b)]
+    }
+
+    fn encode_channel(self, x: f32) -> f32 {
+        let x = x.max(0.0);
+        match self {
+            OutputColorSpace::Srgb => {
+                if x <= 0.003_130_8 {
+                    12.92 * x
+                } else {
+                    1.055 * x.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            OutputColorSpace::Linear => x,
+            OutputColorSpace::Gamma22 => x.powf(1.0 / 2.2),
+            OutputColorSpace::Rec709 => {
+                if x < 0.018 {
+                    4.5 * x
+                } else {
+                    1.099 * x.powf(0.45) - 0.099
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mid_gray_encodes_to_the_documented_values() {
+        for (space, expected) in [
+            (OutputColorSpace::Srgb, 0.735),
+            (OutputColorSpace::Linear, 0.5),
+            (OutputColorSpace::Gamma22, 0.730),
+            (OutputColorSpace::Rec709, 0.706),
+        ] {
+            let [r, g, b] = space.encode([0.5; 3]);
+            assert!((r - expected).abs() < 5e-4, "{:?} encoded mid-gray as {}", space, r);
+            assert_eq!((r, g), (g, b));
+        }
+    }
+
+    #[test]
+    fn curves_fix_black_and_white_and_join_their_linear_toes() {
+        let spaces = [OutputColorSpace::Srgb, OutputColorSpace::Linear, OutputColorSpace::Gamma22, OutputColorSpace::Rec709];
+        for space in spaces {
+            assert_eq!(space.encode([0.0, -1.0, 0.0]), [0.0; 3]);
+            assert!((space.encode([1.0; 3])[0] - 1.0).abs() < 1e-3, "{:?}", space);
+        }
+        for (space, knee) in [(OutputColorSpace::Srgb, 0.003_130_8), (OutputColorSpace::Rec709, 0.018)] {
+            let below = space.encode([knee - 1e-6; 3])[0];
+            let above = space.encode([knee + 1e-6; 3])[0];
+            assert!((above - below).abs() < 1e-3, "{:?} jumps at its knee", space);
+        }
+    }
+}
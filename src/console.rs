@@ -0,0 +1,191 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use minifb::{InputCallback, Key, KeyRepeat, Window};
+use proyecto3gc::app_state::AppState;
+use proyecto3gc::color::Color;
+use proyecto3gc::font::GLYPH_HEIGHT;
+use proyecto3gc::framebuffer::Framebuffer;
+use proyecto3gc::json::Json;
+
+pub const TOGGLE_KEY: Key = Key::Backquote;
+const OUTPUT_LINES: usize = 4;
+const LINE_HEIGHT: usize = GLYPH_HEIGHT + 2;
+const MARGIN: usize = 4;
+const TEXT_COLOR: u32 = 0xFFFFFF;
+const BACKGROUND_DIMMING: f32 = 0.3;
+
+/// A line typed into the console.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// `set <name> <value>`: any setting stored in the app state, plus `samples` for the antialiasing sample count
+    /// and `fov` in degrees. The value is read as JSON, falling back to a bare string for enum names.
+    Set(String, String),
+    /// `save <path>`: writes the current frame as a PNG.
+    Save(String),
+    /// `load <path>`: restores an app state file.
+    Load(String),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["set", name, ref value @ ..] if !value.is_empty() => Ok(Command::Set(name.to_string(), value.join(" "))),
+            ["save", path] => Ok(Command::Save(path.to_string())),
+            ["load", path] => Ok(Command::Load(path.to_string())),
+            ["set", ..] => Err("usage: set <name> <value>".to_string()),
+            [command @ ("save" | "load"), ..] => Err(format!("usage: {} <path>", command)),
+            [] => Err("empty command".to_string()),
+            [command, ..] => Err(format!("unknown command: {}", command)),
+        }
+    }
+
+    /// Applies the command and returns a line describing what it did.
+    pub fn execute(&self, state: &mut AppState, framebuffer: &Framebuffer) -> Result<String, String> {
+        match self {
+            Command::Set(name, value) => {
+                set(state, name, value)?;
+                Ok(format!("{} = {}", name, value))
+            }
+            Command::Save(path) => {
+                framebuffer.save_png(path).map_err(|err| err.to_string())?;
+                Ok(format!("saved {}", path))
+            }
+            Command::Load(path) => {
+                state.load(path)?;
+                Ok(format!("loaded {}", path))
+            }
+        }
+    }
+}
+
+fn set(state: &mut AppState, name: &str, value: &str) -> Result<(), String> {
+    let json = Json::parse(value).unwrap_or_else(|_| Json::String(value.to_string()));
+    match name {
+        "fov" => {
+            let degrees = json.as_f32().filter(|degrees| *degrees > 0.0 && *degrees < 180.0).ok_or("fov must be in (0, 180) degrees")?;
+            state.camera.fov = degrees.to_radians();
+            Ok(())
+        }
+        "samples" => set(state, "aa_samples", value),
+        _ => {
+            let known = state.to_json().get("settings").and_then(|settings| settings.get(name)).is_some();
+            if !known {
+                return Err(format!("unknown setting: {}", name));
+            }
+            state.apply_json(&Json::Object(vec![("settings".to_string(), Json::Object(vec![(name.to_string(), json)]))]))
+        }
+    }
+}
+
+/// Collects the characters minifb reports while the window has focus.
+struct TextInput(Rc<RefCell<String>>);
+
+impl InputCallback for TextInput {
+    fn add_char(&mut self, uni_char: u32) {
+        if let Some(c) = char::from_u32(uni_char).filter(|c| !c.is_control()) {
+            self.0.borrow_mut().push(c);
+        }
+    }
+}
+
+/// Single-line command prompt drawn over the bottom of the frame, with the last few results above it.
+pub struct Console {
+    pub open: bool,
+    typed: Rc<RefCell<String>>,
+    input: String,
+    output: Vec<String>,
+}
+
+impl Console {
+    pub fn attach(window: &mut Window) -> Self {
+        let typed = Rc::new(RefCell::new(String::new()));
+        window.set_input_callback(Box::new(TextInput(Rc::clone(&typed))));
+        Console { open: false, typed, input: String::new(), output: Vec::new() }
+    }
+
+    /// Handles this frame's keys and returns the line submitted with Enter, if any.
+    pub fn update(&mut self, window: &Window) -> Option<String> {
+        let typed: String = self.typed.borrow_mut().drain(..).collect();
+        if window.is_key_pressed(TOGGLE_KEY, KeyRepeat::No) {
+            self.open = !self.open;
+            return None;
+        }
+        if !self.open {
+            return None;
+        }
+        self.input.extend(typed.chars().filter(|&c| c != '`'));
+        if window.is_key_pressed(Key::Backspace, KeyRepeat::Yes) {
+            self.input.pop();
+        }
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) && !self.input.trim().is_empty() {
+            let line = std::mem::take(&mut self.input);
+            self.print(format!("> {}", line));
+            return Some(line);
+        }
+        None
+    }
+
+    pub fn print(&mut self, line: String) {
+        self.output.push(line);
+        if self.output.len() > OUTPUT_LINES {
+            self.output.remove(0);
+        }
+    }
+
+    pub fn draw(&self, framebuffer: &mut Framebuffer) {
+        let lines = self.output.len() + 1;
+        let top = framebuffer.height.saturating_sub(lines * LINE_HEIGHT + 2 * MARGIN);
+        for y in top..framebuffer.height {
+            for x in 0..framebuffer.width {
+                if let Some(pixel) = framebuffer.get_pixel(x, y) {
+                    framebuffer.set_pixel(x, y, (Color::from_hex(pixel) * BACKGROUND_DIMMING).to_hex());
+                }
+            }
+        }
+        let text = format!("{}\n> {}_", self.output.join("\n"), self.input);
+        let text = text.trim_start_matches('\n');
+        framebuffer.draw_text(MARGIN, top + MARGIN, text, TEXT_COLOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+    use proyecto3gc::camera::Camera;
+    use proyecto3gc::tone_map::ToneMap;
+
+    #[test]
+    fn parses_each_command_and_reports_bad_lines() {
+        let set = |name: &str, value: &str| Ok(Command::Set(name.to_string(), value.to_string()));
+        assert_eq!(Command::parse("set samples 4"), set("samples", "4"));
+        assert_eq!(Command::parse("  set   fov 60 "), set("fov", "60"));
+        assert_eq!(Command::parse("set light_color [1, 0.5, 0]"), set("light_color", "[1, 0.5, 0]"));
+        assert_eq!(Command::parse("save out.png"), Ok(Command::Save("out.png".to_string())));
+        assert_eq!(Command::parse("load scene2.json"), Ok(Command::Load("scene2.json".to_string())));
+
+        assert_eq!(Command::parse("set samples"), Err("usage: set <name> <value>".to_string()));
+        assert_eq!(Command::parse("save"), Err("usage: save <path>".to_string()));
+        assert_eq!(Command::parse("load a b"), Err("usage: load <path>".to_string()));
+        assert_eq!(Command::parse("   "), Err("empty command".to_string()));
+        assert_eq!(Command::parse("render now"), Err("unknown command: render".to_string()));
+    }
+
+    #[test]
+    fn set_updates_the_app_state() {
+        let mut state = AppState::new(Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0)));
+        set(&mut state, "samples", "4").unwrap();
+        assert_eq!(state.settings.aa_samples, 4);
+        set(&mut state, "fov", "60").unwrap();
+        assert!((state.camera.fov - 60f32.to_radians()).abs() < 1e-6);
+        set(&mut state, "tone_map", "Reinhard").unwrap();
+        assert_eq!(state.settings.tone_map, ToneMap::Reinhard);
+
+        let before = state.settings;
+        assert!(set(&mut state, "fov", "200").is_err());
+        assert_eq!(set(&mut state, "warp_drive", "1"), Err("unknown setting: warp_drive".to_string()));
+        assert!(set(&mut state, "samples", "many").is_err());
+        assert_eq!(state.settings, before);
+    }
+}
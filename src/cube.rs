@@ -1,83 +1,229 @@
 use nalgebra_glm::Vec3;
-use crate::ray_intersect::{RayIntersect, Intersect};
+use crate::ray_intersect::{RayIntersect, Intersect, INTERSECTION_EPSILON};
 use crate::material::Material;
+use crate::math::saturate;
+use tracer_core::ray_box::ray_box_axes;
+
+/// Outward normal of one face and the axes its texture coordinates run along.
+#[derive(Clone, Copy)]
+struct Face {
+    normal: Vec3,
+    u_axis: usize,
+    v_axis: usize,
+}
 
 #[derive(Clone)]
 pub struct Cube {
-    pub center: Vec3,
-    pub size: f32,
+    center: Vec3,
+    size: f32,
     pub material: Material,
+    min_bound: Vec3,
+    max_bound: Vec3,
+    bounding_radius_squared: f32,
+    /// Indexed by `2 * axis`, plus one for the face on the positive side.
+    faces: [Face; 6],
+    inverse_size: f32,
 }
 
-impl RayIntersect for Cube {
-    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
-        let half_size = self.size / 2.0;
-        let min_bound = self.center - Vec3::new(half_size, half_size, half_size);
-        let max_bound = self.center + Vec3::new(half_size, half_size, half_size);
+const BOUNDING_SPHERE_MARGIN: f32 = 1.0 + 1e-4;
+
+impl Cube {
+    pub fn new(center: Vec3, size: f32, material: Material) -> Self {
+        let mut cube = Cube {
+            center,
+            size,
+            material,
+            min_bound: center,
+            max_bound: center,
+            bounding_radius_squared: 0.0,
+            faces: [Face { normal: Vec3::zeros(), u_axis: 0, v_axis: 0 }; 6],
+            inverse_size: 0.0,
+        };
+        cube.update_bounds();
+        cube
+    }
 
-        let mut t_min = (min_bound.x - ray_origin.x) / ray_direction.x;
-        let mut t_max = (max_bound.x - ray_origin.x) / ray_direction.x;
+    pub fn center(&self) -> Vec3 {
+        self.center
+    }
 
-        if t_min > t_max {
-            std::mem::swap(&mut t_min, &mut t_max);
-        }
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn set_center(&mut self, center: Vec3) {
+        self.center = center;
+        self.update_bounds();
+    }
 
-        let mut t_y_min = (min_bound.y - ray_origin.y) / ray_direction.y;
-        let mut t_y_max = (max_bound.y - ray_origin.y) / ray_direction.y;
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size;
+        self.update_bounds();
+    }
 
-        if t_y_min > t_y_max {
-            std::mem::swap(&mut t_y_min, &mut t_y_max);
+    pub fn translate(&mut self, offset: Vec3) {
+        self.set_center(self.center + offset);
+    }
+
+    fn update_bounds(&mut self) {
+        let half_size = self.size / 2.0;
+        let half_extent = Vec3::new(half_size, half_size, half_size);
+        self.min_bound = self.center - half_extent;
+        self.max_bound = self.center + half_extent;
+
+        let radius = self.size * 3.0_f32.sqrt() * 0.5 * BOUNDING_SPHERE_MARGIN;
+        self.bounding_radius_squared = radius * radius;
+
+        self.faces = std::array::from_fn(|index| {
+            let axis = index / 2;
+            let mut normal = Vec3::zeros();
+            normal[axis] = if index % 2 == 1 { 1.0 } else { -1.0 };
+            let (u_axis, v_axis) = [(2, 1), (0, 2), (0, 1)][axis];
+            Face { normal, u_axis, v_axis }
+        });
+        self.inverse_size = 1.0 / self.size;
+    }
+
+    fn misses_bounding_sphere(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> bool {
+        let radius_squared = self.bounding_radius_squared;
+        let to_center = self.center - ray_origin;
+        let distance_squared = to_center.dot(&to_center);
+        let projection = to_center.dot(ray_direction);
+
+        if distance_squared > radius_squared && projection < 0.0 {
+            return true;
         }
 
-        if (t_min > t_y_max) || (t_y_min > t_max) {
+        let closest_squared = distance_squared - projection * projection / ray_direction.dot(ray_direction);
+        closest_squared > radius_squared
+    }
+}
+
+impl RayIntersect for Cube {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        if !(self.size > 0.0 && self.size.is_finite()) || self.misses_bounding_sphere(ray_origin, ray_direction) {
             return Intersect::empty();
         }
 
-        if t_y_min > t_min {
-            t_min = t_y_min;
+        let epsilon = INTERSECTION_EPSILON * self.size;
+        let min_bound = self.min_bound;
+        let max_bound = self.max_bound;
+
+        let ((t_min, near_axis), (t_max, far_axis)) = match ray_box_axes(ray_origin, ray_direction, &min_bound, &max_bound, epsilon) {
+            Some(slabs) => slabs,
+            None => return Intersect::empty(),
+        };
+
+        // A ray starting inside the cube, such as one refracted into it, leaves through the far face.
+        let (distance, axis, outward) = if t_min >= epsilon {
+            (t_min, near_axis, ray_direction[near_axis] < 0.0)
+        } else {
+            (t_max, far_axis, ray_direction[far_axis] >= 0.0)
+        };
+        if distance < epsilon || !distance.is_finite() {
+            return Intersect::empty();
         }
 
-        if t_y_max < t_max {
-            t_max = t_y_max;
-        }
+        let point = ray_origin + ray_direction * distance;
+        let face = &self.faces[2 * axis + outward as usize];
+        let local = (point - min_bound) * self.inverse_size;
+        let (u, v) = (local[face.u_axis], local[face.v_axis]);
 
-        let mut t_z_min = (min_bound.z - ray_origin.z) / ray_direction.z;
-        let mut t_z_max = (max_bound.z - ray_origin.z) / ray_direction.z;
+        Intersect::new(point, face.normal, distance, self.material)
+            .with_uv(saturate(u), saturate(v))
+    }
+}
 
-        if t_z_min > t_z_max {
-            std::mem::swap(&mut t_z_min, &mut t_z_max);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::hash_index;
+
+    #[test]
+    fn bounding_sphere_never_rejects_a_slab_hit() {
+        let cube = Cube::new(Vec3::new(0.5, -1.0, 2.0), 1.5, Material::sand());
+        let random = |seed: u32| Vec3::new(hash_index(seed, 0), hash_index(seed, 1), hash_index(seed, 2)) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+        let mut hits = 0;
+        for seed in 0..20_000 {
+            let origin = cube.center + random(seed).normalize() * 3.0;
+            let target = cube.center + random(seed + 1_000_000) * cube.size * 0.6;
+            let direction = (target - origin).normalize();
+            if ray_box_axes(&origin, &direction, &cube.min_bound, &cube.max_bound, 0.0).is_some() {
+                hits += 1;
+                assert!(!cube.misses_bounding_sphere(&origin, &direction), "rejected a hit from {:?} along {:?}", origin, direction);
+            }
         }
+        assert!(hits > 1_000);
+    }
 
-        if (t_min > t_z_max) || (t_z_min > t_max) {
-            return Intersect::empty();
+    #[test]
+    fn nearest_hits_are_stable_at_extreme_scales() {
+        for size in [0.001, 1.0, 1000.0] {
+            let cube = Cube::new(Vec3::new(size, 0.0, 0.0), size, Material::sand());
+            let origin = Vec3::new(size, 0.0, size * 5.0);
+            let hit = cube.ray_intersect(&origin, &Vec3::new(0.0, 0.0, -1.0));
+            assert!(hit.is_intersecting, "size {} missed", size);
+            assert!((hit.distance - size * 4.5).abs() <= size * 1e-4, "size {} hit at {}", size, hit.distance);
+            assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
         }
+    }
 
-        if t_z_min > t_min {
-            t_min = t_z_min;
+    #[test]
+    fn degenerate_and_grazing_rays_are_handled() {
+        let direction = Vec3::new(0.0, 0.0, -1.0);
+        for size in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(!Cube::new(Vec3::zeros(), size, Material::sand()).ray_intersect(&Vec3::new(0.0, 0.0, 5.0), &direction).is_intersecting);
         }
 
-        if t_min < 0.0 {
-            return Intersect::empty();
-        }
+        let cube = Cube::new(Vec3::zeros(), 2.0, Material::sand());
+        let inside = cube.ray_intersect(&Vec3::zeros(), &direction);
+        assert!(inside.is_intersecting);
+        assert!((inside.distance - 1.0).abs() < 1e-5);
+        assert_eq!(inside.normal, Vec3::new(0.0, 0.0, -1.0));
 
-        let point = ray_origin + ray_direction * t_min;
-        let mut normal = Vec3::new(0.0, 0.0, 0.0);
-
-        if (point.x - min_bound.x).abs() < 1e-4 {
-            normal = Vec3::new(-1.0, 0.0, 0.0);
-        } else if (point.x - max_bound.x).abs() < 1e-4 {
-            normal = Vec3::new(1.0, 0.0, 0.0);
-        } else if (point.y - min_bound.y).abs() < 1e-4 {
-            normal = Vec3::new(0.0, -1.0, 0.0);
-        } else if (point.y - max_bound.y).abs() < 1e-4 {
-            normal = Vec3::new(0.0, 1.0, 0.0);
-        } else if (point.z - min_bound.z).abs() < 1e-4 {
-            normal = Vec3::new(0.0, 0.0, -1.0);
-        } else if (point.z - max_bound.z).abs() < 1e-4 {
-            normal = Vec3::new(0.0, 0.0, 1.0);
+        assert!(!cube.ray_intersect(&Vec3::new(0.0, 1.01, 5.0), &direction).is_intersecting);
+        assert!(!cube.ray_intersect(&Vec3::new(0.0, 0.0, 5.0), &-direction).is_intersecting);
+    }
+
+    #[test]
+    fn cached_faces_follow_moves_and_resizes() {
+        let mut cube = Cube::new(Vec3::zeros(), 1.0, Material::sand());
+        cube.set_center(Vec3::new(3.0, -2.0, 1.0));
+        cube.set_size(4.0);
+        let random = |seed: u32| Vec3::new(hash_index(seed, 0), hash_index(seed, 1), hash_index(seed, 2)) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+        for seed in 0..2_000 {
+            let origin = cube.center + random(seed).normalize() * 10.0;
+            let direction = (cube.center + random(seed + 1_000_000) * 1.5 - origin).normalize();
+            let hit = cube.ray_intersect(&origin, &direction);
+            assert!(hit.is_intersecting);
+            let offset = (hit.point - cube.center) / 2.0;
+            let axis = offset.iamax();
+            assert!((offset[axis].abs() - 1.0).abs() < 1e-4, "{:?} is not on the surface", hit.point);
+            assert_eq!(hit.normal[axis], offset[axis].signum(), "wrong face at {:?}", hit.point);
+            let (u, v) = hit.uv;
+            let local = (hit.point - cube.min_bound) / 4.0;
+            let expected = [(local.z, local.y), (local.x, local.z), (local.x, local.y)][axis];
+            assert!((u - expected.0).abs() < 1e-5 && (v - expected.1).abs() < 1e-5);
         }
+    }
 
-        let distance = t_min;
-        Intersect::new(point, normal, distance, self.material.clone())  
+    #[test]
+    fn reflection_rays_leave_without_rehitting_the_cube() {
+        let cube = Cube::new(Vec3::new(0.3, -0.2, 0.1), 1.0, Material::mirror());
+        let mut hits = 0;
+        for i in 0..200 {
+            let angle = i as f32 / 200.0 * std::f32::consts::FRAC_PI_2 * 0.999;
+            let direction = Vec3::new(angle.sin(), -angle.cos(), 0.37 * angle.sin()).normalize();
+            let origin = cube.center - direction * 3.0 + Vec3::new(0.0, 0.0, 0.01 * i as f32 - 1.0) * 0.4;
+            let hit = cube.ray_intersect(&origin, &direction);
+            if !hit.is_intersecting {
+                continue;
+            }
+            hits += 1;
+            let reflected = tracer_core::optics::reflect(&direction, &hit.normal).normalize();
+            let rehit = cube.ray_intersect(&hit.point, &reflected);
+            assert!(!rehit.is_intersecting, "ray {} re-hit the cube at distance {}", i, rehit.distance);
+        }
+        assert!(hits > 100);
     }
 }
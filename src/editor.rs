@@ -0,0 +1,95 @@
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::material::Material;
+use crate::object::Object;
+use crate::scene::Scene;
+
+pub const NUDGE_STEP: f32 = 0.05;
+pub const RESIZE_FACTOR: f32 = 1.05;
+const HIGHLIGHT_COLOR: Color = Color::new(255, 140, 0);
+const HIGHLIGHT_WIDTH: f32 = 0.08;
+const SILVER: Color = Color::new(192, 192, 192);
+
+/// A named material constructor.
+pub type MaterialPreset = (&'static str, fn() -> Material);
+
+/// Materials a selected object can be switched to, in cycling order.
+pub const MATERIAL_PRESETS: [MaterialPreset; 6] = [
+    ("sand", Material::sand),
+    ("water", Material::water),
+    ("glass", Material::glass),
+    ("mirror", Material::mirror),
+    ("metal", silver),
+    ("gold", Material::gold),
+];
+
+fn silver() -> Material {
+    Material::metal(SILVER)
+}
+
+/// Index of the nearest camera-visible object under pixel (x, y).
+pub fn pick(scene: &Scene, camera: &Camera, x: f32, y: f32, width: f32, height: f32) -> Option<usize> {
+    let direction = camera.base_change(&camera.pixel_direction(x, y, width, height, 1.0));
+    scene
+        .objects
+        .iter()
+        .enumerate()
+        .filter(|(_, object)| object.visibility().visible_to_camera)
+        .map(|(index, object)| (index, object.ray_intersect(&camera.eye, &direction, &scene.prototypes)))
+        .filter(|(_, intersect)| intersect.is_intersecting)
+        .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+        .map(|(index, _)| index)
+}
+
+/// Switches `object` to the preset after `current`, or to the first one if no preset is applied yet, and returns
+/// the new preset's index.
+pub fn cycle_material(object: &mut Object, current: Option<usize>) -> usize {
+    let preset = current.map_or(0, |preset| (preset + 1) % MATERIAL_PRESETS.len());
+    object.set_material(MATERIAL_PRESETS[preset].1());
+    preset
+}
+
+/// Copy of a cube or instance drawn with an edge outline; other objects are returned unchanged.
+pub fn highlighted(object: &Object, prototypes: &[Cube]) -> Object {
+    let material = match object.material(prototypes) {
+        Some(material) => material,
+        None => return object.clone(),
+    };
+    match object.cube_extent(prototypes) {
+        Some((center, half_size)) => Object::Cube(
+            Cube::new(center, half_size * 2.0, material.with_edges(HIGHLIGHT_COLOR, HIGHLIGHT_WIDTH)),
+            object.visibility(),
+        ),
+        None => object.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+    use crate::instance::Instance;
+    use crate::transform::Transform;
+    use crate::object::Visibility;
+
+    #[test]
+    fn cycling_stores_each_preset_in_order_and_wraps() {
+        let prototypes = [Cube::new(Vec3::new(0.5, 0.5, 0.5), 1.0, Material::sand())];
+        let mut objects = [
+            Object::Cube(Cube::new(Vec3::zeros(), 1.0, Material::sand()), Visibility::default()),
+            Object::Instance(Instance::new(0, Transform::new(Vec3::zeros(), 1.0)), Visibility::default()),
+        ];
+        for object in &mut objects {
+            let mut current = None;
+            for expected in (0..MATERIAL_PRESETS.len()).chain([0]) {
+                let preset = cycle_material(object, current);
+                assert_eq!(preset, expected);
+                let stored = object.material(&prototypes).unwrap();
+                assert_eq!(format!("{:?}", stored), format!("{:?}", MATERIAL_PRESETS[expected].1()), "{}", MATERIAL_PRESETS[expected].0);
+                current = Some(preset);
+            }
+        }
+        assert_eq!(format!("{:?}", prototypes[0].material), format!("{:?}", Material::sand()));
+    }
+}
@@ -0,0 +1,155 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::sky::AnalyticSky;
+use crate::texture::Texture;
+use crate::math::smoothstep;
+
+pub enum EnvMap {
+    Gradient(SkyGradient),
+    Equirect(Texture),
+}
+
+pub struct Environment {
+    pub map: EnvMap,
+    pub light_intensity: f32,
+    pub sky_rotation: f32,
+    pub analytic_sky: Option<AnalyticSky>,
+    /// Bilinearly filter equirect lookups made by `sample_reflection`.
+    pub filter_reflections: bool,
+}
+
+impl Environment {
+    pub fn new(map: EnvMap) -> Self {
+        Environment {
+            map,
+            light_intensity: 1.0,
+            sky_rotation: 0.0,
+            analytic_sky: None,
+            filter_reflections: false,
+        }
+    }
+
+    pub fn sample(&self, direction: &Vec3) -> Color {
+        self.lookup(direction, false)
+    }
+
+    /// Environment lookup for reflected rays, filtered when `filter_reflections` is set.
+    pub fn sample_reflection(&self, direction: &Vec3) -> Color {
+        self.lookup(direction, self.filter_reflections)
+    }
+
+    fn lookup(&self, direction: &Vec3, bilinear: bool) -> Color {
+        let direction = rotate_y(direction, self.sky_rotation);
+        match &self.map {
+            EnvMap::Gradient(gradient) => match &self.analytic_sky {
+                Some(sky) if direction.y >= 0.0 => sky.color(&direction),
+                Some(sky) => {
+                    let horizon = sky.color(&Vec3::new(direction.x, 0.0, direction.z));
+                    horizon.lerp(gradient.below_horizon(self.light_intensity), smoothstep(0.0, 0.25, -direction.y))
+                }
+                None => gradient.color(&direction, self.light_intensity),
+            },
+            EnvMap::Equirect(texture) => {
+                let (u, v) = equirect_uv(&direction);
+                if bilinear {
+                    texture.sample_bilinear(u, v)
+                } else {
+                    texture.sample(u, v)
+                }
+            }
+        }
+    }
+}
+
+fn rotate_y(direction: &Vec3, angle: f32) -> Vec3 {
+    if angle == 0.0 {
+        return *direction;
+    }
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(
+        direction.x * cos - direction.z * sin,
+        direction.y,
+        direction.x * sin + direction.z * cos,
+    )
+}
+
+pub fn equirect_uv(direction: &Vec3) -> (f32, f32) {
+    let direction = direction.normalize();
+    let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+    let v = 0.5 - direction.y.clamp(-1.0, 1.0).asin() / PI;
+    (u.rem_euclid(1.0), v)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SkyGradient {
+    pub sky_day: Color,
+    pub sky_night: Color,
+    pub below_horizon_day: Color,
+    pub below_horizon_night: Color,
+}
+
+impl Default for SkyGradient {
+    fn default() -> Self {
+        SkyGradient {
+            sky_day: Color::new(135, 206, 235),
+            sky_night: Color::new(25, 25, 112),
+            below_horizon_day: Color::new(170, 178, 186),
+            below_horizon_night: Color::new(40, 40, 48),
+        }
+    }
+}
+
+impl SkyGradient {
+    pub fn with_below_horizon(mut self, day: Color, night: Color) -> Self {
+        self.below_horizon_day = day;
+        self.below_horizon_night = night;
+        self
+    }
+
+    pub fn below_horizon(&self, light_intensity: f32) -> Color {
+        self.below_horizon_night.lerp(self.below_horizon_day, light_intensity)
+    }
+
+    pub fn color(&self, ray_direction: &Vec3, light_intensity: f32) -> Color {
+        let t = 0.5 * (ray_direction.y + 1.0);
+
+        let sky_color = self.sky_night.lerp(self.sky_day, light_intensity);
+
+        self.below_horizon(light_intensity).lerp(sky_color, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opposite_directions_map_to_opposite_sides() {
+        for direction in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.3, 0.5, -0.8), Vec3::new(-0.2, -0.9, 0.4)] {
+            let (u, v) = equirect_uv(&direction);
+            let (opposite_u, opposite_v) = equirect_uv(&-direction);
+            let du = (u - opposite_u).rem_euclid(1.0);
+            assert!((du - 0.5).abs() < 1e-5, "{:?}: u {} vs {}", direction, u, opposite_u);
+            assert!((v + opposite_v - 1.0).abs() < 1e-5, "{:?}: v {} vs {}", direction, v, opposite_v);
+        }
+    }
+
+    #[test]
+    fn seam_and_poles_stay_in_range() {
+        for direction in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(-1e-6, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)] {
+            let (u, v) = equirect_uv(&direction);
+            assert!((0.0..1.0).contains(&u) && (0.0..=1.0).contains(&v), "{:?} gave ({}, {})", direction, u, v);
+        }
+        assert_eq!(equirect_uv(&Vec3::new(0.0, 1.0, 0.0)).1, 0.0);
+        assert_eq!(equirect_uv(&Vec3::new(0.0, -1.0, 0.0)).1, 1.0);
+    }
+
+    #[test]
+    fn downward_rays_see_the_configured_below_horizon_color() {
+        let ground = Color::new(90, 110, 120);
+        let gradient = SkyGradient::default().with_below_horizon(ground, Color::new(10, 10, 20));
+        let environment = Environment::new(EnvMap::Gradient(gradient));
+        assert_eq!(environment.sample(&Vec3::new(0.0, -1.0, 0.0)), ground);
+    }
+}
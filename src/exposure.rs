@@ -0,0 +1,63 @@
+use crate::framebuffer::Framebuffer;
+use crate::render_settings::{RenderChannel, RenderSettings};
+
+pub const DEFAULT_EXPOSURE_TARGET: f32 = 0.18;
+pub const EXPOSURE_TARGETS: [f32; 3] = [0.12, DEFAULT_EXPOSURE_TARGET, 0.25];
+const ADAPTATION_RATE: f32 = 0.1;
+const MIN_EXPOSURE: f32 = 0.05;
+const MAX_EXPOSURE: f32 = 20.0;
+
+/// Exposure that would bring a frame measured at `log_average` (already multiplied by `exposure`) to `target`.
+fn target_exposure(log_average: f32, exposure: f32, target: f32) -> f32 {
+    (exposure * target / log_average.max(f32::EPSILON)).clamp(MIN_EXPOSURE, MAX_EXPOSURE)
+}
+
+/// Moves `settings.exposure` a fraction of the way, in log space, towards the exposure that puts the last frame's
+/// log-average luminance at `settings.exposure_target`. Only the final channel is metered.
+pub fn adapt(settings: &mut RenderSettings, framebuffer: &Framebuffer) {
+    if !settings.auto_exposure || settings.channel != RenderChannel::Final {
+        return;
+    }
+    let target = target_exposure(framebuffer.log_average_luminance(), settings.exposure, settings.exposure_target);
+    let exposure = settings.exposure.clamp(MIN_EXPOSURE, MAX_EXPOSURE);
+    settings.exposure = (exposure.ln() + (target.ln() - exposure.ln()) * ADAPTATION_RATE).exp();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meter(framebuffer: &mut Framebuffer, scene_luminance: f32, exposure: f32) {
+        for y in 0..framebuffer.height {
+            for x in 0..framebuffer.width {
+                framebuffer.set_hdr_pixel(x, y, [scene_luminance * exposure; 3]);
+            }
+        }
+    }
+
+    #[test]
+    fn auto_exposure_drives_the_frame_towards_the_target() {
+        for scene_luminance in [0.02, 0.18, 1.5] {
+            let mut settings = RenderSettings { auto_exposure: true, exposure: 1.0, ..RenderSettings::default() };
+            let mut framebuffer = Framebuffer::new(4, 4);
+            let mut previous_error = f32::INFINITY;
+            for _ in 0..120 {
+                meter(&mut framebuffer, scene_luminance, settings.exposure);
+                adapt(&mut settings, &framebuffer);
+                let error = (scene_luminance * settings.exposure / settings.exposure_target).ln().abs();
+                assert!(error <= previous_error + 1e-4, "exposure moved away from the target");
+                previous_error = error;
+            }
+            assert!(previous_error < 0.02, "scene at {} settled {}% off", scene_luminance, previous_error * 100.0);
+        }
+    }
+
+    #[test]
+    fn auto_exposure_is_off_by_default() {
+        let mut settings = RenderSettings::default();
+        let mut framebuffer = Framebuffer::new(2, 2);
+        meter(&mut framebuffer, 5.0, 1.0);
+        adapt(&mut settings, &framebuffer);
+        assert_eq!(settings.exposure, RenderSettings::default().exposure);
+    }
+}
@@ -1,9 +1,29 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::texture::Texture;
+use crate::tone_map::ToneMap;
+use crate::color_space::OutputColorSpace;
+use crate::math::mix;
+use crate::font::{glyph, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::passes::{Pass, Passes};
+use crate::render_settings::RenderChannel;
+
+const BLUR_TAPS: usize = 8;
+const LOG_LUMINANCE_FLOOR: f32 = 1e-4;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
+    hdr: Vec<[f32; 3]>,
+    depth: Vec<f32>,
+    backdrop: Option<Vec<u32>>,
+    passes: Option<Vec<Passes>>,
     background_color: u32,
     current_color: u32,
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+    pub color_space: OutputColorSpace,
 }
 
 impl Framebuffer {
@@ -12,20 +32,210 @@ impl Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            hdr: vec![[0.0; 3]; width * height],
+            depth: vec![f32::INFINITY; width * height],
+            backdrop: None,
+            passes: None,
             background_color: 0x000000,
-            current_color: 0xFFFFFF
+            current_color: 0xFFFFFF,
+            exposure: 1.0,
+            tone_map: ToneMap::None,
+            color_space: OutputColorSpace::Srgb,
         }
     }
 
     pub fn clear(&mut self) {
+        self.depth.fill(f32::INFINITY);
+        if let Some(backdrop) = &self.backdrop {
+            self.buffer.copy_from_slice(backdrop);
+            for (hdr, &pixel) in self.hdr.iter_mut().zip(backdrop) {
+                *hdr = Color::from_hex(pixel).to_unit();
+            }
+            return;
+        }
         for pixel in self.buffer.iter_mut() {
             *pixel = self.background_color;
         }
+        let background = Color::from_hex(self.background_color).to_unit();
+        for pixel in self.hdr.iter_mut() {
+            *pixel = background;
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x < self.width && y < self.height {
+            Some(self.buffer[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x] = color;
+        }
     }
 
     pub fn point(&mut self, x: usize, y: usize) {
+        self.set_pixel(x, y, self.current_color);
+    }
+
+    pub fn write_color(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width && y < self.height {
+            let [r, g, b] = color.to_unit();
+            let linear = [r * self.exposure, g * self.exposure, b * self.exposure];
+            self.hdr[y * self.width + x] = linear;
+            self.buffer[y * self.width + x] = display_hex(self.tone_map, self.color_space, linear);
+        }
+    }
+
+    /// Pixels later written with partial coverage are composited over `image`, resampled to the buffer size.
+    pub fn set_backdrop(&mut self, image: &Texture) {
+        let mut backdrop = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                backdrop.push(image.texel(x * image.width / self.width, y * image.height / self.height).to_hex());
+            }
+        }
+        self.backdrop = Some(backdrop);
+        self.clear();
+    }
+
+    pub fn has_backdrop(&self) -> bool {
+        self.backdrop.is_some()
+    }
+
+    /// Writes a premultiplied color covering `coverage` of the pixel; the uncovered part keeps the backdrop pixel.
+    pub fn write_covered(&mut self, x: usize, y: usize, color: Color, coverage: f32) {
+        let index = y * self.width + x;
+        let backdrop = match &self.backdrop {
+            Some(backdrop) if coverage < 1.0 && x < self.width && y < self.height => backdrop[index],
+            _ => return self.write_color(x, y, color),
+        };
+        let backdrop_color = Color::from_hex(backdrop);
+        if coverage <= 0.0 {
+            self.buffer[index] = backdrop;
+            self.hdr[index] = backdrop_color.to_unit();
+            return;
+        }
+
+        self.write_color(x, y, color * (1.0 / coverage));
+        let [r, g, b] = self.hdr[index];
+        let [br, bg, bb] = backdrop_color.to_unit();
+        self.hdr[index] = [mix(br, r, coverage), mix(bg, g, coverage), mix(bb, b, coverage)];
+        self.buffer[index] = backdrop_color.lerp(Color::from_hex(self.buffer[index]), coverage).to_hex();
+    }
+
+    pub fn depth(&self, x: usize, y: usize) -> f32 {
+        if x < self.width && y < self.height {
+            self.depth[y * self.width + x]
+        } else {
+            f32::INFINITY
+        }
+    }
+
+    pub fn set_depth(&mut self, x: usize, y: usize, depth: f32) {
         if x < self.width && y < self.height {
-            self.buffer[y * self.width + x] = self.current_color;
+            self.depth[y * self.width + x] = depth;
+        }
+    }
+
+    /// Starts keeping the per-pixel shading terms so `save_passes` can write them after the next render.
+    pub fn record_passes(&mut self) {
+        self.passes = Some(vec![Passes::empty(); self.width * self.height]);
+    }
+
+    pub fn set_passes(&mut self, x: usize, y: usize, passes: &Passes) {
+        if let Some(buffer) = &mut self.passes {
+            if x < self.width && y < self.height {
+                buffer[y * self.width + x] = *passes;
+            }
+        }
+    }
+
+    /// Writes one PNG per `Pass` next to `path`. Color passes go through the same exposure, tone map and color
+    /// space as the beauty image; depth and normal are encoded like their debug channels.
+    pub fn save_passes(&self, path: &str) -> Result<Vec<String>, image::ImageError> {
+        let passes = match &self.passes {
+            Some(passes) => passes,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut written = Vec::new();
+        for pass in Pass::ALL {
+            let mut bytes = Vec::with_capacity(self.width * self.height * 3);
+            for (index, terms) in passes.iter().enumerate() {
+                let hex = match pass {
+                    Pass::Diffuse | Pass::Specular | Pass::Reflection | Pass::Transmission | Pass::Emission => {
+                        let color = match pass {
+                            Pass::Diffuse => terms.diffuse,
+                            Pass::Specular => terms.specular,
+                            Pass::Reflection => terms.reflection,
+                            Pass::Transmission => terms.transmission,
+                            _ => terms.emission,
+                        };
+                        let [r, g, b] = color.to_unit();
+                        display_hex(self.tone_map, self.color_space, [r * self.exposure, g * self.exposure, b * self.exposure])
+                    }
+                    Pass::Depth => {
+                        let shade = 1.0 - self.depth[index] / RenderChannel::DEPTH_RANGE;
+                        Color::from_unit(shade, shade, shade).to_hex()
+                    }
+                    Pass::Normal => {
+                        let n = terms.normal * 0.5 + Vec3::new(0.5, 0.5, 0.5);
+                        Color::from_unit(n.x, n.y, n.z).to_hex()
+                    }
+                };
+                bytes.push(((hex >> 16) & 0xFF) as u8);
+                bytes.push(((hex >> 8) & 0xFF) as u8);
+                bytes.push((hex & 0xFF) as u8);
+            }
+            let file_name = pass.file_name(path);
+            image::save_buffer(&file_name, &bytes, self.width as u32, self.height as u32, image::ColorType::Rgb8)?;
+            written.push(file_name);
+        }
+        Ok(written)
+    }
+
+    /// Overwrites the display pixel of every hit whose depth is within `tolerance * focus_distance` of
+    /// `focus_distance`; the HDR buffer is left alone.
+    pub fn highlight_depth(&mut self, focus_distance: f32, tolerance: f32, color: u32) {
+        let band = tolerance * focus_distance;
+        for (pixel, &depth) in self.buffer.iter_mut().zip(&self.depth) {
+            if (depth - focus_distance).abs() <= band {
+                *pixel = color;
+            }
+        }
+    }
+
+    pub fn hdr_pixel(&self, x: usize, y: usize) -> Option<[f32; 3]> {
+        if x < self.width && y < self.height {
+            Some(self.hdr[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// Geometric mean of the exposed linear luminance, skipping non-finite pixels. Each value is offset by
+    /// `LOG_LUMINANCE_FLOOR` so black pixels stay finite.
+    pub fn log_average_luminance(&self) -> f32 {
+        let (sum, count) = self
+            .hdr
+            .iter()
+            .map(|&[r, g, b]| 0.2126 * r + 0.7152 * g + 0.0722 * b)
+            .filter(|luminance| luminance.is_finite())
+            .fold((0.0, 0usize), |(sum, count), luminance| (sum + (luminance.max(0.0) + LOG_LUMINANCE_FLOOR).ln(), count + 1));
+        if count == 0 {
+            return 0.0;
+        }
+        (sum / count as f32).exp()
+    }
+
+    /// Overwrites an already exposed linear value and re-encodes its display pixel.
+    pub fn set_hdr_pixel(&mut self, x: usize, y: usize, linear: [f32; 3]) {
+        if x < self.width && y < self.height {
+            self.hdr[y * self.width + x] = linear;
+            self.buffer[y * self.width + x] = display_hex(self.tone_map, self.color_space, linear);
         }
     }
 
@@ -36,4 +246,344 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
+
+    /// Blends a line over the display buffer at `opacity`, clipped to the buffer bounds.
+    pub fn draw_line(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: u32, opacity: f32) {
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+        let edges = [(-dx, x0), (dx, self.width as f32 - 1.0 - x0), (-dy, y0), (dy, self.height as f32 - 1.0 - y0)];
+        for (p, q) in edges {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return;
+                }
+                continue;
+            }
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+        if t0 > t1 {
+            return;
+        }
+
+        let steps = ((t1 - t0) * dx.abs().max(dy.abs())).ceil().max(1.0) as usize;
+        let color = Color::from_hex(color);
+        for step in 0..=steps {
+            let t = t0 + (t1 - t0) * step as f32 / steps as f32;
+            let (x, y) = ((x0 + dx * t).round() as usize, (y0 + dy * t).round() as usize);
+            if let Some(pixel) = self.get_pixel(x, y) {
+                self.set_pixel(x, y, Color::from_hex(pixel).lerp(color, opacity).to_hex());
+            }
+        }
+    }
+
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: u32) {
+        let mut cursor_x = x;
+        let mut cursor_y = y;
+
+        for c in text.chars() {
+            if c == '\n' {
+                cursor_x = x;
+                cursor_y += GLYPH_HEIGHT + 2;
+                continue;
+            }
+
+            let rows = glyph(c);
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
+                    }
+                    self.set_pixel(cursor_x + column, cursor_y + row, color);
+                }
+            }
+            cursor_x += GLYPH_ADVANCE;
+        }
+    }
+
+    pub fn accumulate(&self, sums: &mut [[f32; 3]]) {
+        for (sum, pixel) in sums.iter_mut().zip(&self.hdr) {
+            sum[0] += pixel[0];
+            sum[1] += pixel[1];
+            sum[2] += pixel[2];
+        }
+    }
+
+    pub fn resolve_average(&mut self, sums: &[[f32; 3]], count: u32) {
+        let count = count.max(1) as f32;
+        for ((pixel, hdr), sum) in self.buffer.iter_mut().zip(self.hdr.iter_mut()).zip(sums) {
+            *hdr = [sum[0] / count, sum[1] / count, sum[2] / count];
+            *pixel = display_hex(self.tone_map, self.color_space, *hdr);
+        }
+    }
+
+    /// Zoom blur: each pixel averages `BLUR_TAPS` samples along the line towards `center`, reaching `strength` of
+    /// the way there.
+    pub fn radial_blur(&mut self, center: (f32, f32), strength: f32) {
+        self.blur_along(|x, y| ((center.0 - x) * strength, (center.1 - y) * strength), 0.0);
+    }
+
+    /// Streaks every pixel along `angle` (radians, screen x towards screen y) over `strength` of the buffer width,
+    /// centered on the pixel.
+    pub fn directional_blur(&mut self, angle: f32, strength: f32) {
+        let length = strength * self.width as f32;
+        let (sin, cos) = angle.sin_cos();
+        self.blur_along(|_, _| (cos * length, sin * length), -0.5);
+    }
+
+    fn blur_along(&mut self, vector: impl Fn(f32, f32) -> (f32, f32), start: f32) {
+        let source = self.hdr.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (dx, dy) = vector(x as f32, y as f32);
+                let mut sum = [0.0; 3];
+                let mut taps = 0.0;
+                for tap in 0..BLUR_TAPS {
+                    let t = start + tap as f32 / BLUR_TAPS as f32;
+                    let sx = (x as f32 + dx * t).round();
+                    let sy = (y as f32 + dy * t).round();
+                    if sx < 0.0 || sy < 0.0 || sx >= self.width as f32 || sy >= self.height as f32 {
+                        continue;
+                    }
+                    let [r, g, b] = source[sy as usize * self.width + sx as usize];
+                    sum = [sum[0] + r, sum[1] + g, sum[2] + b];
+                    taps += 1.0;
+                }
+                if taps > 0.0 {
+                    self.set_hdr_pixel(x, y, [sum[0] / taps, sum[1] / taps, sum[2] / taps]);
+                }
+            }
+        }
+    }
+
+    /// Adds a glow around bright pixels: whatever each channel holds above its `threshold` is tinted by `tint`,
+    /// spread by two box blurs of `radius` pixels and added back at `strength`.
+    pub fn bloom(&mut self, threshold: [f32; 3], tint: Color, radius: usize, strength: f32) {
+        let tint = tint.to_unit();
+        let mut glow: Vec<[f32; 3]> = self
+            .hdr
+            .iter()
+            .map(|pixel| std::array::from_fn(|c| (pixel[c] - threshold[c]).max(0.0) * tint[c]))
+            .collect();
+        for _ in 0..2 {
+            box_blur(&mut glow, self.width, self.height, radius, 1, self.width);
+            box_blur(&mut glow, self.height, self.width, radius, self.width, 1);
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                let [r, g, b] = self.hdr[index];
+                let [gr, gg, gb] = glow[index];
+                self.set_hdr_pixel(x, y, [r + gr * strength, g + gg * strength, b + gb * strength]);
+            }
+        }
+    }
+
+    pub fn to_texture(&self) -> Texture {
+        Texture::from_pixels(self.width, self.height, self.hdr.iter().map(|&[r, g, b]| Color::from_unit(r, g, b)).collect())
+    }
+
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 4);
+        for pixel in &self.buffer {
+            bytes.push(((pixel >> 16) & 0xFF) as u8);
+            bytes.push(((pixel >> 8) & 0xFF) as u8);
+            bytes.push((pixel & 0xFF) as u8);
+            bytes.push(0xFF);
+        }
+        bytes
+    }
+
+    pub fn save_png(&self, path: &str) -> Result<(), image::ImageError> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 3);
+        for pixel in &self.buffer {
+            bytes.push(((pixel >> 16) & 0xFF) as u8);
+            bytes.push(((pixel >> 8) & 0xFF) as u8);
+            bytes.push((pixel & 0xFF) as u8);
+        }
+        image::save_buffer(path, &bytes, self.width as u32, self.height as u32, image::ColorType::Rgb8)
+    }
+
+    pub fn save_hdr(&self, path: &str) -> std::io::Result<()> {
+        let mut bytes = Vec::with_capacity(64 + self.hdr.len() * 4);
+        bytes.extend_from_slice(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n");
+        bytes.extend_from_slice(format!("-Y {} +X {}\n", self.height, self.width).as_bytes());
+        for pixel in &self.hdr {
+            bytes.extend_from_slice(&to_rgbe(*pixel));
+        }
+        std::fs::write(path, bytes)
+    }
+}
+
+/// Running-sum box blur of `radius` along one axis, clamped at the edges. `length` pixels lie `step` apart along
+/// the blurred axis and `lines` such rows start `line_step` apart.
+fn box_blur(values: &mut [[f32; 3]], length: usize, lines: usize, radius: usize, step: usize, line_step: usize) {
+    if radius == 0 || length == 0 {
+        return;
+    }
+    let mut line = vec![[0.0; 3]; length];
+    let window = (2 * radius + 1) as f32;
+    for index in 0..lines {
+        let start = index * line_step;
+        for (i, value) in line.iter_mut().enumerate() {
+            *value = values[start + i * step];
+        }
+        let at = |i: isize| line[i.clamp(0, length as isize - 1) as usize];
+        let mut sum = [0.0; 3];
+        for i in -(radius as isize)..=radius as isize {
+            let [r, g, b] = at(i);
+            sum = [sum[0] + r, sum[1] + g, sum[2] + b];
+        }
+        for i in 0..length {
+            values[start + i * step] = [sum[0] / window, sum[1] / window, sum[2] / window];
+            let [ar, ag, ab] = at(i as isize + radius as isize + 1);
+            let [sr, sg, sb] = at(i as isize - radius as isize);
+            sum = [sum[0] + ar - sr, sum[1] + ag - sg, sum[2] + ab - sb];
+        }
+    }
+}
+
+fn display_hex(tone_map: ToneMap, color_space: OutputColorSpace, linear: [f32; 3]) -> u32 {
+    let [r, g, b] = color_space.encode(tone_map.apply(linear));
+    Color::from_unit(r, g, b).to_hex()
+}
+
+fn to_rgbe([r, g, b]: [f32; 3]) -> [u8; 4] {
+    let (r, g, b) = (r.max(0.0), g.max(0.0), b.max(0.0));
+    let brightest = r.max(g).max(b);
+    if !brightest.is_finite() || brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    let mut exponent = brightest.log2().floor() as i32 + 1;
+    if brightest / 2f32.powi(exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let scale = 256.0 / 2f32.powi(exponent);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubling_exposure_doubles_linear_values() {
+        let color = Color::new(40, 80, 120);
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.write_color(0, 0, color);
+        framebuffer.exposure = 2.0;
+        framebuffer.write_color(1, 0, color);
+        let [r, g, b] = framebuffer.hdr_pixel(0, 0).unwrap();
+        assert_eq!(framebuffer.hdr_pixel(1, 0), Some([2.0 * r, 2.0 * g, 2.0 * b]));
+    }
+
+    fn from_rgbe([r, g, b, e]: [u8; 4]) -> [f32; 3] {
+        if e == 0 {
+            return [0.0; 3];
+        }
+        let scale = 2f32.powi(e as i32 - 136);
+        [(r as f32 + 0.5) * scale, (g as f32 + 0.5) * scale, (b as f32 + 0.5) * scale]
+    }
+
+    #[test]
+    fn rgbe_round_trips_within_mantissa_precision() {
+        for linear in [[0.18, 0.18, 0.18], [1.0, 0.5, 0.25], [12.0, 3.0, 0.01], [0.001, 0.002, 0.0005], [255.0, 1.0, 0.0]] {
+            let decoded = from_rgbe(to_rgbe(linear));
+            let brightest = linear.iter().cloned().fold(0.0, f32::max);
+            for (original, decoded) in linear.iter().zip(decoded) {
+                assert!((original - decoded).abs() <= brightest / 128.0, "{:?} decoded as {:?}", linear, decoded);
+            }
+        }
+        assert_eq!(to_rgbe([0.0, -1.0, 0.0]), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe([f32::NAN, 1.0, 1.0])[0], 0);
+    }
+
+    #[test]
+    fn pixel_accessors_are_bounds_checked() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.set_pixel(2, 1, 0x123456);
+        assert_eq!(framebuffer.get_pixel(2, 1), Some(0x123456));
+        assert_eq!(framebuffer.buffer[5], 0x123456);
+
+        let before = framebuffer.buffer.clone();
+        framebuffer.set_pixel(3, 0, 0xFFFFFF);
+        framebuffer.set_pixel(0, 2, 0xFFFFFF);
+        framebuffer.set_pixel(usize::MAX, usize::MAX, 0xFFFFFF);
+        assert_eq!(framebuffer.buffer, before);
+        assert_eq!(framebuffer.get_pixel(3, 0), None);
+        assert_eq!(framebuffer.get_pixel(0, 2), None);
+        assert_eq!(framebuffer.hdr_pixel(3, 1), None);
+    }
+
+    fn lit_pixels(framebuffer: &Framebuffer) -> Vec<(usize, usize)> {
+        (0..framebuffer.height)
+            .flat_map(|y| (0..framebuffer.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| framebuffer.hdr_pixel(x, y).unwrap()[0] > 0.0)
+            .collect()
+    }
+
+    #[test]
+    fn directional_blur_spreads_a_bright_pixel_along_its_angle() {
+        let mut framebuffer = Framebuffer::new(32, 32);
+        framebuffer.set_hdr_pixel(16, 16, [8.0, 8.0, 8.0]);
+        framebuffer.directional_blur(0.0, 0.25);
+        let lit = lit_pixels(&framebuffer);
+        assert!(lit.len() > 3);
+        assert!(lit.iter().all(|&(_, y)| y == 16), "a horizontal blur leaked off the row: {:?}", lit);
+        assert!(lit.iter().any(|&(x, _)| x < 16) && lit.iter().any(|&(x, _)| x > 16));
+
+        let mut framebuffer = Framebuffer::new(32, 32);
+        framebuffer.set_hdr_pixel(16, 16, [8.0, 8.0, 8.0]);
+        framebuffer.directional_blur(std::f32::consts::FRAC_PI_2, 0.25);
+        assert!(lit_pixels(&framebuffer).iter().all(|&(x, _)| x == 16));
+    }
+
+    #[test]
+    fn radial_blur_streaks_outward_from_the_center() {
+        let mut framebuffer = Framebuffer::new(32, 32);
+        framebuffer.set_hdr_pixel(24, 16, [8.0, 8.0, 8.0]);
+        framebuffer.radial_blur((16.0, 16.0), 0.5);
+        let lit = lit_pixels(&framebuffer);
+        assert!(lit.len() > 1);
+        assert!(lit.iter().all(|&(x, y)| y == 16 && x >= 24), "radial streak went the wrong way: {:?}", lit);
+    }
+
+    #[test]
+    fn focus_highlight_flags_only_pixels_at_the_focus_distance() {
+        let mut framebuffer = Framebuffer::new(5, 1);
+        framebuffer.depth.copy_from_slice(&[4.0, 5.0, 5.2, 9.0, f32::INFINITY]);
+        let hdr = framebuffer.hdr.clone();
+        let before = framebuffer.buffer.clone();
+        framebuffer.highlight_depth(5.0, 0.05, 0xFF00FF);
+
+        let flagged: Vec<bool> = framebuffer.buffer.iter().map(|&pixel| pixel == 0xFF00FF).collect();
+        assert_eq!(flagged, vec![false, true, true, false, false]);
+        assert_eq!(framebuffer.buffer[0], before[0]);
+        assert_eq!(framebuffer.hdr, hdr);
+    }
+
+    #[test]
+    fn blue_only_highlights_bloom_only_in_blue() {
+        let mut framebuffer = Framebuffer::new(16, 16);
+        framebuffer.set_hdr_pixel(8, 8, [0.5, 0.5, 4.0]);
+        framebuffer.bloom([1.0, 1.0, 1.0], Color::new(255, 255, 255), 2, 1.0);
+        let neighbour = framebuffer.hdr_pixel(9, 8).unwrap();
+        assert_eq!((neighbour[0], neighbour[1]), (0.0, 0.0));
+        assert!(neighbour[2] > 0.0);
+
+        let mut tinted = Framebuffer::new(16, 16);
+        tinted.set_hdr_pixel(8, 8, [0.5, 0.5, 4.0]);
+        tinted.bloom([1.0, 1.0, 1.0], Color::new(255, 255, 0), 2, 1.0);
+        assert_eq!(tinted.hdr_pixel(9, 8), Some([0.0, 0.0, 0.0]), "a tint without blue should cancel blue bloom");
+    }
 }
@@ -0,0 +1,82 @@
+use nalgebra_glm::Vec3;
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+
+const NEAR_PLANE: f32 = 0.05;
+const AXIS_LENGTH: f32 = 2.0;
+const AXES: [(Vec3, u32); 3] = [
+    (Vec3::new(1.0, 0.0, 0.0), 0xFF4040),
+    (Vec3::new(0.0, 1.0, 0.0), 0x40FF40),
+    (Vec3::new(0.0, 0.0, 1.0), 0x4080FF),
+];
+const GRID_HALF_EXTENT: i32 = 10;
+const GRID_COLOR: u32 = 0xFFFFFF;
+const GRID_OPACITY: f32 = 0.25;
+
+/// Draws a faint unit grid on the world y = 0 plane and the world axes from the origin on top of the image.
+pub fn draw(framebuffer: &mut Framebuffer, camera: &Camera) {
+    let extent = GRID_HALF_EXTENT as f32;
+    for i in -GRID_HALF_EXTENT..=GRID_HALF_EXTENT {
+        let offset = i as f32;
+        draw_segment(framebuffer, camera, Vec3::new(offset, 0.0, -extent), Vec3::new(offset, 0.0, extent), GRID_COLOR, GRID_OPACITY);
+        draw_segment(framebuffer, camera, Vec3::new(-extent, 0.0, offset), Vec3::new(extent, 0.0, offset), GRID_COLOR, GRID_OPACITY);
+    }
+    for (axis, color) in AXES {
+        draw_segment(framebuffer, camera, Vec3::zeros(), axis * AXIS_LENGTH, color, 1.0);
+    }
+}
+
+fn draw_segment(framebuffer: &mut Framebuffer, camera: &Camera, start: Vec3, end: Vec3, color: u32, opacity: f32) {
+    let (start, end) = match clip_to_near_plane(camera, start, end) {
+        Some(segment) => segment,
+        None => return,
+    };
+    let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+    if let (Some((x0, y0)), Some((x1, y1))) = (camera.project(&start, width, height), camera.project(&end, width, height)) {
+        framebuffer.draw_line(x0, y0, x1, y1, color, opacity);
+    }
+}
+
+/// Cuts the part of the segment closer than `NEAR_PLANE` in front of the camera, so points behind the eye are
+/// never projected.
+fn clip_to_near_plane(camera: &Camera, start: Vec3, end: Vec3) -> Option<(Vec3, Vec3)> {
+    let forward = camera.forward();
+    let start_depth = (start - camera.eye).dot(&forward) - NEAR_PLANE;
+    let end_depth = (end - camera.eye).dot(&forward) - NEAR_PLANE;
+    if start_depth < 0.0 && end_depth < 0.0 {
+        return None;
+    }
+    let crossing = start + (end - start) * (start_depth / (start_depth - end_depth));
+    match (start_depth < 0.0, end_depth < 0.0) {
+        (true, _) => Some((crossing, end)),
+        (_, true) => Some((start, crossing)),
+        _ => Some((start, end)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_origin_projects_to_the_image_center_when_looked_at() {
+        let camera = Camera::new(Vec3::new(3.0, 4.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let (x, y) = camera.project(&Vec3::zeros(), 640.0, 480.0).unwrap();
+        assert!((x - 320.0).abs() < 1e-3 && (y - 240.0).abs() < 1e-3, "origin at ({}, {})", x, y);
+
+        let mut framebuffer = Framebuffer::new(64, 48);
+        draw(&mut framebuffer, &camera);
+        assert_eq!(framebuffer.get_pixel(32, 24), Some(AXES[2].1), "the last axis drawn should cover the origin");
+    }
+
+    #[test]
+    fn segments_behind_the_camera_are_clipped_at_the_near_plane() {
+        let camera = Camera::new(Vec3::zeros(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(clip_to_near_plane(&camera, Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 3.0)).is_none());
+
+        let (start, end) = clip_to_near_plane(&camera, Vec3::new(0.0, -1.0, 2.0), Vec3::new(0.0, -1.0, -4.0)).unwrap();
+        assert!((start.z + NEAR_PLANE).abs() < 1e-6);
+        assert_eq!(end, Vec3::new(0.0, -1.0, -4.0));
+        assert!(camera.project(&start, 64.0, 48.0).is_some());
+    }
+}
@@ -0,0 +1,75 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::material::Material;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    pub prototype: usize,
+    pub transform: Transform,
+    pub diffuse: Option<Color>,
+    /// Replaces the prototype's material for this instance alone.
+    pub material: Option<Material>,
+}
+
+impl Instance {
+    pub fn new(prototype: usize, transform: Transform) -> Self {
+        Instance { prototype, transform, diffuse: None, material: None }
+    }
+
+    pub fn with_diffuse(mut self, diffuse: Color) -> Self {
+        self.diffuse = Some(diffuse);
+        self
+    }
+
+    /// Intersects the ray in prototype space and maps the hit back to world space. Missing prototypes never hit.
+    pub fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, prototypes: &[Cube]) -> Intersect {
+        let scale = self.transform.scale;
+        let prototype = match prototypes.get(self.prototype) {
+            Some(prototype) if scale > 0.0 => prototype,
+            _ => return Intersect::empty(),
+        };
+
+        let local_origin = (ray_origin - self.transform.translation) / scale;
+        let mut intersect = prototype.ray_intersect(&local_origin, ray_direction);
+        if intersect.is_intersecting {
+            intersect.point = self.transform.apply_point(&intersect.point);
+            intersect.distance *= scale;
+            if let Some(material) = self.material {
+                intersect.material = material;
+            }
+            if let Some(diffuse) = self.diffuse {
+                intersect.material.diffuse = diffuse;
+            }
+        }
+        intersect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_instance_hits_like_the_explicit_cube() {
+        let prototypes = [Cube::new(Vec3::new(0.5, 0.5, 0.5), 1.0, Material::sand())];
+        let instance = Instance::new(0, Transform::new(Vec3::new(2.0, -1.0, 3.0), 2.0));
+        let explicit = Cube::new(Vec3::new(3.0, 0.0, 4.0), 2.0, Material::sand());
+
+        let origin = Vec3::new(-4.0, 5.0, 12.0);
+        for i in 0..25 {
+            let target = Vec3::new(2.2 + (i % 5) as f32 * 0.4, -0.8 + (i / 5) as f32 * 0.4, 4.0);
+            let direction = (target - origin).normalize();
+            let instanced = instance.ray_intersect(&origin, &direction, &prototypes);
+            let reference = explicit.ray_intersect(&origin, &direction);
+            assert!(reference.is_intersecting && instanced.is_intersecting);
+            assert!((instanced.distance - reference.distance).abs() < 1e-4);
+            assert!((instanced.point - reference.point).magnitude() < 1e-4);
+            assert_eq!(instanced.normal, reference.normal);
+            assert!((instanced.uv.0 - reference.uv.0).abs() < 1e-4 && (instanced.uv.1 - reference.uv.1).abs() < 1e-4);
+        }
+        assert!(!Instance::new(1, Transform::translation(Vec3::zeros())).ray_intersect(&origin, &-origin, &prototypes).is_intersecting);
+    }
+}
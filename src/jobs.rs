@@ -0,0 +1,80 @@
+use crate::app_state::AppState;
+use crate::json::Json;
+
+/// One still of a batch render.
+pub struct Job {
+    pub state: AppState,
+    pub output: String,
+    pub width: usize,
+    pub height: usize,
+    /// Animation time the scene is posed at, in seconds.
+    pub time: f32,
+}
+
+impl Job {
+    /// Reads a job object. Starting from `base`, it loads the app state file named by `state` if there is one, then
+    /// applies any `camera`, `sun_angle` or `settings` given inline, so a job only lists what differs. `samples`
+    /// is shorthand for the antialiasing sample count; `output` is required.
+    pub fn from_json(json: &Json, base: &AppState, default_size: (usize, usize)) -> Result<Job, String> {
+        let mut state = base.clone();
+        if let Some(path) = json.get("state") {
+            let path = path.as_str().ok_or("state must be a path")?;
+            state.load(path).map_err(|err| format!("{}: {}", path, err))?;
+        }
+        state.apply_json(json)?;
+        if let Some(samples) = json.get("samples") {
+            state.settings.aa_samples = samples.as_usize().ok_or("samples must be a sample count")?;
+        }
+
+        let output = json.get("output").and_then(Json::as_str).ok_or("every job needs an output path")?.to_string();
+        let dimension = |key: &str, default: usize| match json.get(key) {
+            Some(value) => value.as_usize().filter(|&size| size > 0).ok_or(format!("{} must be a positive pixel count", key)),
+            None => Ok(default),
+        };
+        let time = match json.get("time") {
+            Some(value) => value.as_f32().ok_or("time must be a number")?,
+            None => 0.0,
+        };
+        Ok(Job { state, output, width: dimension("width", default_size.0)?, height: dimension("height", default_size.1)?, time })
+    }
+}
+
+/// Reads a job list: a JSON array of job objects.
+pub fn load(path: &str, base: &AppState, default_size: (usize, usize)) -> Result<Vec<Job>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let json = Json::parse(&text)?;
+    let jobs = json.as_array().ok_or("a job list must be an array of jobs")?;
+    jobs.iter()
+        .enumerate()
+        .map(|(index, job)| Job::from_json(job, base, default_size).map_err(|err| format!("job {}: {}", index + 1, err)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+    use crate::camera::Camera;
+
+    fn base() -> AppState {
+        AppState::new(Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0)))
+    }
+
+    #[test]
+    fn a_job_lists_only_what_differs_from_the_base() {
+        let json = Json::parse(r#"{"output": "a.png", "width": 32, "samples": 4, "sun_angle": 1.5, "time": 2.0}"#).unwrap();
+        let job = Job::from_json(&json, &base(), (80, 60)).unwrap();
+        assert_eq!((job.output.as_str(), job.width, job.height), ("a.png", 32, 60));
+        assert_eq!((job.state.settings.aa_samples, job.state.sun_angle, job.time), (4, 1.5, 2.0));
+        assert_eq!(job.state.camera.eye, base().camera.eye);
+    }
+
+    #[test]
+    fn malformed_jobs_are_rejected() {
+        let parse = |text: &str| Job::from_json(&Json::parse(text).unwrap(), &base(), (80, 60)).err();
+        assert_eq!(parse(r#"{"width": 32}"#), Some("every job needs an output path".to_string()));
+        assert_eq!(parse(r#"{"output": "a.png", "height": 0}"#), Some("height must be a positive pixel count".to_string()));
+        assert_eq!(parse(r#"{"output": "a.png", "time": "noon"}"#), Some("time must be a number".to_string()));
+        assert_eq!(parse(r#"{"output": "a.png", "samples": -1}"#), Some("samples must be a sample count".to_string()));
+    }
+}
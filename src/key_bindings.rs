@@ -0,0 +1,479 @@
+use minifb::{Key, KeyRepeat, Window};
+use proyecto3gc::json::Json;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    Held,
+    Pressed,
+    Repeat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Forward,
+    Backward,
+    OrbitLeft,
+    OrbitRight,
+    OrbitUp,
+    OrbitDown,
+    StrafeLeft,
+    StrafeRight,
+    ToggleEnvReflection,
+    ToggleSkyRotation,
+    ToggleChromaticAberration,
+    CycleSpecularModel,
+    CycleShadowSamples,
+    ToggleNanCheck,
+    NextChannel,
+    ToggleLightMarkers,
+    ToggleHud,
+    ToggleTrackLight,
+    NextLightColor,
+    LightScaleDown,
+    LightScaleUp,
+    ExposureDown,
+    ExposureUp,
+    SaveState,
+    LoadState,
+    Screenshot,
+    CycleMotionBlur,
+    ToggleFireflyClamp,
+    SaveHdr,
+    ToggleAnalyticSky,
+    TurbidityDown,
+    TurbidityUp,
+    NextToneMap,
+    CycleLightSamples,
+    ToggleCameraShake,
+    CycleShadowMode,
+    ToggleSortedTransparency,
+    CycleColorSpace,
+    ToggleTemporalReprojection,
+    ToggleEditMode,
+    GrowSelection,
+    ShrinkSelection,
+    CycleSkylightSamples,
+    ToggleFrameBudget,
+    CycleAntialiasing,
+    CycleSamplePattern,
+    ToggleEdgeAntialiasing,
+    CyclePostBlur,
+    ToggleFocusPeaking,
+    FocusNearer,
+    FocusFarther,
+    ToggleEnergyClamp,
+    ToggleGizmo,
+    DumpCamera,
+    ToggleBilinearReflections,
+    ToggleMask,
+    ToggleWind,
+    ToggleAutoExposure,
+    CycleExposureTarget,
+    CycleWaterResolution,
+    ToggleBloom,
+    ToggleCaustics,
+    CycleReconstructionFilter,
+    ToggleDepthTieBreak,
+    CycleMaterialPreset,
+}
+
+impl Action {
+    pub const ALL: [Action; 65] = [
+        Action::Forward,
+        Action::Backward,
+        Action::OrbitLeft,
+        Action::OrbitRight,
+        Action::OrbitUp,
+        Action::OrbitDown,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::ToggleEnvReflection,
+        Action::ToggleSkyRotation,
+        Action::ToggleChromaticAberration,
+        Action::CycleSpecularModel,
+        Action::CycleShadowSamples,
+        Action::ToggleNanCheck,
+        Action::NextChannel,
+        Action::ToggleLightMarkers,
+        Action::ToggleHud,
+        Action::ToggleTrackLight,
+        Action::NextLightColor,
+        Action::LightScaleDown,
+        Action::LightScaleUp,
+        Action::ExposureDown,
+        Action::ExposureUp,
+        Action::SaveState,
+        Action::LoadState,
+        Action::Screenshot,
+        Action::CycleMotionBlur,
+        Action::ToggleFireflyClamp,
+        Action::SaveHdr,
+        Action::ToggleAnalyticSky,
+        Action::TurbidityDown,
+        Action::TurbidityUp,
+        Action::NextToneMap,
+        Action::CycleLightSamples,
+        Action::ToggleCameraShake,
+        Action::CycleShadowMode,
+        Action::ToggleSortedTransparency,
+        Action::CycleColorSpace,
+        Action::ToggleTemporalReprojection,
+        Action::ToggleEditMode,
+        Action::GrowSelection,
+        Action::ShrinkSelection,
+        Action::CycleSkylightSamples,
+        Action::ToggleFrameBudget,
+        Action::CycleAntialiasing,
+        Action::CycleSamplePattern,
+        Action::ToggleEdgeAntialiasing,
+        Action::CyclePostBlur,
+        Action::ToggleFocusPeaking,
+        Action::FocusNearer,
+        Action::FocusFarther,
+        Action::ToggleEnergyClamp,
+        Action::ToggleGizmo,
+        Action::DumpCamera,
+        Action::ToggleBilinearReflections,
+        Action::ToggleMask,
+        Action::ToggleWind,
+        Action::ToggleAutoExposure,
+        Action::CycleExposureTarget,
+        Action::CycleWaterResolution,
+        Action::ToggleBloom,
+        Action::ToggleCaustics,
+        Action::CycleReconstructionFilter,
+        Action::ToggleDepthTieBreak,
+        Action::CycleMaterialPreset,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Forward => "forward",
+            Action::Backward => "backward",
+            Action::OrbitLeft => "orbit_left",
+            Action::OrbitRight => "orbit_right",
+            Action::OrbitUp => "orbit_up",
+            Action::OrbitDown => "orbit_down",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::ToggleEnvReflection => "toggle_env_reflection",
+            Action::ToggleSkyRotation => "toggle_sky_rotation",
+            Action::ToggleChromaticAberration => "toggle_chromatic_aberration",
+            Action::CycleSpecularModel => "cycle_specular_model",
+            Action::CycleShadowSamples => "cycle_shadow_samples",
+            Action::ToggleNanCheck => "toggle_nan_check",
+            Action::NextChannel => "next_channel",
+            Action::ToggleLightMarkers => "toggle_light_markers",
+            Action::ToggleHud => "toggle_hud",
+            Action::ToggleTrackLight => "toggle_track_light",
+            Action::NextLightColor => "next_light_color",
+            Action::LightScaleDown => "light_scale_down",
+            Action::LightScaleUp => "light_scale_up",
+            Action::ExposureDown => "exposure_down",
+            Action::ExposureUp => "exposure_up",
+            Action::SaveState => "save_state",
+            Action::LoadState => "load_state",
+            Action::Screenshot => "screenshot",
+            Action::CycleMotionBlur => "cycle_motion_blur",
+            Action::ToggleFireflyClamp => "toggle_firefly_clamp",
+            Action::SaveHdr => "save_hdr",
+            Action::ToggleAnalyticSky => "toggle_analytic_sky",
+            Action::TurbidityDown => "turbidity_down",
+            Action::TurbidityUp => "turbidity_up",
+            Action::NextToneMap => "next_tone_map",
+            Action::CycleLightSamples => "cycle_light_samples",
+            Action::ToggleCameraShake => "toggle_camera_shake",
+            Action::CycleShadowMode => "cycle_shadow_mode",
+            Action::ToggleSortedTransparency => "toggle_sorted_transparency",
+            Action::CycleColorSpace => "cycle_color_space",
+            Action::ToggleTemporalReprojection => "toggle_temporal_reprojection",
+            Action::ToggleEditMode => "toggle_edit_mode",
+            Action::GrowSelection => "grow_selection",
+            Action::ShrinkSelection => "shrink_selection",
+            Action::CycleSkylightSamples => "cycle_skylight_samples",
+            Action::ToggleFrameBudget => "toggle_frame_budget",
+            Action::CycleAntialiasing => "cycle_antialiasing",
+            Action::CycleSamplePattern => "cycle_sample_pattern",
+            Action::ToggleEdgeAntialiasing => "toggle_edge_antialiasing",
+            Action::CyclePostBlur => "cycle_post_blur",
+            Action::ToggleFocusPeaking => "toggle_focus_peaking",
+            Action::FocusNearer => "focus_nearer",
+            Action::FocusFarther => "focus_farther",
+            Action::ToggleEnergyClamp => "toggle_energy_clamp",
+            Action::ToggleGizmo => "toggle_gizmo",
+            Action::DumpCamera => "dump_camera",
+            Action::ToggleBilinearReflections => "toggle_bilinear_reflections",
+            Action::ToggleMask => "toggle_mask",
+            Action::ToggleWind => "toggle_wind",
+            Action::ToggleAutoExposure => "toggle_auto_exposure",
+            Action::CycleExposureTarget => "cycle_exposure_target",
+            Action::CycleWaterResolution => "cycle_water_resolution",
+            Action::ToggleBloom => "toggle_bloom",
+            Action::ToggleCaustics => "toggle_caustics",
+            Action::CycleReconstructionFilter => "cycle_reconstruction_filter",
+            Action::ToggleDepthTieBreak => "toggle_depth_tie_break",
+            Action::CycleMaterialPreset => "cycle_material_preset",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+
+    pub fn trigger(self) -> Trigger {
+        match self {
+            Action::Forward
+            | Action::Backward
+            | Action::OrbitLeft
+            | Action::OrbitRight
+            | Action::OrbitUp
+            | Action::OrbitDown
+            | Action::StrafeLeft
+            | Action::StrafeRight => Trigger::Held,
+            Action::LightScaleDown
+            | Action::LightScaleUp
+            | Action::ExposureDown
+            | Action::ExposureUp
+            | Action::TurbidityDown
+            | Action::TurbidityUp
+            | Action::GrowSelection
+            | Action::ShrinkSelection
+            | Action::FocusNearer
+            | Action::FocusFarther => Trigger::Repeat,
+            _ => Trigger::Pressed,
+        }
+    }
+
+    fn default_key(self) -> Key {
+        match self {
+            Action::Forward => Key::W,
+            Action::Backward => Key::S,
+            Action::OrbitLeft => Key::A,
+            Action::OrbitRight => Key::D,
+            Action::OrbitUp => Key::Up,
+            Action::OrbitDown => Key::Down,
+            Action::StrafeLeft => Key::Left,
+            Action::StrafeRight => Key::Right,
+            Action::ToggleEnvReflection => Key::R,
+            Action::ToggleSkyRotation => Key::Y,
+            Action::ToggleChromaticAberration => Key::X,
+            Action::CycleSpecularModel => Key::B,
+            Action::CycleShadowSamples => Key::H,
+            Action::ToggleNanCheck => Key::N,
+            Action::NextChannel => Key::Tab,
+            Action::ToggleLightMarkers => Key::I,
+            Action::ToggleHud => Key::F1,
+            Action::ToggleTrackLight => Key::T,
+            Action::NextLightColor => Key::L,
+            Action::LightScaleDown => Key::LeftBracket,
+            Action::LightScaleUp => Key::RightBracket,
+            Action::ExposureDown => Key::Minus,
+            Action::ExposureUp => Key::Equal,
+            Action::SaveState => Key::F9,
+            Action::LoadState => Key::F10,
+            Action::Screenshot => Key::F12,
+            Action::CycleMotionBlur => Key::M,
+            Action::ToggleFireflyClamp => Key::K,
+            Action::SaveHdr => Key::F11,
+            Action::ToggleAnalyticSky => Key::G,
+            Action::TurbidityDown => Key::Comma,
+            Action::TurbidityUp => Key::Period,
+            Action::NextToneMap => Key::O,
+            Action::CycleLightSamples => Key::J,
+            Action::ToggleCameraShake => Key::V,
+            Action::CycleShadowMode => Key::P,
+            Action::ToggleSortedTransparency => Key::U,
+            Action::CycleColorSpace => Key::Z,
+            Action::ToggleTemporalReprojection => Key::Q,
+            Action::ToggleEditMode => Key::E,
+            Action::GrowSelection => Key::PageUp,
+            Action::ShrinkSelection => Key::PageDown,
+            Action::CycleSkylightSamples => Key::F,
+            Action::ToggleFrameBudget => Key::Backslash,
+            Action::CycleAntialiasing => Key::Semicolon,
+            Action::CycleSamplePattern => Key::Apostrophe,
+            Action::ToggleEdgeAntialiasing => Key::F2,
+            Action::CyclePostBlur => Key::F3,
+            Action::ToggleFocusPeaking => Key::F4,
+            Action::FocusNearer => Key::Home,
+            Action::FocusFarther => Key::End,
+            Action::ToggleEnergyClamp => Key::F5,
+            Action::ToggleGizmo => Key::F6,
+            Action::DumpCamera => Key::C,
+            Action::ToggleBilinearReflections => Key::F7,
+            Action::ToggleMask => Key::Insert,
+            Action::ToggleWind => Key::F8,
+            Action::ToggleAutoExposure => Key::Slash,
+            Action::CycleExposureTarget => Key::Key0,
+            Action::CycleWaterResolution => Key::Key9,
+            Action::ToggleBloom => Key::Key8,
+            Action::ToggleCaustics => Key::Key7,
+            Action::CycleReconstructionFilter => Key::Key6,
+            Action::ToggleDepthTieBreak => Key::Key5,
+            Action::CycleMaterialPreset => Key::Key4,
+        }
+    }
+}
+
+const KEY_NAMES: [(&str, Key); 89] = [
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("Key0", Key::Key0),
+    ("Key1", Key::Key1),
+    ("Key2", Key::Key2),
+    ("Key3", Key::Key3),
+    ("Key4", Key::Key4),
+    ("Key5", Key::Key5),
+    ("Key6", Key::Key6),
+    ("Key7", Key::Key7),
+    ("Key8", Key::Key8),
+    ("Key9", Key::Key9),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Space", Key::Space),
+    ("Tab", Key::Tab),
+    ("Enter", Key::Enter),
+    ("Backspace", Key::Backspace),
+    ("Delete", Key::Delete),
+    ("Insert", Key::Insert),
+    ("Home", Key::Home),
+    ("End", Key::End),
+    ("PageUp", Key::PageUp),
+    ("PageDown", Key::PageDown),
+    ("Minus", Key::Minus),
+    ("Equal", Key::Equal),
+    ("LeftBracket", Key::LeftBracket),
+    ("RightBracket", Key::RightBracket),
+    ("Semicolon", Key::Semicolon),
+    ("Apostrophe", Key::Apostrophe),
+    ("Comma", Key::Comma),
+    ("Period", Key::Period),
+    ("Slash", Key::Slash),
+    ("Backslash", Key::Backslash),
+    ("Backquote", Key::Backquote),
+    ("NumPad0", Key::NumPad0),
+    ("NumPad1", Key::NumPad1),
+    ("NumPad2", Key::NumPad2),
+    ("NumPad3", Key::NumPad3),
+    ("NumPad4", Key::NumPad4),
+    ("NumPad5", Key::NumPad5),
+    ("NumPad6", Key::NumPad6),
+    ("NumPad7", Key::NumPad7),
+    ("NumPad8", Key::NumPad8),
+    ("NumPad9", Key::NumPad9),
+    ("NumPadDot", Key::NumPadDot),
+    ("NumPadSlash", Key::NumPadSlash),
+    ("NumPadAsterisk", Key::NumPadAsterisk),
+    ("NumPadMinus", Key::NumPadMinus),
+    ("NumPadPlus", Key::NumPadPlus),
+    ("NumPadEnter", Key::NumPadEnter),
+];
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES.iter().find(|(key_name, _)| key_name.eq_ignore_ascii_case(name)).map(|&(_, key)| key)
+}
+
+fn valid_key_names() -> String {
+    KEY_NAMES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Key)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            bindings: Action::ALL.iter().map(|&action| (action, action.default_key())).collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut bindings = KeyBindings::default();
+        bindings.apply_json(&Json::parse(&text)?)?;
+        Ok(bindings)
+    }
+
+    pub fn apply_json(&mut self, json: &Json) -> Result<(), String> {
+        let fields = match json {
+            Json::Object(fields) => fields,
+            _ => return Err("key bindings must be a JSON object of action names to key names".to_string()),
+        };
+
+        for (name, value) in fields {
+            let action = Action::from_name(name).ok_or_else(|| {
+                let actions: Vec<_> = Action::ALL.iter().map(|action| action.name()).collect();
+                format!("unknown action '{}'; valid actions: {}", name, actions.join(", "))
+            })?;
+            let key_name = value
+                .as_str()
+                .ok_or_else(|| format!("key for action '{}' must be a string", name))?;
+            let key = key_from_name(key_name).ok_or_else(|| {
+                format!("unknown key '{}' for action '{}'; valid keys: {}", key_name, name, valid_key_names())
+            })?;
+            self.bind(action, key);
+        }
+        Ok(())
+    }
+
+    pub fn bind(&mut self, action: Action, key: Key) {
+        match self.bindings.iter_mut().find(|(bound, _)| *bound == action) {
+            Some(binding) => binding.1 = key,
+            None => self.bindings.push((action, key)),
+        }
+    }
+
+    pub fn triggered(&self, window: &Window) -> Vec<Action> {
+        self.bindings
+            .iter()
+            .filter(|&&(action, key)| match action.trigger() {
+                Trigger::Held => window.is_key_down(key),
+                Trigger::Pressed => window.is_key_pressed(key, KeyRepeat::No),
+                Trigger::Repeat => window.is_key_pressed(key, KeyRepeat::Yes),
+            })
+            .map(|&(action, _)| action)
+            .collect()
+    }
+}
@@ -0,0 +1,49 @@
+//! The ray tracer without a window: scene description, the renderer and image output. The interactive binary in
+//! `main.rs` adds the minifb window, key bindings and console on top; a wasm32 build exports `render_to_rgba` to a
+//! page instead.
+
+pub mod framebuffer;
+pub mod ray_intersect;
+pub mod cube;
+pub mod plane;
+pub mod sphere;
+pub mod object;
+pub mod camera;
+pub mod material;
+pub mod render_settings;
+pub mod light;
+pub mod render_stats;
+pub mod texture;
+pub mod environment;
+pub mod sampling;
+pub mod font;
+pub mod json;
+pub mod app_state;
+pub mod animation;
+pub mod sky;
+pub mod scene;
+pub mod beach;
+pub mod scene_builder;
+pub mod transform;
+pub mod instance;
+pub mod tone_map;
+pub mod color_space;
+pub mod noise;
+pub mod shadow_map;
+pub mod temporal;
+pub mod editor;
+pub mod budget;
+pub mod passes;
+pub mod scatter;
+pub mod caustics;
+pub mod clock;
+pub mod exposure;
+pub mod gizmo;
+pub mod jobs;
+pub mod timer;
+pub mod renderer;
+pub mod render_pool;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+
+pub use tracer_core::{color, math, optics};
@@ -0,0 +1,116 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::material::Material;
+use crate::math::saturate;
+
+const EMISSIVE_RANGE_SCALE: f32 = 3.0;
+const MIN_FACING_WEIGHT: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub position: Vec3,
+    pub color: Color,
+    pub range: Option<f32>,
+    /// When false the light skips the occlusion test entirely.
+    pub casts_shadows: bool,
+    /// Multiplier on the global light radius that sets this light's penumbra spread.
+    pub shadow_softness: f32,
+    /// Removes its diffuse contribution instead of adding it, to darken a region; it casts no highlight.
+    pub negative: bool,
+}
+
+impl Light {
+    pub fn new(position: Vec3, color: Color) -> Self {
+        Light {
+            position,
+            color,
+            range: None,
+            casts_shadows: true,
+            shadow_softness: 1.0,
+            negative: false,
+        }
+    }
+
+    pub fn white(position: Vec3) -> Self {
+        Light::new(position, Color::new(255, 255, 255))
+    }
+
+    pub fn from_emissive(position: Vec3, material: &Material) -> Self {
+        let intensity = material.emission.luminance() / 255.0;
+        Light {
+            position,
+            color: material.emission,
+            range: Some(EMISSIVE_RANGE_SCALE * intensity.max(0.0).sqrt()),
+            casts_shadows: true,
+            shadow_softness: 1.0,
+            negative: false,
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn without_shadows(mut self) -> Self {
+        self.casts_shadows = false;
+        self
+    }
+
+    pub fn with_shadow_softness(mut self, softness: f32) -> Self {
+        self.shadow_softness = softness;
+        self
+    }
+
+    pub fn negative(mut self) -> Self {
+        self.negative = true;
+        self
+    }
+
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        let range = match self.range {
+            Some(range) => range,
+            None => return 1.0,
+        };
+        if distance >= range {
+            return 0.0;
+        }
+
+        let ratio = distance / range;
+        let window = saturate(1.0 - ratio * ratio * ratio * ratio);
+        window * window * (1.0 / (distance * distance)).min(1.0)
+    }
+
+    pub fn importance(&self, point: &Vec3, normal: &Vec3) -> f32 {
+        let to_light = self.position - point;
+        let distance = to_light.magnitude();
+        let facing = (normal.dot(&to_light) / distance.max(f32::EPSILON)).max(0.0);
+        self.color.luminance() * self.attenuation(distance) * (MIN_FACING_WEIGHT + (1.0 - MIN_FACING_WEIGHT) * facing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emitters_contribute_nothing_beyond_their_range() {
+        let lamp = Light::from_emissive(Vec3::zeros(), &Material::emissive(Color::new(255, 220, 180), 4.0));
+        let range = lamp.range.unwrap();
+        assert!(lamp.attenuation(range * 0.5) > 0.0);
+        assert_eq!(lamp.attenuation(range), 0.0);
+        assert_eq!(lamp.attenuation(range * 2.0), 0.0);
+
+        let floor = Vec3::new(0.0, -range * 1.01, 0.0);
+        assert_eq!(lamp.importance(&floor, &Vec3::new(0.0, 1.0, 0.0)), 0.0);
+        assert!(lamp.attenuation(range * 0.999) < 1e-3, "falloff should reach zero smoothly at the edge");
+    }
+
+    #[test]
+    fn range_grows_with_intensity_and_can_be_overridden() {
+        let dim = Light::from_emissive(Vec3::zeros(), &Material::emissive(Color::new(255, 255, 255), 1.0));
+        let bright = Light::from_emissive(Vec3::zeros(), &Material::emissive(Color::new(255, 255, 255), 9.0));
+        assert!(bright.range > dim.range);
+        assert_eq!(dim.with_range(2.5).range, Some(2.5));
+    }
+}
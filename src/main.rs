@@ -1,297 +1,436 @@
-mod framebuffer;
-mod ray_intersect;
-mod cube;
-mod color;
-mod camera;
-mod material;
-
-use minifb::{Window, WindowOptions, Key};
-use nalgebra_glm::{Vec3, normalize};
-use std::time::{Duration, Instant}; 
-use std::f32::consts::PI;
-
-use crate::color::Color;
-use crate::ray_intersect::{Intersect, RayIntersect};
-use crate::cube::Cube;
-use crate::framebuffer::Framebuffer;
-use crate::camera::Camera;
-use crate::material::Material;
-
-const ORIGIN_BIAS: f32 = 1e-4;
-const SKYBOX_COLOR: Color = Color::new(68, 142, 228);
-
-#[derive(Clone)]
-enum Object {
-    Cube(Cube, bool),
+mod key_bindings;
+mod console;
+
+use minifb::{Window, WindowOptions, Key, MouseButton, MouseMode};
+use nalgebra_glm::Vec3;
+use std::time::Duration;
+use proyecto3gc::{editor, exposure, gizmo, jobs};
+use proyecto3gc::color::Color;
+use proyecto3gc::framebuffer::Framebuffer;
+use proyecto3gc::camera::Camera;
+use proyecto3gc::render_settings::{RenderSettings, ShadowMode, SpecularModel};
+use proyecto3gc::texture::Texture;
+use proyecto3gc::environment::{EnvMap, Environment, SkyGradient};
+use proyecto3gc::app_state::AppState;
+use proyecto3gc::sky::AnalyticSky;
+use proyecto3gc::scene::Scene;
+use proyecto3gc::shadow_map::ShadowMap;
+use proyecto3gc::caustics::CausticsMap;
+use proyecto3gc::temporal::TemporalHistory;
+use proyecto3gc::budget::{FrameBudget, DEFAULT_FRAME_BUDGET_MS};
+use proyecto3gc::clock::{Clock, DEFAULT_RECORDING_FPS};
+use proyecto3gc::timer::Instant;
+use proyecto3gc::exposure::EXPOSURE_TARGETS;
+use proyecto3gc::beach::{Beach, SUN_LIGHT, WATER_RESOLUTIONS};
+use proyecto3gc::render_pool::RenderPool;
+use proyecto3gc::renderer::{apply_post_effects, calculate_light_intensity, configure_display, render_monitor, render_motion_blurred, render_offscreen};
+use crate::key_bindings::{Action, KeyBindings};
+use crate::console::{Command, Console};
+
+const LIGHT_COLOR_PRESETS: [Color; 5] = [
+    Color::new(255, 255, 255),
+    Color::new(255, 223, 0),
+    Color::new(255, 140, 60),
+    Color::new(150, 190, 255),
+    Color::new(255, 120, 200),
+];
+const LIGHT_SCALE_STEP: f32 = 1.1;
+const SKY_ROTATION_SPEED: f32 = 0.02;
+const EXPOSURE_STEP: f32 = 1.25;
+const CHROMATIC_ABERRATION_STRENGTH: f32 = 0.01;
+const FIREFLY_CLAMP_MULTIPLE: f32 = 4.0;
+const REFLECTION_CLAMP: f32 = 1.0;
+const RADIANCE_CLAMP: f32 = 4.0;
+const TURBIDITY_STEP: f32 = 0.25;
+const TURBIDITY_RANGE: (f32, f32) = (1.7, 10.0);
+const HUD_COLOR: u32 = 0xFFFFFF;
+const FOCUS_PEAKING_COLOR: u32 = 0x39FF14;
+const FOCUS_PEAKING_TOLERANCE: f32 = 0.03;
+const FOCUS_STEP: f32 = 1.05;
+const CONVERGED_IMAGE_PATH: &str = "converged.png";
+const APP_STATE_PATH: &str = "app.state";
+const SCREENSHOT_PATH: &str = "screenshot.png";
+const TURNTABLE_DIR: &str = "turntable";
+const HDR_IMAGE_PATH: &str = "frame.hdr";
+const SUN_ORBIT_RADIUS: f32 = 15.0;
+const CAMERA_SHAKE_AMPLITUDE: f32 = 0.08;
+const WIND_STRENGTH: f32 = 0.12;
+const SHADOW_MAP_RESOLUTION: usize = 256;
+const SHADOW_MAP_HALF_EXTENT: f32 = 9.0;
+const SCENE_CENTER: Vec3 = Vec3::new(0.0, 5.0, 0.0);
+const CAUSTICS_RESOLUTION: usize = 64;
+const CAUSTICS_DEPTH: f32 = 3.0;
+const WATER_REFRACTIVE_INDEX: f32 = 1.33;
+const TEMPORAL_BLEND: f32 = 0.9;
+const MONITOR_RESOLUTION: (usize, usize) = (80, 60);
+
+
+fn sun_position(sun_angle: f32) -> Vec3 {
+    Vec3::new(SUN_ORBIT_RADIUS * sun_angle.cos(), SUN_ORBIT_RADIUS * sun_angle.sin(), 0.0)
 }
 
-fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3 {
-    let offset = intersect.normal * ORIGIN_BIAS;
-    if direction.dot(&intersect.normal) < 0.0 {
-        intersect.point - offset
+fn update_scene(scene: &mut Scene, beach: &Beach, state: &AppState, elapsed_time: f32) {
+    let sun = sun_position(state.sun_angle);
+    scene.lights = beach.lights(sun, LIGHT_COLOR_PRESETS[state.light_color_index % LIGHT_COLOR_PRESETS.len()]);
+    scene.objects = beach.objects_at(elapsed_time, state.settings.wind_strength);
+    scene.prototypes.clone_from(&beach.prototypes);
+    scene.environment.light_intensity = calculate_light_intensity(&sun);
+    scene.environment.sky_rotation = elapsed_time * state.settings.sky_rotation_speed;
+    scene.environment.filter_reflections = state.settings.bilinear_reflections;
+    scene.environment.analytic_sky = if state.settings.analytic_sky && sun.y > 0.0 {
+        Some(AnalyticSky::new(sun, state.settings.sky_turbidity))
     } else {
-        intersect.point + offset
-    }
+        None
+    };
+    scene.shadow_map = match state.settings.shadow_mode {
+        ShadowMode::ShadowMap => Some((
+            SUN_LIGHT,
+            ShadowMap::build(&scene.objects, &scene.prototypes, sun, SCENE_CENTER, SHADOW_MAP_HALF_EXTENT, SHADOW_MAP_RESOLUTION),
+        )),
+        ShadowMode::RayTraced | ShadowMode::Blob => None,
+    };
+    scene.caustics = if state.settings.caustics && sun.y > beach.water.origin.y {
+        let to_sun = sun - beach.water.center();
+        Some((SUN_LIGHT, CausticsMap::trace(&beach.water, elapsed_time, to_sun, CAUSTICS_DEPTH, WATER_REFRACTIVE_INDEX, CAUSTICS_RESOLUTION)))
+    } else {
+        None
+    };
 }
 
-fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
-    incident - 2.0 * incident.dot(normal) * normal
-}
+fn load_env_map() -> EnvMap {
+    let args: Vec<String> = std::env::args().collect();
+    let path = match args.iter().position(|arg| arg == "--env") {
+        Some(index) => match args.get(index + 1) {
+            Some(path) => path,
+            None => {
+                eprintln!("--env requires an image path");
+                return EnvMap::Gradient(SkyGradient::default());
+            }
+        },
+        None => return EnvMap::Gradient(SkyGradient::default()),
+    };
 
-fn cast_shadow(
-    intersect: &Intersect,
-    light_position: &Vec3,
-    objects: &[Object],
-) -> f32 {
-    let light_dir = (light_position - intersect.point).normalize();
-    let light_distance = (light_position - intersect.point).magnitude();
-
-    let shadow_ray_origin = offset_origin(intersect, &light_dir);
-    let mut shadow_intensity = 0.0;
-
-    for object in objects {
-        let shadow_intersect = match object {
-            Object::Cube(cube, _) => cube.ray_intersect(&shadow_ray_origin, &light_dir),
-        };
-        if shadow_intersect.is_intersecting && shadow_intersect.distance < light_distance {
-            let distance_ratio = shadow_intersect.distance / light_distance;
-            shadow_intensity = 1.0 - distance_ratio.powf(2.0).min(1.0);
-            break;
+    match Texture::load(path) {
+        Ok(texture) => EnvMap::Equirect(texture),
+        Err(err) => {
+            eprintln!("failed to load environment map {}: {}", path, err);
+            EnvMap::Gradient(SkyGradient::default())
         }
     }
-
-    shadow_intensity
 }
 
-fn interpolate_color(color1: Color, color2: Color, factor: f32) -> Color {
-    Color::new(
-        (color1.red() as f32 * (1.0 - factor) + color2.red() as f32 * factor) as u8,
-        (color1.green() as f32 * (1.0 - factor) + color2.green() as f32 * factor) as u8,
-        (color1.blue() as f32 * (1.0 - factor) + color2.blue() as f32 * factor) as u8,
-    )
+fn report_scene_warnings(scene: &Scene) {
+    for warning in scene.validate() {
+        eprintln!("scene warning: {}", warning);
+    }
 }
 
-fn calculate_light_intensity(light_position: &Vec3) -> f32 {
-    let max_intensity = 1.0;  
-    let min_intensity = 0.2;  
-
-    let light_height_factor = (light_position.y + 1.0).max(0.0) / 10.0;  
+/// Directory passed to `--record`, created if needed. Recording saves every frame and animates on a fixed clock.
+fn recording_dir() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--record")?;
+    let dir = match args.get(index + 1) {
+        Some(dir) => dir.clone(),
+        None => {
+            eprintln!("--record requires an output directory");
+            std::process::exit(2);
+        }
+    };
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        eprintln!("failed to create {}: {}", dir, err);
+        std::process::exit(2);
+    }
+    Some(dir)
+}
 
-    min_intensity + (max_intensity - min_intensity) * light_height_factor.clamp(0.0, 1.0)
+fn recording_fps() -> f32 {
+    let args: Vec<String> = std::env::args().collect();
+    let index = match args.iter().position(|arg| arg == "--fps") {
+        Some(index) => index,
+        None => return DEFAULT_RECORDING_FPS,
+    };
+    match args.get(index + 1).map(|fps| fps.parse::<f32>()) {
+        Some(Ok(fps)) if fps > 0.0 && fps.is_finite() => fps,
+        _ => {
+            eprintln!("--fps requires a positive number");
+            std::process::exit(2);
+        }
+    }
 }
 
+fn auto_frame_requested() -> bool {
+    std::env::args().any(|arg| arg == "--auto-frame")
+}
 
-fn skybox_color(ray_direction: &Vec3, light_intensity: f32) -> Color {
-    let t = 0.5 * (ray_direction.y + 1.0);  
+fn load_backdrop() -> Option<Texture> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--backdrop")?;
+    let path = match args.get(index + 1) {
+        Some(path) => path,
+        None => {
+            eprintln!("--backdrop requires an image path");
+            return None;
+        }
+    };
 
-    let sky_color_day = Color::new(135, 206, 235);  
-    let ground_color_day = Color::new(222, 184, 135);  
+    match Texture::load(path) {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            eprintln!("failed to load backdrop {}: {}", path, err);
+            None
+        }
+    }
+}
 
-    let sky_color_night = Color::new(25, 25, 112);  
-    let ground_color_night = Color::new(50, 50, 50);  
+fn load_key_bindings() -> KeyBindings {
+    let args: Vec<String> = std::env::args().collect();
+    let path = match args.iter().position(|arg| arg == "--keys") {
+        Some(index) => match args.get(index + 1) {
+            Some(path) => path,
+            None => {
+                eprintln!("--keys requires a key bindings file path");
+                return KeyBindings::default();
+            }
+        },
+        None => return KeyBindings::default(),
+    };
 
-    let sky_color = interpolate_color(sky_color_night, sky_color_day, light_intensity);
-    let ground_color = interpolate_color(ground_color_night, ground_color_day, light_intensity);
+    match KeyBindings::load(path) {
+        Ok(bindings) => bindings,
+        Err(err) => {
+            eprintln!("failed to load key bindings {}: {}", path, err);
+            KeyBindings::default()
+        }
+    }
+}
 
-    let blended_color = Color::new(
-        ((1.0 - t) * ground_color.red() as f32 + t * sky_color.red() as f32) as u8,
-        ((1.0 - t) * ground_color.green() as f32 + t * sky_color.green() as f32) as u8,
-        ((1.0 - t) * ground_color.blue() as f32 + t * sky_color.blue() as f32) as u8,
+fn draw_hud(framebuffer: &mut Framebuffer, settings: &RenderSettings, frame_time: Duration) {
+    let on_off = |flag: bool| if flag { "ON" } else { "OFF" };
+    let specular = match settings.specular_model {
+        SpecularModel::Phong => "PHONG",
+        SpecularModel::BlinnPhong => "BLINN-PHONG",
+    };
+    let text = format!(
+        "FRAME {:.1} MS\nCHANNEL {:?}\nEXPOSURE {:.2}{}\nFOCUS {:.2}\nTONE MAP {:?}\nCOLOR SPACE {:?}\nLIGHT SCALE {:.2}\nSHADOWS {:?}\nANTIALIASING {} {:?} {:?}\nEDGE AA {}\nSHADOW SAMPLES {}\nLIGHT SAMPLES {}\nSKYLIGHT SAMPLES {}\nSPECULAR {}\nSPECULAR KNEE {:.2}\nMOTION BLUR {}\nPOST BLUR {:?}\nBLOOM {}\nCAUSTICS {}\nDEPTH TIE BREAK {}\nTEMPORAL {}\nFRAME BUDGET {}\nFIREFLY CLAMP {}\nENERGY CLAMP {}\nSKY {}\nENV REFLECTION {}\nENV FILTER {}\nNAN CHECK {}",
+        frame_time.as_secs_f32() * 1000.0,
+        settings.channel,
+        settings.exposure,
+        if settings.auto_exposure { format!(" AUTO {:.2}", settings.exposure_target) } else { String::new() },
+        settings.focus_distance,
+        settings.tone_map,
+        settings.color_space,
+        settings.light_scale,
+        settings.shadow_mode,
+        settings.aa_samples.max(1),
+        settings.sample_pattern,
+        settings.reconstruction_filter,
+        on_off(settings.edge_antialiasing),
+        settings.shadow_samples,
+        if settings.light_samples == 0 { "ALL".to_string() } else { settings.light_samples.to_string() },
+        settings.skylight_samples,
+        specular,
+        settings.specular_knee,
+        settings.motion_blur_samples,
+        settings.post_blur,
+        on_off(settings.bloom),
+        if settings.caustics { format!("{:.1}", settings.caustics_strength) } else { "OFF".to_string() },
+        on_off(settings.depth_tie_break),
+        on_off(settings.temporal_blend > 0.0),
+        if settings.frame_budget_ms > 0.0 { format!("{:.0} MS", settings.frame_budget_ms) } else { "OFF".to_string() },
+        settings.firefly_clamp.map_or("OFF".to_string(), |multiple| format!("{:.1}X MEAN", multiple)),
+        match (settings.reflection_clamp, settings.radiance_clamp) {
+            (None, None) => "OFF".to_string(),
+            (reflection, radiance) => format!(
+                "REFLECTION {} TOTAL {}",
+                reflection.map_or("-".to_string(), |limit| format!("{:.1}", limit)),
+                radiance.map_or("-".to_string(), |limit| format!("{:.1}", limit)),
+            ),
+        },
+        if settings.analytic_sky { format!("ANALYTIC T={:.2}", settings.sky_turbidity) } else { "GRADIENT".to_string() },
+        on_off(settings.env_reflection),
+        if settings.bilinear_reflections { "BILINEAR" } else { "POINT" },
+        on_off(settings.debug_non_finite),
     );
-
-    blended_color
-}
-fn fresnel(cos_theta: f32, refractive_index: f32) -> f32 {
-    let r0 = ((1.0 - refractive_index) / (1.0 + refractive_index)).powi(2);
-    r0 + (1.0 - r0) * (1.0 - cos_theta).powi(5)
+    framebuffer.draw_text(8, 8, &text, HUD_COLOR);
 }
-pub fn cast_ray(
-    ray_origin: &Vec3,
-    ray_direction: &Vec3,
-    objects: &[Object],
-    light_positions: &[Vec3],
-    depth: u32,
-    light_intensity: f32,
-) -> Color {
-    if depth > 3 {
-        return SKYBOX_COLOR;
-    }
-
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = f32::INFINITY;
 
-    for object in objects {
-        let i = match object {
-            Object::Cube(cube, _) => cube.ray_intersect(ray_origin, ray_direction),
-        };
-        if i.is_intersecting && i.distance < zbuffer {
-            zbuffer = i.distance;
-            intersect = i;
+/// Ends the window session: saves the last frame as the converged image, then stops the render threads and
+/// waits for them to exit.
+fn shutdown(framebuffer: &Framebuffer, frames_rendered: usize, render_pool: &mut RenderPool) {
+    if frames_rendered > 0 {
+        match framebuffer.save_png(CONVERGED_IMAGE_PATH) {
+            Ok(()) => println!("saved final frame to {}", CONVERGED_IMAGE_PATH),
+            Err(err) => eprintln!("failed to save {}: {}", CONVERGED_IMAGE_PATH, err),
         }
     }
+    render_pool.shutdown();
+}
 
-    if !intersect.is_intersecting {
-        return skybox_color(ray_direction, light_intensity);
+fn initial_state() -> AppState {
+    let mut state = AppState::new(Camera::new(
+        Vec3::new(5.0, 7.0, 10.0), 
+        Vec3::new(0.0, 5.0, 0.0),  
+        Vec3::new(0.0, 1.0, 0.0),  
+    ));
+    if let Some(threads) = thread_count_arg() {
+        state.settings.threads = threads;
     }
-
-    let mut total_diffuse = Color::black();
-    let mut total_specular = Color::black();
-
-    for light_position in light_positions {
-        let light_dir = (light_position - intersect.point).normalize();
-        let view_dir = (ray_origin - intersect.point).normalize();
-        let reflect_dir = reflect(&-light_dir, &intersect.normal).normalize();
-
-        let shadow_intensity = cast_shadow(&intersect, light_position, objects);
-        let light_intensity = 1.5 * (1.0 - shadow_intensity);
-
-        let cos_theta = -ray_direction.dot(&intersect.normal).max(0.0);
-        let fresnel_effect = fresnel(cos_theta, intersect.material.refractive_index);
-
-        let diffuse_intensity = intersect.normal.dot(&light_dir).max(0.0).min(1.0);
-        total_diffuse = total_diffuse
-            + (intersect.material.diffuse * intersect.material.albedo[0] * diffuse_intensity * light_intensity);
-
-        let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(intersect.material.specular);
-        total_specular = total_specular
-            + (Color::new(255, 255, 255) * intersect.material.albedo[1] * specular_intensity * light_intensity * fresnel_effect);
+    if let Some(pixel_aspect) = pixel_aspect_arg() {
+        state.camera.pixel_aspect = pixel_aspect;
     }
-
-    let emission = if intersect.material.is_emissive {
-        intersect.material.emission
-    } else {
-        Color::black()
-    };
-
-    total_diffuse + total_specular + emission
+    state
 }
 
+fn pixel_aspect_arg() -> Option<f32> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--pixel-aspect")?;
+    match args.get(index + 1).map(|aspect| aspect.parse::<f32>()) {
+        Some(Ok(aspect)) if aspect > 0.0 && aspect.is_finite() => Some(aspect),
+        _ => {
+            eprintln!("--pixel-aspect requires a positive number");
+            std::process::exit(2);
+        }
+    }
+}
 
+fn thread_count_arg() -> Option<usize> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--threads")?;
+    match args.get(index + 1).map(|count| count.parse::<usize>()) {
+        Some(Ok(count)) if count > 0 => Some(count),
+        _ => {
+            eprintln!("--threads requires a positive thread count");
+            std::process::exit(2);
+        }
+    }
+}
 
-pub fn render(
-    framebuffer: &mut Framebuffer,
-    objects: &[Object],
-    camera: &Camera,
-    light_positions: &[Vec3],  
-    light_intensity: f32,  
-) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
-    let fov = PI / 3.0;
-    let perspective_scale = (fov * 0.5).tan();
-
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            let screen_x = (2.0 * x as f32) / width - 1.0;
-            let screen_y = -(2.0 * y as f32) / height + 1.0;
-
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
-            let screen_y = screen_y * perspective_scale;
-
-            let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
-            let rotated_direction = camera.base_change(&ray_direction);
-
-            let pixel_color = cast_ray(&camera.eye, &rotated_direction, objects, light_positions, 0, light_intensity);
-
-            framebuffer.set_current_color(pixel_color.to_hex());
-            framebuffer.point(x, y);
+fn headless_output() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--headless")?;
+    match args.get(index + 1) {
+        Some(path) => Some(path.clone()),
+        None => {
+            eprintln!("--headless requires an output image path");
+            std::process::exit(2);
         }
     }
 }
 
+fn jobs_path() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--jobs")?;
+    match args.get(index + 1) {
+        Some(path) => Some(path.clone()),
+        None => {
+            eprintln!("--jobs requires a job list path");
+            std::process::exit(2);
+        }
+    }
+}
 
-fn generate_wave_grid(
-    water_material: Material, 
-    grid_size: usize, 
-    cube_size: f32, 
-    elapsed_time: f32
-) -> Vec<Object> {
-    let mut water_cubes = Vec::new();
-    for x in 0..grid_size {
-        for z in 0..grid_size {
-            let wave_height = (elapsed_time * 2.0 + (x as f32 + z as f32) * 0.5).sin() * 0.2; 
-            water_cubes.push(Object::Cube(
-                Cube {
-                    center: Vec3::new(x as f32 * cube_size, 4.9 + wave_height, z as f32 * cube_size),
-                    size: cube_size,
-                    material: water_material,
-                },
-                false,
-            ));
+/// Renders every job of a job list in order, each as its own headless still.
+fn render_jobs(path: &str, default_size: (usize, usize)) {
+    let mut jobs = match jobs::load(path, &initial_state(), default_size) {
+        Ok(jobs) => jobs,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            std::process::exit(2);
         }
+    };
+    let count = jobs.len();
+    let mut pool = RenderPool::new(initial_state().settings.threads);
+    for (index, job) in jobs.iter_mut().enumerate() {
+        println!("job {}/{}: {} ({}x{}, {} samples)", index + 1, count, job.output, job.width, job.height, job.state.settings.aa_samples.max(1));
+        let start = Instant::now();
+        render_still(&mut pool, &mut job.state, job.time, &job.output, job.width, job.height);
+        println!("job {}/{} finished in {:.1} s", index + 1, count, start.elapsed().as_secs_f32());
     }
-    water_cubes
 }
 
-fn generate_sand_border(sand_material: Material, grid_size: usize, cube_size: f32) -> Vec<Object> {
-    let mut sand_cubes = Vec::new();
-    for x in 0..grid_size {
-        for z in 0..grid_size {
-            if x == 0 || x == grid_size - 1 || z == 0 || z == grid_size - 1 {
-                sand_cubes.push(Object::Cube(
-                    Cube {
-                        center: Vec3::new(x as f32 * cube_size, 4.9, z as f32 * cube_size),
-                        size: cube_size,
-                        material: sand_material,
-                    },
-                    false,
-                ));
-            }
+fn turntable_frames() -> Option<usize> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--turntable")?;
+    match args.get(index + 1).map(|frames| frames.parse::<usize>()) {
+        Some(Ok(frames)) if frames > 0 => Some(frames),
+        _ => {
+            eprintln!("--turntable requires a positive frame count");
+            std::process::exit(2);
         }
     }
-    sand_cubes
 }
 
-fn generate_sand_house(sand_material: Material, start_position: Vec3, cube_size: f32) -> Vec<Object> {
-    let mut house_cubes = Vec::new();
-
-    let house_width = 5;  
-    let house_height = 3;  
-    let house_depth = 5;  
-
-    for x in 0..house_width {
-        for y in 0..house_height {
-            for z in 0..house_depth {
-                let is_door = x == 2 && z == 0 && y < 2;  
-                let is_window = y == 1 && (x == 1 || x == 3) && (z == 0 || z == house_depth - 1);  
-                
-                if !(is_door || is_window) {  
-                    house_cubes.push(Object::Cube(
-                        Cube {
-                            center: Vec3::new(
-                                start_position.x + x as f32 * cube_size,
-                                start_position.y + y as f32 * cube_size,
-                                start_position.z + z as f32 * cube_size,
-                            ),
-                            size: cube_size,
-                            material: sand_material,
-                        },
-                        false,
-                    ));
-                }
+/// The `--record` directory, or `TURNTABLE_DIR` created in the working directory.
+fn turntable_dir() -> String {
+    match recording_dir() {
+        Some(dir) => dir,
+        None => {
+            if let Err(err) = std::fs::create_dir_all(TURNTABLE_DIR) {
+                eprintln!("failed to create {}: {}", TURNTABLE_DIR, err);
+                std::process::exit(2);
             }
+            TURNTABLE_DIR.to_string()
         }
     }
+}
 
-    for x in 0..house_width {
-        for z in 0..house_depth {
-            house_cubes.push(Object::Cube(
-                Cube {
-                    center: Vec3::new(
-                        start_position.x + x as f32 * cube_size,
-                        start_position.y + house_height as f32 * cube_size,  
-                        start_position.z + z as f32 * cube_size,
-                    ),
-                    size: cube_size,
-                    material: sand_material,
-                },
-                false,
-            ));
-        }
+/// Auto-frames the scene, then orbits the camera a full turn around the bounding-box center in `frames` even
+/// steps.
+fn turntable_states(frames: usize) -> Vec<AppState> {
+    let mut scene = Scene::new(Environment::new(load_env_map()));
+    let mut framed = initial_state();
+    update_scene(&mut scene, &Beach::new(), &framed, 0.0);
+    framed.camera.frame(scene.bounding_box());
+    let pivot = framed.camera.center;
+
+    (0..frames)
+        .map(|frame| {
+            let mut state = framed.clone();
+            state.camera.orbit_around(pivot, std::f32::consts::TAU * frame as f32 / frames as f32, 0.0);
+            state
+        })
+        .collect()
+}
+
+/// Renders the turntable into `dir` as numbered PNGs.
+fn render_turntable(frames: usize, dir: &str, width: usize, height: usize) {
+    let mut pool = RenderPool::new(initial_state().settings.threads);
+    for (frame, mut state) in turntable_states(frames).into_iter().enumerate() {
+        render_still(&mut pool, &mut state, 0.0, &format!("{}/frame_{:05}.png", dir, frame), width, height);
     }
+}
 
-    house_cubes
+fn render_headless(path: &str, width: usize, height: usize) {
+    let mut state = initial_state();
+    render_still(&mut RenderPool::new(state.settings.threads), &mut state, 0.0, path, width, height);
+}
+
+fn render_still(pool: &mut RenderPool, state: &mut AppState, elapsed_time: f32, path: &str, width: usize, height: usize) {
+    let beach = Beach::new();
+    let mut scene = Scene::new(Environment::new(load_env_map()));
+    update_scene(&mut scene, &beach, state, elapsed_time);
+    report_scene_warnings(&scene);
+    if auto_frame_requested() {
+        state.camera.frame(scene.bounding_box());
+    }
+    let mut monitor = Framebuffer::new(MONITOR_RESOLUTION.0, MONITOR_RESOLUTION.1);
+    let settings = state.settings;
+    pool.install(settings.threads, || render_monitor(&mut scene, &mut monitor, &beach.monitor_camera, &settings));
+
+    let record_passes = std::env::args().any(|arg| arg == "--passes");
+    let backdrop = load_backdrop();
+    let framebuffer = pool.install(settings.threads, || {
+        render_offscreen(&scene, &state.camera, &settings, width, height, backdrop.as_ref(), record_passes)
+    });
+    match image::save_buffer(path, &framebuffer.to_rgba(), width as u32, height as u32, image::ColorType::Rgba8) {
+        Ok(()) => println!("saved headless render to {}", path),
+        Err(err) => eprintln!("failed to save {}: {}", path, err),
+    }
+    match framebuffer.save_passes(path) {
+        Ok(written) if !written.is_empty() => println!("saved render passes to {}", written.join(", ")),
+        Ok(_) => {}
+        Err(err) => eprintln!("failed to save render passes: {}", err),
+    }
 }
 
 fn main() {
@@ -301,7 +440,23 @@ fn main() {
     let framebuffer_height = 600;
     let frame_delay = Duration::from_millis(16);
 
+    if let Some(frames) = turntable_frames() {
+        render_turntable(frames, &turntable_dir(), framebuffer_width, framebuffer_height);
+        return;
+    }
+    if let Some(path) = jobs_path() {
+        render_jobs(&path, (framebuffer_width, framebuffer_height));
+        return;
+    }
+    if let Some(path) = headless_output() {
+        render_headless(&path, framebuffer_width, framebuffer_height);
+        return;
+    }
+
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    if let Some(backdrop) = load_backdrop() {
+        framebuffer.set_backdrop(&backdrop);
+    }
 
     let mut window = Window::new(
         "Refractor",
@@ -311,159 +466,422 @@ fn main() {
     )
     .unwrap();
 
+    let key_bindings = load_key_bindings();
+    let mut console = Console::attach(&mut window);
+    let mut monitor = Framebuffer::new(MONITOR_RESOLUTION.0, MONITOR_RESOLUTION.1);
+    let mut beach = Beach::new();
+    let mut scene = Scene::new(Environment::new(load_env_map()));
 
-    let sand_color = Material::new(
-        Color::new(237, 201, 175),
-        1.0,
-        [0.9, 0.1, 0.0, 0.0],
-        0.0,
-        Color::black(),  
-        false,           
-    );
-    
-    let brown_trunk = Material::new(
-        Color::new(139, 69, 19),
-        1.0,
-        [0.9, 0.1, 0.0, 0.0],
-        0.0,
-        Color::black(),  
-        false,           
-    );
-    
-    let green_leaf = Material::new(
-        Color::new(34, 139, 34),
-        1.0,
-        [0.9, 0.1, 0.0, 0.0],
-        0.0,
-        Color::black(),  
-        false,           
-    );
-    
-    let water_material = Material::new(
-        Color::new(0, 191, 255),
-        1.0,
-        [0.9, 0.1, 0.0, 0.0],
-        0.0,
-        Color::black(),  
-        false,           
-    );
-    
-    let light_cube_material = Material::new(
-        Color::black(),              
-        0.0,                        
-        [0.0, 0.0, 0.0, 0.0],        
-        0.0,                         
-        Color::new(255, 223, 0),      
-        true                         
-    );
-    
-    let mut objects = vec![
-        Object::Cube(Cube { center: Vec3::new(0.0, 0.0, 0.0), size: 10.0, material: sand_color }, false),
-        Object::Cube(Cube { center: Vec3::new(1.0, 5.2, -4.0), size: 0.5, material: light_cube_material }, true),  
-        Object::Cube(Cube { center: Vec3::new(4.5, 5.2, 2.0), size: 0.5, material: light_cube_material }, true),  
-    ];
-
-    let trunk_start_y = 5.0;  
-    let trunk_cube_size = 0.4;
-    let num_trunk_cubes = 5;  
-
-    for i in 0..num_trunk_cubes {
-        objects.push(Object::Cube(Cube { 
-            center: Vec3::new(0.0, trunk_start_y + i as f32 * trunk_cube_size, 0.0),  
-            size: trunk_cube_size, 
-            material: brown_trunk 
-        }, false));
-    }
+    let recording = recording_dir();
+    let mut clock = match recording {
+        Some(_) => Clock::fixed(recording_fps()),
+        None => Clock::real_time(),
+    };
 
-    let leaf_start_y = trunk_start_y + num_trunk_cubes as f32 * trunk_cube_size; 
-    let leaf_positions = vec![
-        Vec3::new(0.0, leaf_start_y, 0.0),
-        Vec3::new(0.5, leaf_start_y, 0.5),
-        Vec3::new(-0.5, leaf_start_y, 0.5),
-        Vec3::new(0.5, leaf_start_y, -0.5),
-        Vec3::new(-0.5, leaf_start_y, -0.5),
-    ];
-
-    for pos in leaf_positions {
-        objects.push(Object::Cube(Cube { center: pos, size: 0.5, material: green_leaf }, false));
+    let mut state = initial_state();
+    let mut render_pool = RenderPool::new(state.settings.threads);
+    update_scene(&mut scene, &beach, &state, 0.0);
+    report_scene_warnings(&scene);
+    if auto_frame_requested() {
+        state.camera.frame(scene.bounding_box());
     }
 
-    let start_time = Instant::now();  
-
-    let mut camera = Camera::new(
-        Vec3::new(5.0, 5.0, 10.0), 
-        Vec3::new(0.0, 2.0, 0.0),  
-        Vec3::new(0.0, 1.0, 0.0),  
-    );
-
-    let mut angle: f32 = 0.0;
-    let radius = 15.0;
     let rotation_speed = 0.05;
 
+    let mut title_dirty = true;
+    let mut frames_rendered = 0;
+    let mut history = TemporalHistory::new();
+    let mut history_settings = state.settings.history_key();
+    let mut budget = FrameBudget::new();
+    let mut edit_mode = false;
+    let mut focus_peaking = false;
+    let mut show_gizmo = false;
+    let mut selection: Option<usize> = None;
+    let mut material_preset: Option<usize> = None;
+    let mut mouse_was_down = false;
+    let mut last_frame = Instant::now();
+
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        angle += rotation_speed; 
-        
-        let yellow_light_position = Vec3::new(radius * angle.cos(), radius * angle.sin(), 0.0);
-        let light_positions = vec![
-            Vec3::new(1.0, 5.2, -4.0),  
-            Vec3::new(4.5, 5.2, 2.0),   
-            yellow_light_position,       
-        ];
-    
-        let light_intensity = calculate_light_intensity(&yellow_light_position);
+        state.sun_angle += rotation_speed; 
 
-        let elapsed_time = start_time.elapsed().as_secs_f32();
-        
-        let water_grid = generate_wave_grid(water_material, 6, 0.5, elapsed_time);  
-    
-        let sand_border = generate_sand_border(sand_color, 6, 0.5);
-    
-        let sand_house = generate_sand_house(sand_color, Vec3::new(-4.5, 5.2, -4.0), 0.5);
-    
-        let mut objects_with_water_and_house = objects.clone();
-        objects_with_water_and_house.extend(water_grid);
-        objects_with_water_and_house.extend(sand_border);
-        objects_with_water_and_house.extend(sand_house);  
+        let elapsed_time = clock.now();
+        update_scene(&mut scene, &beach, &state, elapsed_time);
+        if let Some(index) = selection.filter(|_| edit_mode) {
+            scene.objects[index] = editor::highlighted(&scene.objects[index], &scene.prototypes);
+        }
+        render_pool.install(state.settings.threads, || render_monitor(&mut scene, &mut monitor, &beach.monitor_camera, &state.settings));
+
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if edit_mode && mouse_down && !mouse_was_down {
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+                selection = editor::pick(&scene, &state.camera, x, y, width, height)
+                    .filter(|&index| index < beach.static_objects.len() && beach.static_objects[index].cube_extent(&beach.prototypes).is_some());
+                material_preset = None;
+            }
+        }
+        mouse_was_down = mouse_down;
     
-      if window.is_key_down(Key::W) {
-        camera.move_camera("forward"); 
-    }
-
-    if window.is_key_down(Key::S) {
-        camera.move_camera("backward");
-    }
-
-    if window.is_key_down(Key::A) {
-        camera.orbit(rotation_speed, 0.0);  
-    }
-
-    if window.is_key_down(Key::D) {
-        camera.orbit(-rotation_speed, 0.0);  
-    }
-
-    if window.is_key_down(Key::Up) {
-        camera.orbit(0.0, -rotation_speed);  
-    }
+        if let Some(line) = console.update(&window) {
+            let result = Command::parse(&line).and_then(|command| command.execute(&mut state, &framebuffer));
+            console.print(result.unwrap_or_else(|err| format!("error: {}", err)));
+            title_dirty = true;
+        }
 
-    if window.is_key_down(Key::Down) {
-        camera.orbit(0.0, rotation_speed);  
-    }
+        let actions = if console.open { Vec::new() } else { key_bindings.triggered(&window) };
+        for action in actions {
+            let selected = selection.filter(|_| edit_mode);
+            match action {
+                Action::ToggleEditMode => {
+                    edit_mode = !edit_mode;
+                    title_dirty = true;
+                }
+                Action::StrafeLeft | Action::StrafeRight | Action::OrbitUp | Action::OrbitDown if selected.is_some() => {
+                    let offset = match action {
+                        Action::StrafeLeft => Vec3::new(-editor::NUDGE_STEP, 0.0, 0.0),
+                        Action::StrafeRight => Vec3::new(editor::NUDGE_STEP, 0.0, 0.0),
+                        Action::OrbitUp => Vec3::new(0.0, 0.0, -editor::NUDGE_STEP),
+                        _ => Vec3::new(0.0, 0.0, editor::NUDGE_STEP),
+                    };
+                    if let Some(index) = selected {
+                        beach.static_objects[index].translate(offset);
+                    }
+                }
+                Action::ToggleMask => {
+                    if let Some(index) = selected {
+                        let visibility = beach.static_objects[index].visibility_mut();
+                        visibility.masked = !visibility.masked;
+                    }
+                }
+                Action::CycleMaterialPreset => {
+                    if let Some(index) = selected {
+                        let preset = editor::cycle_material(&mut beach.static_objects[index], material_preset);
+                        material_preset = Some(preset);
+                        println!("set material of object {} to {}", index, editor::MATERIAL_PRESETS[preset].0);
+                    }
+                }
+                Action::OrbitLeft | Action::OrbitRight if selected.is_some() => {
+                    let yaw = if action == Action::OrbitLeft { rotation_speed } else { -rotation_speed };
+                    match selected.and_then(|index| beach.static_objects[index].cube_extent(&beach.prototypes)) {
+                        Some((pivot, _)) => state.camera.orbit_around(pivot, yaw, 0.0),
+                        None => state.camera.orbit(yaw, 0.0),
+                    }
+                }
+                Action::GrowSelection | Action::ShrinkSelection => {
+                    if let Some(index) = selected {
+                        let factor = if action == Action::GrowSelection { editor::RESIZE_FACTOR } else { 1.0 / editor::RESIZE_FACTOR };
+                        beach.static_objects[index].resize(factor, &beach.prototypes);
+                    }
+                }
+                Action::Forward => state.camera.move_camera("forward"),
+                Action::Backward => state.camera.move_camera("backward"),
+                Action::OrbitLeft => state.camera.orbit(rotation_speed, 0.0),
+                Action::OrbitRight => state.camera.orbit(-rotation_speed, 0.0),
+                Action::OrbitUp => state.camera.orbit(0.0, -rotation_speed),
+                Action::OrbitDown => state.camera.orbit(0.0, rotation_speed),
+                Action::StrafeLeft => state.camera.move_camera("left"),
+                Action::StrafeRight => state.camera.move_camera("right"),
+                Action::ToggleEnvReflection => state.settings.env_reflection = !state.settings.env_reflection,
+                Action::ToggleSkyRotation => {
+                    state.settings.sky_rotation_speed = if state.settings.sky_rotation_speed == 0.0 {
+                        SKY_ROTATION_SPEED
+                    } else {
+                        0.0
+                    };
+                }
+                Action::ToggleChromaticAberration => {
+                    state.settings.chromatic_aberration = if state.settings.chromatic_aberration == 0.0 {
+                        CHROMATIC_ABERRATION_STRENGTH
+                    } else {
+                        0.0
+                    };
+                }
+                Action::CycleSpecularModel => {
+                    state.settings.specular_model = match state.settings.specular_model {
+                        SpecularModel::Phong => SpecularModel::BlinnPhong,
+                        SpecularModel::BlinnPhong => SpecularModel::Phong,
+                    };
+                }
+                Action::CycleShadowSamples => {
+                    state.settings.shadow_samples = match state.settings.shadow_samples {
+                        1 => 4,
+                        4 => 16,
+                        _ => 1,
+                    };
+                }
+                Action::CycleShadowMode => state.settings.shadow_mode = state.settings.shadow_mode.next(),
+                Action::CycleLightSamples => {
+                    state.settings.light_samples = match state.settings.light_samples {
+                        0 => 1,
+                        1 => 2,
+                        _ => 0,
+                    };
+                }
+                Action::CycleSkylightSamples => {
+                    state.settings.skylight_samples = match state.settings.skylight_samples {
+                        0 => 8,
+                        8 => 32,
+                        _ => 0,
+                    };
+                }
+                Action::ToggleFrameBudget => {
+                    state.settings.frame_budget_ms = if state.settings.frame_budget_ms > 0.0 { 0.0 } else { DEFAULT_FRAME_BUDGET_MS };
+                }
+                Action::CycleAntialiasing => {
+                    state.settings.aa_samples = match state.settings.aa_samples {
+                        0 | 1 => 4,
+                        4 => 9,
+                        9 => 16,
+                        _ => 1,
+                    };
+                }
+                Action::CycleSamplePattern => state.settings.sample_pattern = state.settings.sample_pattern.next(),
+                Action::CycleReconstructionFilter => state.settings.reconstruction_filter = state.settings.reconstruction_filter.next(),
+                Action::ToggleEdgeAntialiasing => state.settings.edge_antialiasing = !state.settings.edge_antialiasing,
+                Action::CyclePostBlur => state.settings.post_blur = state.settings.post_blur.next(),
+                Action::ToggleNanCheck => state.settings.debug_non_finite = !state.settings.debug_non_finite,
+                Action::NextChannel => state.settings.channel = state.settings.channel.next(),
+                Action::ToggleLightMarkers => {
+                    for object in beach.static_objects.iter_mut() {
+                        let visibility = object.visibility_mut();
+                        if !visibility.casts_shadow {
+                            visibility.visible_to_camera = !visibility.visible_to_camera;
+                        }
+                    }
+                }
+                Action::ToggleHud => state.show_hud = !state.show_hud,
+                Action::ToggleFocusPeaking => focus_peaking = !focus_peaking,
+                Action::ToggleGizmo => show_gizmo = !show_gizmo,
+                Action::FocusNearer => state.settings.focus_distance /= FOCUS_STEP,
+                Action::FocusFarther => state.settings.focus_distance *= FOCUS_STEP,
+                Action::ToggleTrackLight => state.track_light = !state.track_light,
+                Action::NextLightColor => {
+                    state.light_color_index = (state.light_color_index + 1) % LIGHT_COLOR_PRESETS.len();
+                }
+                Action::LightScaleDown => {
+                    state.settings.light_scale /= LIGHT_SCALE_STEP;
+                    title_dirty = true;
+                }
+                Action::LightScaleUp => {
+                    state.settings.light_scale *= LIGHT_SCALE_STEP;
+                    title_dirty = true;
+                }
+                Action::ExposureDown => {
+                    state.settings.exposure /= EXPOSURE_STEP;
+                    title_dirty = true;
+                }
+                Action::ExposureUp => {
+                    state.settings.exposure *= EXPOSURE_STEP;
+                    title_dirty = true;
+                }
+                Action::CycleWaterResolution => {
+                    let current = WATER_RESOLUTIONS.iter().position(|&resolution| resolution == beach.water.resolution);
+                    beach.water.resolution = WATER_RESOLUTIONS[current.map_or(0, |index| (index + 1) % WATER_RESOLUTIONS.len())];
+                }
+                Action::ToggleCaustics => state.settings.caustics = !state.settings.caustics,
+                Action::ToggleDepthTieBreak => state.settings.depth_tie_break = !state.settings.depth_tie_break,
+                Action::ToggleBloom => state.settings.bloom = !state.settings.bloom,
+                Action::ToggleAutoExposure => state.settings.auto_exposure = !state.settings.auto_exposure,
+                Action::CycleExposureTarget => {
+                    let current = EXPOSURE_TARGETS.iter().position(|&target| target == state.settings.exposure_target);
+                    state.settings.exposure_target = EXPOSURE_TARGETS[current.map_or(0, |index| (index + 1) % EXPOSURE_TARGETS.len())];
+                }
+                Action::SaveState => match state.save(APP_STATE_PATH) {
+                    Ok(()) => println!("saved state to {}", APP_STATE_PATH),
+                    Err(err) => eprintln!("failed to save {}: {}", APP_STATE_PATH, err),
+                },
+                Action::LoadState => match state.load(APP_STATE_PATH) {
+                    Ok(()) => {
+                        println!("restored state from {}", APP_STATE_PATH);
+                        title_dirty = true;
+                    }
+                    Err(err) => eprintln!("failed to restore {}: {}", APP_STATE_PATH, err),
+                },
+                Action::DumpCamera => println!("{}", state.camera_json()),
+                Action::CycleMotionBlur => {
+                    state.settings.motion_blur_samples = match state.settings.motion_blur_samples {
+                        0 => 2,
+                        2 => 4,
+                        _ => 0,
+                    };
+                }
+                Action::ToggleEnergyClamp => {
+                    let enabled = state.settings.radiance_clamp.is_none();
+                    state.settings.reflection_clamp = if enabled { Some(REFLECTION_CLAMP) } else { None };
+                    state.settings.radiance_clamp = if enabled { Some(RADIANCE_CLAMP) } else { None };
+                }
+                Action::ToggleFireflyClamp => {
+                    state.settings.firefly_clamp = match state.settings.firefly_clamp {
+                        Some(_) => None,
+                        None => Some(FIREFLY_CLAMP_MULTIPLE),
+                    };
+                }
+                Action::SaveHdr => match framebuffer.save_hdr(HDR_IMAGE_PATH) {
+                    Ok(()) => println!("saved linear frame to {}", HDR_IMAGE_PATH),
+                    Err(err) => eprintln!("failed to save {}: {}", HDR_IMAGE_PATH, err),
+                },
+                Action::ToggleAnalyticSky => state.settings.analytic_sky = !state.settings.analytic_sky,
+                Action::ToggleBilinearReflections => state.settings.bilinear_reflections = !state.settings.bilinear_reflections,
+                Action::ToggleTemporalReprojection => {
+                    state.settings.temporal_blend = if state.settings.temporal_blend > 0.0 { 0.0 } else { TEMPORAL_BLEND };
+                }
+                Action::ToggleSortedTransparency => state.settings.sorted_transparency = !state.settings.sorted_transparency,
+                Action::ToggleWind => {
+                    state.settings.wind_strength = if state.settings.wind_strength > 0.0 { 0.0 } else { WIND_STRENGTH };
+                }
+                Action::ToggleCameraShake => {
+                    state.settings.camera_shake = if state.settings.camera_shake > 0.0 { 0.0 } else { CAMERA_SHAKE_AMPLITUDE };
+                }
+                Action::TurbidityDown => {
+                    state.settings.sky_turbidity = (state.settings.sky_turbidity - TURBIDITY_STEP).max(TURBIDITY_RANGE.0);
+                }
+                Action::TurbidityUp => {
+                    state.settings.sky_turbidity = (state.settings.sky_turbidity + TURBIDITY_STEP).min(TURBIDITY_RANGE.1);
+                }
+                Action::NextToneMap => state.settings.tone_map = state.settings.tone_map.next(),
+                Action::CycleColorSpace => state.settings.color_space = state.settings.color_space.next(),
+                Action::Screenshot => match framebuffer.save_png(SCREENSHOT_PATH) {
+                    Ok(()) => println!("saved screenshot to {}", SCREENSHOT_PATH),
+                    Err(err) => eprintln!("failed to save {}: {}", SCREENSHOT_PATH, err),
+                },
+            }
+        }
 
-    if window.is_key_down(Key::Left) {
-        camera.move_camera("left");  
-    }
+        if state.track_light {
+            state.camera.look_at(sun_position(state.sun_angle));
+        }
 
-    if window.is_key_down(Key::Right) {
-        camera.move_camera("right");  
+    if title_dirty {
+        window.set_title(&format!(
+            "Refractor - light intensity {:.2} - exposure {:.2}{}",
+            state.settings.light_scale,
+            state.settings.exposure,
+            if edit_mode { " - EDIT (click to select)" } else { "" }
+        ));
+        title_dirty = false;
     }
     
-        render(&mut framebuffer, &objects_with_water_and_house, &camera, &light_positions, light_intensity);
+        configure_display(&mut framebuffer, &state.settings);
+        let frame_start = elapsed_time - clock.frame_duration(last_frame.elapsed().as_secs_f32());
+        let view = state.camera.shaken(state.settings.camera_shake, elapsed_time);
+        let stats = render_pool.install(state.settings.threads, || {
+            render_motion_blurred(&mut framebuffer, &mut scene, &beach, &view, &state.settings, frame_start, elapsed_time)
+        });
+        if state.settings.history_key() != history_settings {
+            history.reset();
+            history_settings = state.settings.history_key();
+        }
+        if state.settings.temporal_blend > 0.0 {
+            history.resolve(&mut framebuffer, &view, state.settings.temporal_blend);
+        }
+        apply_post_effects(&mut framebuffer, &state.settings);
+        if let Some(dir) = &recording {
+            let path = format!("{}/frame_{:05}.png", dir, frames_rendered);
+            if let Err(err) = framebuffer.save_png(&path) {
+                eprintln!("failed to save {}: {}", path, err);
+            }
+        }
+        frames_rendered += 1;
+        clock.advance();
+
+        let frame_time = last_frame.elapsed();
+        last_frame = Instant::now();
+        budget.update(&mut state.settings, frame_time);
+        exposure::adapt(&mut state.settings, &framebuffer);
+
+        // Overlays are only shown in the window: the clean frame is put back once it is presented, so screenshots,
+        // console saves and the converged image never include them.
+        let clean_frame = framebuffer.buffer.clone();
+        if focus_peaking {
+            framebuffer.highlight_depth(state.settings.focus_distance, FOCUS_PEAKING_TOLERANCE, FOCUS_PEAKING_COLOR);
+        }
+        if show_gizmo {
+            gizmo::draw(&mut framebuffer, &view);
+        }
+        if state.show_hud {
+            draw_hud(&mut framebuffer, &state.settings, frame_time);
+        }
+        if console.open {
+            console.draw(&mut framebuffer);
+        }
+
+        if stats.non_finite_pixels > 0 {
+            println!("{} of {} pixels were NaN/Inf", stats.non_finite_pixels, stats.pixels);
+        }
     
         window
             .update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height)
             .unwrap();
+        framebuffer.buffer = clean_frame;
     
         std::thread::sleep(frame_delay);
     }
-    
+
+    shutdown(&framebuffer, frames_rendered, &mut render_pool);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_stops_the_render_threads() {
+        let mut render_pool = RenderPool::new(2);
+        let mut framebuffer = Framebuffer::new(4, 3);
+        let settings = RenderSettings::default();
+        let scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        let camera = initial_state().camera;
+        render_pool.install(2, || proyecto3gc::renderer::render(&mut framebuffer, &scene, &camera, &settings, None));
+        assert_eq!(render_pool.live_workers(), 2);
+
+        shutdown(&framebuffer, 0, &mut render_pool);
+        assert_eq!(render_pool.live_workers(), 0);
+    }
+
+    #[test]
+    fn a_job_list_renders_every_still_at_its_size() {
+        let dir = std::env::temp_dir().join(format!("jobs_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (first, second) = (dir.join("first.png"), dir.join("second.png"));
+        let list = format!(
+            r#"[{{"output": "{}", "width": 16, "height": 12}}, {{"output": "{}", "width": 10, "height": 8, "time": 1.0}}]"#,
+            first.display(),
+            second.display()
+        );
+        let path = dir.join("jobs.json");
+        std::fs::write(&path, list).unwrap();
+
+        render_jobs(path.to_str().unwrap(), (80, 60));
+        assert_eq!(image::image_dimensions(&first).unwrap(), (16, 12));
+        assert_eq!(image::image_dimensions(&second).unwrap(), (10, 8));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn turntable_orbits_evenly_and_writes_every_frame() {
+        let frames = 8;
+        let states = turntable_states(frames);
+        assert_eq!(states.len(), frames);
+        let pivot = states[0].camera.center;
+        let offset = |state: &AppState| state.camera.eye - pivot;
+        let azimuth = |state: &AppState| offset(state).z.atan2(offset(state).x);
+        let radius = offset(&states[0]).magnitude();
+        for (previous, state) in states.iter().zip(&states[1..]) {
+            assert!((state.camera.center - pivot).magnitude() < 1e-3);
+            assert!((offset(state).magnitude() - radius).abs() < 1e-3 * radius);
+            assert!((offset(state).y - offset(&states[0]).y).abs() < 1e-3 * radius);
+            let step = (azimuth(state) - azimuth(previous)).rem_euclid(std::f32::consts::TAU);
+            let step = step.min(std::f32::consts::TAU - step);
+            assert!((step - std::f32::consts::TAU / frames as f32).abs() < 1e-3, "azimuth stepped by {}", step);
+        }
+
+        let dir = std::env::temp_dir().join(format!("turntable_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        render_turntable(3, dir.to_str().unwrap(), 8, 6);
+        for frame in 0..3 {
+            assert_eq!(image::image_dimensions(dir.join(format!("frame_{:05}.png", frame))).unwrap(), (8, 6));
+        }
+        assert!(!dir.join("frame_00003.png").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
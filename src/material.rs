@@ -1,4 +1,5 @@
 use crate::color::Color;
+use crate::math::{clamp, saturate};
 #[derive(Debug, Clone, Copy)]
 pub struct Material {
     pub diffuse: Color,
@@ -7,6 +8,20 @@ pub struct Material {
     pub refractive_index: f32,
     pub emission: Color, 
     pub is_emissive: bool, 
+    pub roughness: f32,
+    pub specular_color: Color,
+    pub monitor: Option<usize>,
+    pub edge_color: Color,
+    pub edge_width: f32,
+    pub opacity: f32,
+    /// Flat tint applied to whatever is seen through a translucent surface, independent of `diffuse`.
+    pub transmission_color: Color,
+    /// Shows `diffuse` as is, skipping lights, shadows, specular and reflection.
+    pub unlit: bool,
+    /// Strength of a thin dielectric layer over the base, which adds its own highlight and reflection and takes
+    /// light away from everything below it by its Fresnel term.
+    pub clear_coat: f32,
+    pub clear_coat_roughness: f32,
 }
 
 impl Material {
@@ -25,9 +40,155 @@ impl Material {
             refractive_index,
             emission,
             is_emissive,
+            roughness: 0.0,
+            specular_color: Color::new(255, 255, 255),
+            monitor: None,
+            edge_color: Color::black(),
+            edge_width: 0.0,
+            opacity: 1.0,
+            transmission_color: Color::new(255, 255, 255),
+            unlit: false,
+            clear_coat: 0.0,
+            clear_coat_roughness: 0.0,
         }
     }
 
+    pub fn with_roughness(mut self, roughness: f32) -> Self {
+        self.roughness = saturate(roughness);
+        self
+    }
+
+    pub fn with_specular_color(mut self, specular_color: Color) -> Self {
+        self.specular_color = specular_color;
+        self
+    }
+
+    pub fn with_edges(mut self, edge_color: Color, edge_width: f32) -> Self {
+        self.edge_color = edge_color;
+        self.edge_width = clamp(edge_width, 0.0, 0.5);
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = saturate(opacity);
+        self
+    }
+
+    pub fn with_transmission_color(mut self, transmission_color: Color) -> Self {
+        self.transmission_color = transmission_color;
+        self
+    }
+
+    pub fn with_clear_coat(mut self, strength: f32, roughness: f32) -> Self {
+        self.clear_coat = saturate(strength);
+        self.clear_coat_roughness = saturate(roughness);
+        self
+    }
+
+    /// Blinn-Phong exponent matching the coat roughness, so a smooth coat gives a tight highlight.
+    pub fn clear_coat_exponent(&self) -> f32 {
+        2.0 / (self.clear_coat_roughness * self.clear_coat_roughness).max(1e-3) - 2.0
+    }
+
+    pub fn unlit(mut self) -> Self {
+        self.unlit = true;
+        self
+    }
+
+    pub fn with_monitor(mut self, index: usize) -> Self {
+        self.monitor = Some(index);
+        self
+    }
+
+    pub fn matte(diffuse: Color) -> Self {
+        Material::new(
+            diffuse,
+            1.0,
+            [0.9, 0.1, 0.0, 0.0],
+            0.0,
+            Color::black(),
+            false,
+        )
+        .with_roughness(0.8)
+    }
+
+    pub fn sand() -> Self {
+        Material::matte(Color::new(237, 201, 175))
+    }
+
+    pub fn water() -> Self {
+        Material::new(
+            Color::new(0, 191, 255),
+            1.0,
+            [0.3, 0.1, 0.0, 0.6],
+            1.33,
+            Color::black(),
+            false,
+        )
+        .with_roughness(0.1)
+        .with_opacity(0.7)
+        .with_transmission_color(Color::new(170, 225, 215))
+    }
+
+    pub fn glass() -> Self {
+        Material::new(
+            Color::new(255, 255, 255),
+            125.0,
+            [0.05, 0.15, 0.1, 0.7],
+            1.5,
+            Color::black(),
+            false,
+        )
+    }
+
+    pub fn mirror() -> Self {
+        Material::new(
+            Color::new(255, 255, 255),
+            1000.0,
+            [0.0, 0.2, 0.8, 0.0],
+            0.0,
+            Color::black(),
+            false,
+        )
+    }
+
+    pub fn metal(color: Color) -> Self {
+        Material::new(
+            color * 0.5,
+            50.0,
+            [0.2, 0.4, 0.4, 0.0],
+            0.0,
+            Color::black(),
+            false,
+        )
+        .with_roughness(0.3)
+        .with_specular_color(color)
+    }
+
+    pub fn emissive(color: Color, intensity: f32) -> Self {
+        Material::new(
+            Color::black(),
+            0.0,
+            [0.0, 0.0, 0.0, 0.0],
+            0.0,
+            color * intensity,
+            true,
+        )
+    }
+
+    pub fn gold() -> Self {
+        Material::new(
+            Color::new(45, 32, 8),
+            60.0,
+            [0.1, 0.9, 0.0, 0.0],
+            0.0,
+            Color::black(),
+            false,
+        )
+        .with_roughness(0.3)
+        .with_specular_color(Color::new(255, 200, 80))
+    }
+
     pub fn black() -> Self {
         Material {
             diffuse: Color::new(0, 0, 0),
@@ -36,6 +197,47 @@ impl Material {
             refractive_index: 0.0,
             emission: Color::black(), 
             is_emissive: false,
+            roughness: 0.0,
+            specular_color: Color::new(255, 255, 255),
+            monitor: None,
+            edge_color: Color::black(),
+            edge_width: 0.0,
+            opacity: 1.0,
+            transmission_color: Color::new(255, 255, 255),
+            unlit: false,
+            clear_coat: 0.0,
+            clear_coat_roughness: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_never_reflect_more_light_than_they_receive() {
+        let presets = [
+            Material::matte(Color::new(128, 128, 128)),
+            Material::sand(),
+            Material::water(),
+            Material::glass(),
+            Material::mirror(),
+            Material::metal(Color::new(200, 200, 210)),
+            Material::emissive(Color::new(255, 240, 200), 2.0),
+            Material::gold(),
+        ];
+        for material in presets {
+            assert!(material.albedo.iter().all(|&weight| weight >= 0.0));
+            assert!(material.albedo.iter().sum::<f32>() <= 1.0 + 1e-6, "{:?}", material.albedo);
         }
     }
+
+    #[test]
+    fn transparent_presets_use_real_refractive_indices() {
+        assert_eq!(Material::water().refractive_index, 1.33);
+        assert_eq!(Material::glass().refractive_index, 1.5);
+        assert_eq!(Material::mirror().albedo[3], 0.0);
+        assert!(Material::emissive(Color::new(255, 255, 255), 1.0).is_emissive);
+    }
 }
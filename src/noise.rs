@@ -0,0 +1,18 @@
+use crate::math::{mix, smoothstep};
+
+fn lattice(index: i32, seed: u32) -> f32 {
+    let mut bits = (index as u32).wrapping_mul(0x9E37_79B9) ^ seed.wrapping_mul(0x85EB_CA6B);
+    bits ^= bits >> 15;
+    bits = bits.wrapping_mul(0x2C1B_3C6D);
+    bits ^= bits >> 12;
+    bits = bits.wrapping_mul(0x297A_2D39);
+    bits ^= bits >> 15;
+    (bits >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+}
+
+/// Smooth 1D value noise in [-1, 1]; each `seed` gives an independent curve.
+pub fn value_noise(x: f32, seed: u32) -> f32 {
+    let cell = x.floor();
+    let t = smoothstep(0.0, 1.0, x - cell);
+    mix(lattice(cell as i32, seed), lattice(cell as i32 + 1, seed), t)
+}
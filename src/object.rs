@@ -0,0 +1,202 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::instance::Instance;
+use crate::material::Material;
+use crate::plane::Plane;
+use crate::sphere::Sphere;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Visibility {
+    pub visible_to_camera: bool,
+    pub casts_shadow: bool,
+    /// Drawn white in the `Mask` channel.
+    pub masked: bool,
+}
+
+impl Visibility {
+    pub fn light_marker() -> Self {
+        Visibility {
+            visible_to_camera: true,
+            casts_shadow: false,
+            masked: false,
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility {
+            visible_to_camera: true,
+            casts_shadow: true,
+            masked: false,
+        }
+    }
+}
+
+const ID_COLOR_MULTIPLIER: u32 = 0x9E_3779;
+
+/// Label color for the object at `index` in the scene. Multiplying by an odd constant modulo 2^24 is a bijection,
+/// so every index below 2^24 - 1 gets its own color and none of them is black, which stays reserved for the sky.
+pub fn id_color(index: usize) -> Color {
+    Color::from_hex((index as u32 + 1).wrapping_mul(ID_COLOR_MULTIPLIER) & 0xFF_FFFF)
+}
+
+#[derive(Clone)]
+pub enum Object {
+    Cube(Cube, Visibility),
+    Plane(Plane, Visibility),
+    Instance(Instance, Visibility),
+    Sphere(Sphere, Visibility),
+}
+
+impl Object {
+    pub fn visibility(&self) -> Visibility {
+        match self {
+            Object::Cube(_, visibility)
+            | Object::Plane(_, visibility)
+            | Object::Instance(_, visibility)
+            | Object::Sphere(_, visibility) => *visibility,
+        }
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        match self {
+            Object::Cube(cube, _) => cube.translate(offset),
+            Object::Plane(plane, _) => plane.point += offset,
+            Object::Instance(instance, _) => instance.transform.translation += offset,
+            Object::Sphere(sphere, _) => sphere.center += offset,
+        }
+    }
+
+    pub fn transform(&mut self, transform: &Transform) {
+        match self {
+            Object::Cube(cube, _) => {
+                cube.set_size(cube.size() * transform.scale);
+                cube.set_center(transform.apply_point(&cube.center()));
+            }
+            Object::Plane(plane, _) => plane.point = transform.apply_point(&plane.point),
+            Object::Instance(instance, _) => instance.transform = transform.then(&instance.transform),
+            Object::Sphere(sphere, _) => {
+                sphere.radius *= transform.scale;
+                sphere.center = transform.apply_point(&sphere.center);
+            }
+        }
+    }
+
+    pub fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, prototypes: &[Cube]) -> Intersect {
+        match self {
+            Object::Cube(cube, _) => cube.ray_intersect(ray_origin, ray_direction),
+            Object::Plane(plane, _) => plane.ray_intersect(ray_origin, ray_direction),
+            Object::Instance(instance, _) => instance.ray_intersect(ray_origin, ray_direction, prototypes),
+            Object::Sphere(sphere, _) => sphere.ray_intersect(ray_origin, ray_direction),
+        }
+    }
+
+    /// Scales a cube, instance or sphere about its own center; planes are unaffected.
+    pub fn resize(&mut self, factor: f32, prototypes: &[Cube]) {
+        if let Object::Sphere(sphere, _) = self {
+            sphere.radius *= factor;
+            return;
+        }
+        let (center, _) = match self.cube_extent(prototypes) {
+            Some(extent) => extent,
+            None => return,
+        };
+        match self {
+            Object::Cube(cube, _) => cube.set_size(cube.size() * factor),
+            Object::Instance(instance, _) => {
+                instance.transform.scale *= factor;
+                let prototype_center = prototypes[instance.prototype].center();
+                instance.transform.translation = center - prototype_center * instance.transform.scale;
+            }
+            Object::Plane(..) | Object::Sphere(..) => {}
+        }
+    }
+
+    /// Center and half size of the axis-aligned cube enclosing a bounded object; planes are unbounded.
+    pub fn extent(&self, prototypes: &[Cube]) -> Option<(Vec3, f32)> {
+        match self {
+            Object::Sphere(sphere, _) => Some((sphere.center, sphere.radius)),
+            _ => self.cube_extent(prototypes),
+        }
+    }
+
+    /// Center and half size of cube-shaped objects, resolving instances through `prototypes`.
+    pub fn cube_extent(&self, prototypes: &[Cube]) -> Option<(Vec3, f32)> {
+        match self {
+            Object::Cube(cube, _) => Some((cube.center(), cube.size() * 0.5)),
+            Object::Plane(..) | Object::Sphere(..) => None,
+            Object::Instance(instance, _) => {
+                let prototype = prototypes.get(instance.prototype)?;
+                Some((
+                    instance.transform.apply_point(&prototype.center()),
+                    prototype.size() * instance.transform.scale * 0.5,
+                ))
+            }
+        }
+    }
+
+    /// Material of a cube, instance or sphere, resolving instances through `prototypes`; planes have none.
+    pub fn material(&self, prototypes: &[Cube]) -> Option<Material> {
+        match self {
+            Object::Cube(cube, _) => Some(cube.material),
+            Object::Sphere(sphere, _) => Some(sphere.material),
+            Object::Plane(..) => None,
+            Object::Instance(instance, _) => instance.material.or_else(|| prototypes.get(instance.prototype).map(|prototype| prototype.material)),
+        }
+    }
+
+    /// Replaces the whole material of a cube, instance or sphere, dropping any instance diffuse tint; planes are
+    /// unaffected.
+    pub fn set_material(&mut self, material: Material) {
+        match self {
+            Object::Cube(cube, _) => cube.material = material,
+            Object::Sphere(sphere, _) => sphere.material = material,
+            Object::Plane(..) => {}
+            Object::Instance(instance, _) => {
+                instance.material = Some(material);
+                instance.diffuse = None;
+            }
+        }
+    }
+
+    pub fn visibility_mut(&mut self) -> &mut Visibility {
+        match self {
+            Object::Cube(_, visibility)
+            | Object::Plane(_, visibility)
+            | Object::Instance(_, visibility)
+            | Object::Sphere(_, visibility) => visibility,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{NUDGE_STEP, RESIZE_FACTOR};
+
+    #[test]
+    fn nudging_and_resizing_update_the_stored_extent() {
+        let prototypes = [Cube::new(Vec3::new(0.5, 0.5, 0.5), 1.0, Material::sand())];
+        let mut objects = [
+            Object::Cube(Cube::new(Vec3::new(1.0, 2.0, 3.0), 1.0, Material::sand()), Visibility::default()),
+            Object::Instance(Instance::new(0, Transform::new(Vec3::new(1.0, 2.0, 3.0), 0.5)), Visibility::default()),
+        ];
+        let step = Vec3::new(NUDGE_STEP, 0.0, -NUDGE_STEP);
+        for object in &mut objects {
+            let (center, half_size) = object.cube_extent(&prototypes).unwrap();
+            object.translate(step);
+            let (nudged, nudged_half) = object.cube_extent(&prototypes).unwrap();
+            assert!((nudged - center - step).magnitude() < 1e-6);
+            assert_eq!(nudged_half, half_size);
+
+            object.resize(RESIZE_FACTOR, &prototypes);
+            let (resized, resized_half) = object.cube_extent(&prototypes).unwrap();
+            assert!((resized - nudged).magnitude() < 1e-6, "resizing moved the center");
+            assert!((resized_half - half_size * RESIZE_FACTOR).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::math::mix;
+
+/// Per-sample shading terms written out as separate AOVs. In the final channel the beauty color is
+/// `diffuse + specular + emission + reflection + transmission`.
+#[derive(Debug, Clone, Copy)]
+pub struct Passes {
+    /// Direct diffuse lighting plus skylight ambient.
+    pub diffuse: Color,
+    pub specular: Color,
+    /// Environment reflection and traced mirror bounces.
+    pub reflection: Color,
+    /// Light refracted through the surface.
+    pub transmission: Color,
+    /// Emissive and monitor texels, flat edge colors and unlit surfaces.
+    pub emission: Color,
+    pub normal: Vec3,
+}
+
+impl Passes {
+    pub fn empty() -> Self {
+        Passes {
+            diffuse: Color::black(),
+            specular: Color::black(),
+            reflection: Color::black(),
+            transmission: Color::black(),
+            emission: Color::black(),
+            normal: Vec3::zeros(),
+        }
+    }
+
+    pub fn beauty(&self) -> Color {
+        self.diffuse + self.specular + self.emission + self.reflection + self.transmission
+    }
+
+    pub fn lerp(&self, other: &Passes, factor: f32) -> Passes {
+        Passes {
+            diffuse: self.diffuse.lerp(other.diffuse, factor),
+            specular: self.specular.lerp(other.specular, factor),
+            reflection: self.reflection.lerp(other.reflection, factor),
+            transmission: self.transmission.lerp(other.transmission, factor),
+            emission: self.emission.lerp(other.emission, factor),
+            normal: Vec3::new(
+                mix(self.normal.x, other.normal.x, factor),
+                mix(self.normal.y, other.normal.y, factor),
+                mix(self.normal.z, other.normal.z, factor),
+            ),
+        }
+    }
+
+    /// Weighted sum of sub-pixel samples, with weights that may be negative.
+    pub fn weighted_sum(samples: &[(Passes, f32)]) -> Passes {
+        let sum = |channel: fn(&Passes) -> Color| Color::weighted_sum(samples.iter().map(|(passes, weight)| (channel(passes), *weight)));
+        Passes {
+            diffuse: sum(|passes| passes.diffuse),
+            specular: sum(|passes| passes.specular),
+            reflection: sum(|passes| passes.reflection),
+            transmission: sum(|passes| passes.transmission),
+            emission: sum(|passes| passes.emission),
+            normal: samples.iter().fold(Vec3::zeros(), |total, (passes, weight)| total + passes.normal * *weight),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    Diffuse,
+    Specular,
+    Reflection,
+    Transmission,
+    Emission,
+    Depth,
+    Normal,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 7] = [
+        Pass::Diffuse,
+        Pass::Specular,
+        Pass::Reflection,
+        Pass::Transmission,
+        Pass::Emission,
+        Pass::Depth,
+        Pass::Normal,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Diffuse => "diffuse",
+            Pass::Specular => "specular",
+            Pass::Reflection => "reflection",
+            Pass::Transmission => "transmission",
+            Pass::Emission => "emission",
+            Pass::Depth => "depth",
+            Pass::Normal => "normal",
+        }
+    }
+
+    /// Output file for this pass next to `path`: `out.png` becomes `out_diffuse.png`.
+    pub fn file_name(self, path: &str) -> String {
+        match path.rsplit_once('.') {
+            Some((stem, extension)) if !extension.contains('/') => format!("{}_{}.{}", stem, self.name(), extension),
+            _ => format!("{}_{}.png", path, self.name()),
+        }
+    }
+}
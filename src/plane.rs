@@ -0,0 +1,126 @@
+use nalgebra_glm::Vec3;
+use crate::ray_intersect::{RayIntersect, Intersect, INTERSECTION_EPSILON};
+use crate::material::Material;
+use crate::sampling::orthonormal_basis;
+use crate::math::{mix, saturate};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Shore {
+    pub wet_material: Material,
+    pub water_center: Vec3,
+    pub water_half_extent: f32,
+    pub wet_width: f32,
+}
+
+impl Shore {
+    pub fn wetness(&self, point: &Vec3) -> f32 {
+        let dx = (point.x - self.water_center.x).abs() - self.water_half_extent;
+        let dz = (point.z - self.water_center.z).abs() - self.water_half_extent;
+        let distance = dx.max(dz).max(0.0);
+        if self.wet_width <= 0.0 {
+            return if distance > 0.0 { 0.0 } else { 1.0 };
+        }
+        1.0 - saturate(distance / self.wet_width)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Plane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub material: Material,
+    pub shore: Option<Shore>,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, material: Material) -> Self {
+        Plane {
+            point,
+            normal: normal.normalize(),
+            material,
+            shore: None,
+        }
+    }
+
+    pub fn with_shore(mut self, shore: Shore) -> Self {
+        self.shore = Some(shore);
+        self
+    }
+
+    pub fn uv_at(&self, point: &Vec3) -> (f32, f32) {
+        let (tangent, bitangent) = orthonormal_basis(&self.normal);
+        let local = point - self.point;
+        (local.dot(&tangent).rem_euclid(1.0), local.dot(&bitangent).rem_euclid(1.0))
+    }
+
+    pub fn material_at(&self, point: &Vec3) -> Material {
+        match &self.shore {
+            Some(shore) => {
+                let wetness = shore.wetness(point);
+                let mut material = self.material;
+                material.diffuse = self.material.diffuse.lerp(shore.wet_material.diffuse, wetness);
+                material.roughness = self.material.roughness
+                    + (shore.wet_material.roughness - self.material.roughness) * wetness;
+                material.clear_coat = mix(self.material.clear_coat, shore.wet_material.clear_coat, wetness);
+                material.clear_coat_roughness = mix(self.material.clear_coat_roughness, shore.wet_material.clear_coat_roughness, wetness);
+                material
+            }
+            None => self.material,
+        }
+    }
+}
+
+impl RayIntersect for Plane {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let denom = self.normal.dot(ray_direction);
+        if denom.abs() < 1e-6 {
+            return Intersect::empty();
+        }
+
+        let distance = (self.point - ray_origin).dot(&self.normal) / denom;
+        if distance < INTERSECTION_EPSILON || !distance.is_finite() {
+            return Intersect::empty();
+        }
+
+        let point = ray_origin + ray_direction * distance;
+        let (u, v) = self.uv_at(&point);
+        Intersect::new(point, self.normal, distance, self.material_at(&point)).with_uv(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ground() -> Plane {
+        Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 2.0, 0.0), Material::sand())
+    }
+
+    #[test]
+    fn rays_meet_the_plane_from_either_side() {
+        let plane = ground();
+        assert_eq!(plane.normal, Vec3::new(0.0, 1.0, 0.0));
+        let hit = plane.ray_intersect(&Vec3::new(3.0, 4.0, -2.0), &Vec3::new(0.0, -1.0, 0.0));
+        assert!(hit.is_intersecting);
+        assert!((hit.distance - 5.0).abs() < 1e-5);
+        assert!((hit.point - Vec3::new(3.0, -1.0, -2.0)).magnitude() < 1e-5);
+
+        let oblique = plane.ray_intersect(&Vec3::new(0.0, 1.0, 0.0), &Vec3::new(1.0, -1.0, 0.0).normalize());
+        assert!((oblique.point - Vec3::new(2.0, -1.0, 0.0)).magnitude() < 1e-4);
+        let below = plane.ray_intersect(&Vec3::new(0.0, -3.0, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+        assert!(below.is_intersecting && (below.distance - 2.0).abs() < 1e-5);
+        assert!((0.0..1.0).contains(&hit.uv.0) && (0.0..1.0).contains(&hit.uv.1));
+    }
+
+    #[test]
+    fn parallel_receding_and_degenerate_rays_miss() {
+        let plane = ground();
+        let origin = Vec3::new(0.0, 1.0, 0.0);
+        assert!(!plane.ray_intersect(&origin, &Vec3::new(1.0, 0.0, 0.0)).is_intersecting, "parallel");
+        assert!(!plane.ray_intersect(&Vec3::new(0.0, -1.0, 0.0), &Vec3::new(1.0, 0.0, 0.0)).is_intersecting, "in the plane");
+        assert!(!plane.ray_intersect(&origin, &Vec3::new(0.0, 1.0, 0.0)).is_intersecting, "plane behind the origin");
+        assert!(!plane.ray_intersect(&Vec3::new(0.0, -1.0, 0.0), &Vec3::new(0.0, 1.0, 0.0)).is_intersecting, "leaving the surface");
+        assert!(!plane.ray_intersect(&origin, &Vec3::zeros()).is_intersecting);
+        assert!(!plane.ray_intersect(&origin, &Vec3::new(f32::NAN, -1.0, 0.0)).is_intersecting);
+    }
+}
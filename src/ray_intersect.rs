@@ -1,6 +1,10 @@
 use nalgebra_glm::Vec3;
 use crate::material::Material;
 
+/// Hits closer than this (scaled by the cube size for cubes) are rejected, so a ray spawned on a surface never
+/// re-hits it at `t ≈ 0` however small the origin bias is.
+pub const INTERSECTION_EPSILON: f32 = 1e-5;
+
 #[derive(Debug, Clone)]
 pub struct Intersect {
     pub point: Vec3,
@@ -8,6 +12,9 @@ pub struct Intersect {
     pub distance: f32,
     pub material: Material,
     pub is_intersecting: bool,
+    pub uv: (f32, f32),
+    /// Width of the primary ray cone at the hit, in world units.
+    pub cone_width: f32,
 }
 
 impl Intersect {
@@ -18,9 +25,16 @@ impl Intersect {
             distance,
             material,
             is_intersecting: true,
+            uv: (0.0, 0.0),
+            cone_width: 0.0,
         }
     }
 
+    pub fn with_uv(mut self, u: f32, v: f32) -> Self {
+        self.uv = (u, v);
+        self
+    }
+
     pub fn empty() -> Self {
         Intersect {
             point: Vec3::new(0.0, 0.0, 0.0),
@@ -28,6 +42,8 @@ impl Intersect {
             distance: 0.0,
             material: Material::black(),
             is_intersecting: false,
+            uv: (0.0, 0.0),
+            cone_width: 0.0,
         }
     }
 }
@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// Worker threads owned by one render session. `render` traces on the pool it is installed in and on the calling
+/// thread when there is none, so nothing runs on rayon's global pool and the threads go away with the session.
+pub struct RenderPool {
+    threads: usize,
+    pool: Option<ThreadPool>,
+    live_workers: Arc<AtomicUsize>,
+}
+
+impl RenderPool {
+    /// A pool of `threads` workers, or one per core when that is zero. The workers start on the first `install`.
+    pub fn new(threads: usize) -> Self {
+        RenderPool { threads, pool: None, live_workers: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    /// Runs `job` on the pool's workers, restarting them first when `threads` differs from the running count.
+    pub fn install<R: Send>(&mut self, threads: usize, job: impl FnOnce() -> R + Send) -> R {
+        if threads != self.threads {
+            self.shutdown();
+            self.threads = threads;
+        }
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => {
+                let exited = Arc::clone(&self.live_workers);
+                let pool = ThreadPoolBuilder::new()
+                    .num_threads(self.threads)
+                    .thread_name(|index| format!("render-{}", index))
+                    .exit_handler(move |_| {
+                        exited.fetch_sub(1, Ordering::AcqRel);
+                    })
+                    .build()
+                    .expect("failed to start render threads");
+                self.live_workers.fetch_add(pool.current_num_threads(), Ordering::AcqRel);
+                self.pool.insert(pool)
+            }
+        };
+        pool.install(job)
+    }
+
+    /// Worker threads that have been started and not yet exited.
+    pub fn live_workers(&self) -> usize {
+        self.live_workers.load(Ordering::Acquire)
+    }
+
+    /// Stops the workers and waits for every one of them to exit. A later `install` starts a fresh set.
+    pub fn shutdown(&mut self) {
+        self.pool = None;
+        while self.live_workers() > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+impl Drop for RenderPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_stops_every_worker_and_install_restarts_them() {
+        let mut pool = RenderPool::new(3);
+        assert_eq!(pool.live_workers(), 0);
+        assert_eq!(pool.install(3, rayon::current_num_threads), 3);
+        assert_eq!(pool.live_workers(), 3);
+
+        pool.shutdown();
+        assert_eq!(pool.live_workers(), 0);
+
+        assert_eq!(pool.install(2, rayon::current_num_threads), 2);
+        assert_eq!(pool.live_workers(), 2);
+        pool.shutdown();
+        assert_eq!(pool.live_workers(), 0);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::color::Color;
+use crate::tone_map::ToneMap;
+use crate::color_space::OutputColorSpace;
+use crate::exposure::DEFAULT_EXPOSURE_TARGET;
+use crate::sampling::{ReconstructionFilter, SamplePattern};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecularModel {
+    Phong,
+    BlinnPhong,
+}
+
+impl SpecularModel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Phong" => Some(SpecularModel::Phong),
+            "BlinnPhong" => Some(SpecularModel::BlinnPhong),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowMode {
+    /// Shadow rays towards every light sample.
+    RayTraced,
+    /// The sun is shadowed from a per-frame orthographic depth map; other lights stay ray traced.
+    ShadowMap,
+    /// No occlusion rays; the ground is darkened under soft ellipses projected from each cube.
+    Blob,
+}
+
+impl ShadowMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "RayTraced" => Some(ShadowMode::RayTraced),
+            "ShadowMap" => Some(ShadowMode::ShadowMap),
+            "Blob" => Some(ShadowMode::Blob),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ShadowMode::RayTraced => ShadowMode::ShadowMap,
+            ShadowMode::ShadowMap => ShadowMode::Blob,
+            ShadowMode::Blob => ShadowMode::RayTraced,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostBlur {
+    Off,
+    /// Zoom streaks towards the image center.
+    Radial,
+    /// Horizontal streaks across the whole image.
+    Directional,
+}
+
+impl PostBlur {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Off" => Some(PostBlur::Off),
+            "Radial" => Some(PostBlur::Radial),
+            "Directional" => Some(PostBlur::Directional),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            PostBlur::Off => PostBlur::Radial,
+            PostBlur::Radial => PostBlur::Directional,
+            PostBlur::Directional => PostBlur::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderChannel {
+    /// Fully shaded image.
+    Final,
+    /// Summed diffuse term of all lights, without specular or emission.
+    Diffuse,
+    /// Summed specular term of all lights.
+    Specular,
+    /// Surface normal remapped from [-1, 1] to [0, 255] per axis (x -> red, y -> green, z -> blue).
+    Normal,
+    /// Hit distance as grayscale, white at the eye fading to black at `DEPTH_RANGE`.
+    Depth,
+    /// Surface u in red and v in green, both in [0, 1]; blue is zero.
+    UV,
+    /// White where the nearest hit is a masked object, black everywhere else, for compositing mattes.
+    Mask,
+    /// Flat color per object, unique for every scene index, over a black background; for segmentation labels.
+    ObjectId,
+    /// Red where the two nearest opaque hits are within the depth tie tolerance, dark gray on other hits and
+    /// black on misses, to find surfaces that z-fight.
+    DepthConflict,
+}
+
+impl RenderChannel {
+    pub const DEPTH_RANGE: f32 = 30.0;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Final" => Some(RenderChannel::Final),
+            "Diffuse" => Some(RenderChannel::Diffuse),
+            "Specular" => Some(RenderChannel::Specular),
+            "Normal" => Some(RenderChannel::Normal),
+            "Depth" => Some(RenderChannel::Depth),
+            "UV" => Some(RenderChannel::UV),
+            "Mask" => Some(RenderChannel::Mask),
+            "ObjectId" => Some(RenderChannel::ObjectId),
+            "DepthConflict" => Some(RenderChannel::DepthConflict),
+            _ => None,
+        }
+    }
+
+    /// Channels whose pixel values are labels rather than light, shown without exposure, tone mapping or encoding.
+    pub fn is_label(self) -> bool {
+        matches!(self, RenderChannel::Mask | RenderChannel::ObjectId | RenderChannel::DepthConflict)
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            RenderChannel::Final => RenderChannel::Diffuse,
+            RenderChannel::Diffuse => RenderChannel::Specular,
+            RenderChannel::Specular => RenderChannel::Normal,
+            RenderChannel::Normal => RenderChannel::Depth,
+            RenderChannel::Depth => RenderChannel::UV,
+            RenderChannel::UV => RenderChannel::Mask,
+            RenderChannel::Mask => RenderChannel::ObjectId,
+            RenderChannel::ObjectId => RenderChannel::DepthConflict,
+            RenderChannel::DepthConflict => RenderChannel::Final,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    pub env_reflection: bool,
+    pub light_scale: f32,
+    pub specular_knee: f32,
+    pub debug_non_finite: bool,
+    pub specular_model: SpecularModel,
+    pub shadow_samples: usize,
+    pub light_radius: f32,
+    pub channel: RenderChannel,
+    pub glossy_samples: usize,
+    pub sky_rotation_speed: f32,
+    pub exposure: f32,
+    pub chromatic_aberration: f32,
+    pub motion_blur_samples: usize,
+    pub firefly_clamp: Option<f32>,
+    pub analytic_sky: bool,
+    pub sky_turbidity: f32,
+    pub threads: usize,
+    pub tone_map: ToneMap,
+    pub light_samples: usize,
+    pub camera_shake: f32,
+    pub shadow_mode: ShadowMode,
+    pub sorted_transparency: bool,
+    pub contact_hardening: f32,
+    pub color_space: OutputColorSpace,
+    pub temporal_blend: f32,
+    pub skylight_samples: usize,
+    pub frame_budget_ms: f32,
+    pub budget_min_level: usize,
+    pub budget_max_level: usize,
+    pub aa_samples: usize,
+    pub sample_pattern: SamplePattern,
+    pub reconstruction_filter: ReconstructionFilter,
+    pub edge_antialiasing: bool,
+    pub post_blur: PostBlur,
+    pub focus_distance: f32,
+    pub reflection_clamp: Option<f32>,
+    pub radiance_clamp: Option<f32>,
+    /// Bounces whose accumulated path throughput falls below this are not traced at all; zero traces every bounce.
+    pub reflection_cutoff: f32,
+    pub bilinear_reflections: bool,
+    pub wind_strength: f32,
+    pub auto_exposure: bool,
+    pub exposure_target: f32,
+    pub bloom: bool,
+    /// Per-channel level above which exposed linear light feeds the bloom.
+    pub bloom_threshold: [f32; 3],
+    pub bloom_tint: Color,
+    pub caustics: bool,
+    pub caustics_strength: f32,
+    pub depth_tie_break: bool,
+    /// Past `ROULETTE_MIN_DEPTH`, bounces continue with probability equal to their throughput instead of always.
+    pub russian_roulette: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            env_reflection: false,
+            light_scale: 1.5,
+            specular_knee: 0.8,
+            debug_non_finite: false,
+            specular_model: SpecularModel::Phong,
+            shadow_samples: 1,
+            light_radius: 0.25,
+            channel: RenderChannel::Final,
+            glossy_samples: 8,
+            sky_rotation_speed: 0.0,
+            exposure: 1.0,
+            chromatic_aberration: 0.0,
+            motion_blur_samples: 0,
+            firefly_clamp: None,
+            analytic_sky: false,
+            sky_turbidity: 3.0,
+            threads: 0,
+            tone_map: ToneMap::None,
+            light_samples: 0,
+            camera_shake: 0.0,
+            shadow_mode: ShadowMode::RayTraced,
+            sorted_transparency: false,
+            contact_hardening: 0.0,
+            color_space: OutputColorSpace::Srgb,
+            temporal_blend: 0.0,
+            skylight_samples: 0,
+            frame_budget_ms: 0.0,
+            budget_min_level: 0,
+            budget_max_level: 4,
+            aa_samples: 1,
+            sample_pattern: SamplePattern::Grid,
+            reconstruction_filter: ReconstructionFilter::Box,
+            edge_antialiasing: false,
+            post_blur: PostBlur::Off,
+            focus_distance: 8.0,
+            reflection_clamp: None,
+            radiance_clamp: None,
+            reflection_cutoff: 0.0,
+            bilinear_reflections: false,
+            wind_strength: 0.0,
+            auto_exposure: false,
+            exposure_target: DEFAULT_EXPOSURE_TARGET,
+            bloom: false,
+            bloom_threshold: [0.8; 3],
+            bloom_tint: Color::new(255, 255, 255),
+            caustics: false,
+            caustics_strength: 1.0,
+            depth_tie_break: false,
+            russian_roulette: false,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// These settings with the values adjusted every frame by the frame budget and auto exposure reset to their
+    /// defaults. Temporal history only needs to restart when this changes, not when those values drift.
+    pub fn history_key(&self) -> RenderSettings {
+        let defaults = RenderSettings::default();
+        RenderSettings {
+            exposure: defaults.exposure,
+            shadow_samples: defaults.shadow_samples,
+            glossy_samples: defaults.glossy_samples,
+            skylight_samples: defaults.skylight_samples,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::budget::FrameBudget;
+
+    #[test]
+    fn adaptive_settings_keep_history() {
+        let mut settings = RenderSettings { frame_budget_ms: 10.0, auto_exposure: true, ..RenderSettings::default() };
+        let key = settings.history_key();
+        let mut budget = FrameBudget::new();
+        for _ in 0..3 {
+            budget.update(&mut settings, Duration::from_millis(1));
+        }
+        settings.exposure *= 1.5;
+        assert_ne!(settings, RenderSettings { frame_budget_ms: 10.0, auto_exposure: true, ..RenderSettings::default() });
+        assert_eq!(settings.history_key(), key);
+
+        settings.channel = RenderChannel::Normal;
+        assert_ne!(settings.history_key(), key);
+    }
+}
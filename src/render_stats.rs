@@ -0,0 +1,5 @@
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderStats {
+    pub pixels: usize,
+    pub non_finite_pixels: usize,
+}
@@ -0,0 +1,1467 @@
+//! Ray casting, shading and the per-frame render: everything that turns a `Scene` and a `Camera` into a framebuffer.
+
+use nalgebra_glm::Vec3;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::color::Color;
+use crate::ray_intersect::Intersect;
+use crate::object::{id_color, Object};
+use crate::framebuffer::Framebuffer;
+use crate::camera::Camera;
+use crate::render_settings::{PostBlur, RenderChannel, RenderSettings, ShadowMode, SpecularModel};
+use crate::render_stats::RenderStats;
+use crate::texture::Texture;
+use crate::environment::Environment;
+use crate::sampling::{cosine_hemisphere, ggx_half_vector, hash_point, pick_weighted, russian_roulette, Samples};
+use crate::optics::{fresnel, reflect, refract};
+use crate::scene::Scene;
+use crate::passes::Passes;
+use crate::beach::Beach;
+use crate::light::Light;
+use crate::math::{mix, saturate, smoothstep};
+use crate::tone_map::{scale_soft_clipped, ToneMap};
+use crate::color_space::OutputColorSpace;
+
+const ORIGIN_BIAS: f32 = 1e-4;
+/// Hits whose distances differ by less than this fraction of the nearer one count as a depth tie.
+const DEPTH_TIE_TOLERANCE: f32 = 1e-4;
+const DEPTH_CONFLICT_COLOR: Color = Color::new(255, 0, 0);
+/// Bounces below this depth always continue; russian roulette only decides deeper ones.
+const ROULETTE_MIN_DEPTH: u32 = 2;
+const SKYBOX_COLOR: Color = Color::new(68, 142, 228);
+const ENV_REFLECTION_STRENGTH: f32 = 0.15;
+const CLEAR_COAT_REFRACTIVE_INDEX: f32 = 1.5;
+const SKYLIGHT_STRENGTH: f32 = 0.35;
+const NON_FINITE_MARKER: Color = Color::new(255, 0, 255);
+const BLOOM_RADIUS: usize = 6;
+const BLOOM_STRENGTH: f32 = 0.8;
+const CONTACT_HARDENING_SPREAD: f32 = 4.0;
+const BLOB_RADIUS_SCALE: f32 = 1.3;
+const BLOB_MAX_STRETCH: f32 = 4.0;
+const BLOB_CORE: f32 = 0.4;
+const BLOB_DARKNESS: f32 = 0.8;
+const RADIAL_BLUR_STRENGTH: f32 = 0.06;
+const DIRECTIONAL_BLUR_STRENGTH: f32 = 0.015;
+/// Side of the square tiles `render` hands out to worker threads.
+pub const TILE_SIZE: usize = 32;
+
+fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3 {
+    let offset = intersect.normal * ORIGIN_BIAS;
+    if direction.dot(&intersect.normal) < 0.0 {
+        intersect.point - offset
+    } else {
+        intersect.point + offset
+    }
+}
+
+/// Fraction of `light` blocked at the hit, averaged over `samples` offsets on a sphere of `light_radius` around it.
+/// The offsets are generated once per frame and shared by every pixel.
+pub fn cast_shadow(
+    intersect: &Intersect,
+    light: &Light,
+    light_radius: f32,
+    contact_hardening: f32,
+    scene: &Scene,
+    samples: &[Vec3],
+    skip_object: Option<usize>,
+) -> f32 {
+    if !light.casts_shadows {
+        return 0.0;
+    }
+
+    let light_position = &light.position;
+    let light_radius = light_radius * light.shadow_softness;
+    let light_radius = if contact_hardening > 0.0 && samples.len() > 1 {
+        light_radius * contact_hardening_scale(intersect, light_position, light_radius, contact_hardening, scene, samples, skip_object)
+    } else {
+        light_radius
+    };
+
+    let mut total_shadow = 0.0;
+
+    for sample in samples {
+        total_shadow += cast_shadow_sample(intersect, &(light_position + sample * light_radius), scene, skip_object);
+    }
+
+    total_shadow / samples.len() as f32
+}
+
+fn contact_hardening_scale(
+    intersect: &Intersect,
+    light_position: &Vec3,
+    light_radius: f32,
+    strength: f32,
+    scene: &Scene,
+    samples: &[Vec3],
+    skip_object: Option<usize>,
+) -> f32 {
+    let ratios: Vec<f32> = samples
+        .iter()
+        .filter_map(|sample| shadow_blocker(intersect, &(light_position + sample * light_radius), scene, skip_object))
+        .collect();
+    if ratios.is_empty() {
+        return 1.0;
+    }
+
+    let blocker_ratio = ratios.iter().sum::<f32>() / ratios.len() as f32;
+    let penumbra = blocker_ratio / (1.0 - blocker_ratio).max(f32::EPSILON);
+    mix(1.0, (penumbra * CONTACT_HARDENING_SPREAD).min(CONTACT_HARDENING_SPREAD), saturate(strength))
+}
+
+/// How much one shadow ray from the hit to `light_position` is blocked; a blocker near the hit darkens most.
+pub fn cast_shadow_sample(
+    intersect: &Intersect,
+    light_position: &Vec3,
+    scene: &Scene,
+    skip_object: Option<usize>,
+) -> f32 {
+    shadow_blocker(intersect, light_position, scene, skip_object)
+        .map_or(0.0, |distance_ratio| 1.0 - distance_ratio.powf(2.0).min(1.0))
+}
+
+/// Distance to the first shadow caster between the hit and the light, as a fraction of the distance to the light.
+fn shadow_blocker(
+    intersect: &Intersect,
+    light_position: &Vec3,
+    scene: &Scene,
+    skip_object: Option<usize>,
+) -> Option<f32> {
+    let light_dir = (light_position - intersect.point).normalize();
+    let light_distance = (light_position - intersect.point).magnitude();
+
+    let shadow_ray_origin = offset_origin(intersect, &light_dir);
+
+    for (index, object) in scene.objects.iter().enumerate() {
+        if Some(index) == skip_object || !object.visibility().casts_shadow {
+            continue;
+        }
+        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir, &scene.prototypes);
+        if shadow_intersect.is_intersecting && shadow_intersect.distance < light_distance {
+            return Some(shadow_intersect.distance / light_distance);
+        }
+    }
+
+    None
+}
+
+fn blob_shadow(intersect: &Intersect, light_position: &Vec3, scene: &Scene) -> f32 {
+    let normal = intersect.normal;
+    let mut shadow: f32 = 0.0;
+
+    for object in &scene.objects {
+        if !object.visibility().casts_shadow {
+            continue;
+        }
+        let (center, half_size) = match object.extent(&scene.prototypes) {
+            Some(extent) => extent,
+            None => continue,
+        };
+        let height = (center - intersect.point).dot(&normal);
+        let light_dir = (center - light_position).normalize();
+        let descent = -light_dir.dot(&normal);
+        if height <= 0.0 || descent <= 0.0 {
+            continue;
+        }
+
+        let footprint = center + light_dir * (height / descent);
+        let offset = intersect.point - footprint;
+        let lateral = light_dir + normal * descent;
+        let radius = half_size * BLOB_RADIUS_SCALE;
+        let distance = if lateral.magnitude() > f32::EPSILON {
+            let along_dir = lateral.normalize();
+            let along = offset.dot(&along_dir);
+            let across = (offset - along_dir * along).magnitude();
+            let stretch = (1.0 / descent).min(BLOB_MAX_STRETCH);
+            ((along / stretch).powi(2) + across.powi(2)).sqrt() / radius
+        } else {
+            offset.magnitude() / radius
+        };
+        shadow = shadow.max(1.0 - smoothstep(BLOB_CORE, 1.0, distance));
+    }
+
+    shadow * BLOB_DARKNESS
+}
+
+pub fn calculate_light_intensity(light_position: &Vec3) -> f32 {
+    let max_intensity = 1.0;  
+    let min_intensity = 0.2;  
+
+    let light_height_factor = (light_position.y + 1.0).max(0.0) / 10.0;  
+
+    mix(min_intensity, max_intensity, saturate(light_height_factor))
+}
+
+
+fn glossy_environment(
+    ray_direction: &Vec3,
+    intersect: &Intersect,
+    environment: &Environment,
+    glossy_samples: &[(f32, f32)],
+    firefly_clamp: Option<f32>,
+) -> Color {
+    let roughness = intersect.material.roughness;
+    if roughness <= 0.0 || glossy_samples.len() <= 1 {
+        return environment.sample_reflection(&reflect(ray_direction, &intersect.normal).normalize());
+    }
+
+    let reflections = glossy_samples.iter().filter_map(|&(u1, u2)| {
+        let half_vector = ggx_half_vector(&intersect.normal, roughness, u1, u2);
+        let direction = reflect(ray_direction, &half_vector).normalize();
+        if direction.dot(&intersect.normal) > 0.0 {
+            Some(environment.sample_reflection(&direction))
+        } else {
+            None
+        }
+    });
+
+    Color::average_clamped(reflections, firefly_clamp)
+        .unwrap_or_else(|| environment.sample_reflection(&reflect(ray_direction, &intersect.normal).normalize()))
+}
+
+/// Cosine-weighted estimate of the environment light reaching `intersect`: each of the shared `skylight_samples`
+/// directions adds the environment's radiance that way, or nothing when a shadow caster blocks it. The sample set is
+/// rotated per point so neighbouring pixels decorrelate.
+pub fn skylight(intersect: &Intersect, scene: &Scene, skylight_samples: &[(f32, f32)], skip_object: Option<usize>) -> Color {
+    if skylight_samples.is_empty() {
+        return Color::black();
+    }
+
+    let rotation = hash_point(&intersect.point, 0);
+    let visible = skylight_samples.iter().map(|&(u1, u2)| {
+        let direction = cosine_hemisphere(&intersect.normal, u1, (u2 + rotation).fract());
+        let origin = offset_origin(intersect, &direction);
+        let blocked = scene.objects.iter().enumerate().any(|(index, object)| {
+            Some(index) != skip_object
+                && object.visibility().casts_shadow
+                && object.ray_intersect(&origin, &direction, &scene.prototypes).is_intersecting
+        });
+        if blocked {
+            Color::black()
+        } else {
+            scene.environment.sample(&direction)
+        }
+    });
+
+    Color::average_clamped(visible, None).unwrap_or_else(Color::black)
+}
+
+fn select_lights(lights: &[Light], intersect: &Intersect, sample_count: usize) -> Vec<(usize, f32)> {
+    if sample_count == 0 || lights.len() <= sample_count {
+        return (0..lights.len()).map(|index| (index, 1.0)).collect();
+    }
+
+    let weights: Vec<f32> = lights.iter().map(|light| light.importance(&intersect.point, &intersect.normal)).collect();
+    let total: f32 = weights.iter().sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut selected: Vec<(usize, f32)> = Vec::with_capacity(sample_count);
+    for k in 0..sample_count {
+        let u = (k as f32 + hash_point(&intersect.point, k as u32)) / sample_count as f32;
+        let index = pick_weighted(&weights, total, u);
+        let weight = total / (weights[index] * sample_count as f32);
+        match selected.iter_mut().find(|(selected_index, _)| *selected_index == index) {
+            Some(entry) => entry.1 += weight,
+            None => selected.push((index, weight)),
+        }
+    }
+    selected
+}
+
+fn on_cube_edge(intersect: &Intersect, object: &Object) -> bool {
+    let width = intersect.material.edge_width;
+    if width <= 0.0 || !matches!(object, Object::Cube(..) | Object::Instance(..)) {
+        return false;
+    }
+    let (u, v) = intersect.uv;
+    u < width || u > 1.0 - width || v < width || v > 1.0 - width
+}
+
+fn monitor_texel(intersect: &Intersect, object: &Object, scene: &Scene) -> Option<Color> {
+    let texture = scene.monitors.get(intersect.material.monitor?)?;
+    if intersect.normal.z < 0.5 {
+        return None;
+    }
+    let (u, v) = intersect.uv;
+    let face_size = object.cube_extent(&scene.prototypes).map_or(1.0, |(_, half_size)| 2.0 * half_size);
+    let footprint = intersect.cone_width / face_size;
+    Some(texture.sample_footprint(u, 1.0 - v, footprint))
+}
+
+/// Whether a camera ray hits anything visible to the camera, without shading it.
+fn primary_hit(ray_origin: &Vec3, ray_direction: &Vec3, scene: &Scene) -> bool {
+    scene.objects.iter().any(|object| {
+        object.visibility().visible_to_camera && object.ray_intersect(ray_origin, ray_direction, &scene.prototypes).is_intersecting
+    })
+}
+
+/// Shaded color of a primary ray, premultiplied by `coverage`, and the distance to the nearest opaque hit.
+#[derive(Debug, Clone, Copy)]
+pub struct RaySample {
+    pub color: Color,
+    pub coverage: f32,
+    pub distance: f32,
+    pub passes: Passes,
+}
+
+/// `throughput` is the product of the bounce weights along the path so far, 1 for camera rays.
+#[allow(clippy::too_many_arguments)]
+pub fn cast_ray(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    scene: &Scene,
+    depth: u32,
+    throughput: f32,
+    settings: &RenderSettings,
+    samples: &Samples,
+    cone_spread: f32,
+) -> RaySample {
+    if depth > 3 {
+        return RaySample { color: SKYBOX_COLOR, coverage: 1.0, distance: f32::INFINITY, passes: Passes::empty() };
+    }
+
+    let mut intersect = Intersect::empty();
+    let mut zbuffer = f32::INFINITY;
+    let mut hit_object = None;
+    let mut runner_up = f32::INFINITY;
+    let mut layers = Vec::new();
+
+    for (index, object) in scene.objects.iter().enumerate() {
+        if depth == 0 && !object.visibility().visible_to_camera {
+            continue;
+        }
+        let mut i = object.ray_intersect(ray_origin, ray_direction, &scene.prototypes);
+        if !i.is_intersecting {
+            continue;
+        }
+        i.cone_width = i.distance * cone_spread;
+        if settings.sorted_transparency && i.material.opacity < 1.0 {
+            layers.push((i, index));
+        } else {
+            let tied = hit_object.is_some() && (i.distance - zbuffer).abs() <= DEPTH_TIE_TOLERANCE * zbuffer;
+            let nearer = if settings.depth_tie_break && tied { i.point.y > intersect.point.y } else { i.distance < zbuffer };
+            if nearer {
+                runner_up = runner_up.min(zbuffer);
+                zbuffer = i.distance;
+                intersect = i;
+                hit_object = Some(index);
+            } else {
+                runner_up = runner_up.min(i.distance);
+            }
+        }
+    }
+
+    if settings.channel == RenderChannel::DepthConflict {
+        let color = match hit_object {
+            Some(_) if (runner_up - zbuffer).abs() <= DEPTH_TIE_TOLERANCE * zbuffer => DEPTH_CONFLICT_COLOR,
+            Some(_) => Color::from_unit(0.2, 0.2, 0.2),
+            None => Color::black(),
+        };
+        let coverage = if hit_object.is_some() { 1.0 } else { 0.0 };
+        return RaySample { color, coverage, distance: zbuffer, passes: Passes::empty() };
+    }
+
+    let base = match hit_object {
+        Some(index) => {
+            let (mut color, mut passes) = shade_hit(&intersect, index, ray_origin, ray_direction, scene, settings, samples);
+            let traces_secondary = settings.channel == RenderChannel::Final
+                && !intersect.material.unlit
+                && !on_cube_edge(&intersect, &scene.objects[index]);
+            let trace_bounce = |direction: Vec3, weight: f32| {
+                let path_throughput = throughput * weight;
+                let mut weight = weight;
+                if settings.russian_roulette && depth + 1 >= ROULETTE_MIN_DEPTH {
+                    match russian_roulette(path_throughput, hash_point(&direction, depth)) {
+                        Some(survival) => weight /= survival,
+                        None => return Color::black(),
+                    }
+                }
+                let origin = offset_origin(&intersect, &direction);
+                let bounce = cast_ray(&origin, &direction, scene, depth + 1, path_throughput, settings, samples, cone_spread);
+                let background = scene.environment.sample_reflection(&direction) * (1.0 - bounce.coverage);
+                let contribution = (bounce.color + background) * weight;
+                match settings.reflection_clamp {
+                    Some(limit) => contribution.with_max_luminance(limit * 255.0),
+                    None => contribution,
+                }
+            };
+
+            let reflectivity = intersect.material.albedo[2];
+            if traces_secondary && reflectivity > 0.0 && throughput * reflectivity >= settings.reflection_cutoff {
+                let reflected = trace_bounce(reflect(ray_direction, &intersect.normal).normalize(), reflectivity);
+                color = color + reflected;
+                passes.reflection = passes.reflection + reflected;
+            }
+
+            let transmittance = intersect.material.albedo[3];
+            let refractive_index = intersect.material.refractive_index;
+            if traces_secondary && transmittance > 0.0 && refractive_index > 0.0 {
+                let entering = ray_direction.dot(&intersect.normal) < 0.0;
+                let (normal, eta) = if entering { (intersect.normal, 1.0 / refractive_index) } else { (-intersect.normal, refractive_index) };
+                let (direction, weight) = match refract(ray_direction, &normal, eta) {
+                    None => (reflect(ray_direction, &normal).normalize(), transmittance),
+                    Some(direction) => {
+                        let direction = direction.normalize();
+                        // Schlick needs the angle on the less dense side: the incident one going in, the
+                        // transmitted one coming out.
+                        let cos_theta = if entering { -ray_direction.dot(&normal) } else { direction.dot(&intersect.normal) };
+                        (direction, transmittance * (1.0 - fresnel(cos_theta, refractive_index)))
+                    }
+                };
+                if throughput * weight >= settings.reflection_cutoff {
+                    let transmitted = trace_bounce(direction, weight) * intersect.material.transmission_color;
+                    color = color + transmitted;
+                    passes.transmission = transmitted;
+                }
+            }
+            if let Some(limit) = settings.radiance_clamp {
+                color = color.with_max_luminance(limit * 255.0);
+            }
+            (color, 1.0, passes)
+        }
+        None => (Color::black(), 0.0, Passes::empty()),
+    };
+
+    layers.retain(|(layer, _)| layer.distance < zbuffer);
+    layers.sort_by(|(a, _), (b, _)| b.distance.total_cmp(&a.distance));
+    let (color, coverage, passes) = layers.iter().fold(base, |(behind, coverage, behind_passes), (layer, index)| {
+        let (color, passes) = shade_hit(layer, *index, ray_origin, ray_direction, scene, settings, samples);
+        let opacity = layer.material.opacity;
+        let transmitted = behind * layer.material.transmission_color;
+        (transmitted.lerp(color, opacity), mix(coverage, 1.0, opacity), behind_passes.lerp(&passes, opacity))
+    });
+    RaySample { color, coverage, distance: zbuffer, passes }
+}
+
+fn shade_hit(
+    intersect: &Intersect,
+    hit_object: usize,
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    scene: &Scene,
+    settings: &RenderSettings,
+    samples: &Samples,
+) -> (Color, Passes) {
+    let geometry = Passes { normal: intersect.normal, ..Passes::empty() };
+    match settings.channel {
+        RenderChannel::Normal => {
+            let n = intersect.normal * 0.5 + Vec3::new(0.5, 0.5, 0.5);
+            return (Color::from_unit(n.x, n.y, n.z), geometry);
+        }
+        RenderChannel::Depth => {
+            let shade = 1.0 - intersect.distance / RenderChannel::DEPTH_RANGE;
+            return (Color::from_unit(shade, shade, shade), geometry);
+        }
+        RenderChannel::UV => {
+            let (u, v) = intersect.uv;
+            return (Color::from_unit(u, v, 0.0), geometry);
+        }
+        RenderChannel::Mask => {
+            let shade = if scene.objects[hit_object].visibility().masked { 1.0 } else { 0.0 };
+            return (Color::from_unit(shade, shade, shade), geometry);
+        }
+        RenderChannel::ObjectId => return (id_color(hit_object), geometry),
+        _ => {}
+    }
+
+    if on_cube_edge(intersect, &scene.objects[hit_object]) {
+        let edge_color = intersect.material.edge_color;
+        return (edge_color, Passes { emission: edge_color, ..geometry });
+    }
+    if intersect.material.unlit {
+        let flat = monitor_texel(intersect, &scene.objects[hit_object], scene).unwrap_or(intersect.material.diffuse);
+        return (flat, Passes { emission: flat, ..geometry });
+    }
+
+    let mut total_diffuse = Color::black();
+    let mut removed_diffuse = Color::black();
+    let mut total_specular = Color::black();
+    let mut coat_specular = Color::black();
+
+    let cos_theta = (-ray_direction).dot(&intersect.normal).max(0.0);
+    let fresnel_effect = fresnel(cos_theta, intersect.material.refractive_index);
+    let coat_fresnel = intersect.material.clear_coat * fresnel(cos_theta, CLEAR_COAT_REFRACTIVE_INDEX);
+
+    for (light_index, light_weight) in select_lights(&scene.lights, intersect, settings.light_samples) {
+        let light = &scene.lights[light_index];
+        let light_position = &light.position;
+        let light_dir = (light_position - intersect.point).normalize();
+        let view_dir = (ray_origin - intersect.point).normalize();
+        let reflect_dir = reflect(&-light_dir, &intersect.normal).normalize();
+
+        let attenuation = light.attenuation((light_position - intersect.point).magnitude());
+        if attenuation <= 0.0 {
+            continue;
+        }
+
+        let shadow_intensity = if !light.casts_shadows {
+            0.0
+        } else if settings.shadow_mode == ShadowMode::Blob {
+            match scene.objects[hit_object] {
+                Object::Plane(..) => blob_shadow(intersect, light_position, scene),
+                _ => 0.0,
+            }
+        } else {
+            match &scene.shadow_map {
+                Some((index, map)) if *index == light_index && settings.shadow_mode == ShadowMode::ShadowMap => map.shadow(&intersect.point),
+                _ => cast_shadow(intersect, light, settings.light_radius, settings.contact_hardening, scene, &samples.shadow, Some(hit_object)),
+            }
+        };
+        let light_intensity = settings.light_scale * (1.0 - shadow_intensity) * attenuation * light_weight;
+
+        let diffuse_intensity = saturate(intersect.normal.dot(&light_dir));
+        let diffuse = intersect.material.diffuse * light.color * intersect.material.albedo[0] * diffuse_intensity * light_intensity;
+        if light.negative {
+            removed_diffuse = removed_diffuse + diffuse;
+            continue;
+        }
+        total_diffuse = total_diffuse + diffuse;
+
+        let specular_intensity = match settings.specular_model {
+            SpecularModel::Phong => view_dir.dot(&reflect_dir),
+            SpecularModel::BlinnPhong => intersect.normal.dot(&(light_dir + view_dir).normalize()),
+        }
+        .max(0.0)
+        .powf(intersect.material.specular);
+        total_specular = total_specular
+            + scale_soft_clipped(
+                intersect.material.specular_color * light.color,
+                intersect.material.albedo[1] * specular_intensity * light_intensity * fresnel_effect,
+                settings.specular_knee,
+            );
+
+        if coat_fresnel > 0.0 {
+            let coat_intensity = intersect.normal.dot(&(light_dir + view_dir).normalize()).max(0.0).powf(intersect.material.clear_coat_exponent());
+            coat_specular = coat_specular + scale_soft_clipped(light.color, coat_intensity * light_intensity * coat_fresnel, settings.specular_knee);
+        }
+    }
+
+    if let (Some((light_index, caustics)), Object::Plane(..)) = (&scene.caustics, &scene.objects[hit_object]) {
+        if let (Some(density), Some(light)) = (caustics.density(&intersect.point), scene.lights.get(*light_index)) {
+            let diffuse_intensity = saturate(intersect.normal.dot(&(light.position - intersect.point).normalize()));
+            let caustic_intensity = settings.light_scale * settings.caustics_strength * density * diffuse_intensity;
+            total_diffuse = total_diffuse + intersect.material.diffuse * light.color * (intersect.material.albedo[0] * caustic_intensity);
+        }
+    }
+    let total_diffuse = total_diffuse - removed_diffuse;
+
+    let emission = match monitor_texel(intersect, &scene.objects[hit_object], scene) {
+        Some(texel) => texel,
+        None if intersect.material.is_emissive => intersect.material.emission,
+        None => Color::black(),
+    };
+
+    let reflect_environment = |surface: &Intersect, weight: f32| {
+        if !settings.env_reflection || weight < settings.reflection_cutoff {
+            return Color::black();
+        }
+        let reflection = glossy_environment(ray_direction, surface, &scene.environment, &samples.glossy, settings.firefly_clamp) * weight;
+        match settings.reflection_clamp {
+            Some(limit) => reflection.with_max_luminance(limit * 255.0),
+            None => reflection,
+        }
+    };
+    let env_reflection = reflect_environment(intersect, ENV_REFLECTION_STRENGTH * (1.0 - intersect.material.roughness) * fresnel_effect);
+    let coat_reflection = if coat_fresnel > 0.0 {
+        let mut coat = intersect.clone();
+        coat.material.roughness = intersect.material.clear_coat_roughness;
+        reflect_environment(&coat, ENV_REFLECTION_STRENGTH * (1.0 - coat.material.roughness) * coat_fresnel)
+    } else {
+        Color::black()
+    };
+
+    let ambient = skylight(intersect, scene, &samples.skylight, Some(hit_object))
+        * intersect.material.diffuse
+        * (intersect.material.albedo[0] * SKYLIGHT_STRENGTH);
+
+    let base_weight = 1.0 - coat_fresnel;
+    let total_diffuse = total_diffuse * base_weight;
+    let total_specular = total_specular * base_weight + coat_specular;
+    let passes = Passes {
+        diffuse: total_diffuse + ambient * base_weight,
+        specular: total_specular,
+        reflection: env_reflection * base_weight + coat_reflection,
+        transmission: Color::black(),
+        emission,
+        normal: intersect.normal,
+    };
+    let color = match settings.channel {
+        RenderChannel::Diffuse => total_diffuse,
+        RenderChannel::Specular => total_specular,
+        _ => passes.beauty(),
+    };
+    match settings.radiance_clamp {
+        Some(limit) => (color.with_max_luminance(limit * 255.0), passes),
+        None => (color, passes),
+    }
+}
+
+
+
+pub fn render(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    region: Option<(usize, usize, usize, usize)>,
+) -> RenderStats {
+    render_tiled(framebuffer, scene, camera, settings, region, (TILE_SIZE, TILE_SIZE))
+}
+
+/// `render` with `tile` (width, height) pieces of the region as the unit of work. Every worker of the pool this is
+/// installed in pulls the next tile from a shared counter and writes it to the framebuffer as soon as it is traced,
+/// so a few expensive tiles don't leave the other threads idle. Outside a pool the calling thread traces them all.
+/// Each pixel is seeded from its own coordinates, so the image doesn't depend on the tile size or thread count.
+pub fn render_tiled(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    region: Option<(usize, usize, usize, usize)>,
+    tile: (usize, usize),
+) -> RenderStats {
+    let (x0, y0, x1, y1) = region.unwrap_or((0, 0, framebuffer.width, framebuffer.height));
+    let region = (
+        x0.min(framebuffer.width),
+        y0.min(framebuffer.height),
+        x1.min(framebuffer.width),
+        y1.min(framebuffer.height),
+    );
+    let tile = (tile.0.max(1), tile.1.max(1));
+
+    if settings.debug_non_finite {
+        render_pixels::<true>(framebuffer, scene, camera, settings, region, tile)
+    } else {
+        render_pixels::<false>(framebuffer, scene, camera, settings, region, tile)
+    }
+}
+
+fn render_pixels<const CHECK_NON_FINITE: bool>(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    (x0, y0, x1, y1): (usize, usize, usize, usize),
+    (tile_width, tile_height): (usize, usize),
+) -> RenderStats {
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+    let perspective_scale = (camera.fov * 0.5).tan();
+    let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+    let cone_spread = 2.0 * perspective_scale / height;
+    let has_backdrop = framebuffer.has_backdrop();
+
+    let offsets = settings.sample_pattern.offsets(settings.aa_samples);
+    let weights = settings.reconstruction_filter.weights(&offsets);
+
+    let trace_sample = |x: f32, y: f32| {
+        let trace = |lens_scale: f32| {
+            let ray_direction = camera.pixel_direction(x, y, width, height, lens_scale);
+            let rotated_direction = camera.base_change(&ray_direction);
+            let sample = cast_ray(&camera.eye, &rotated_direction, scene, 0, 1.0, settings, &samples, cone_spread);
+            if has_backdrop || sample.coverage >= 1.0 {
+                return sample;
+            }
+            let background = match settings.channel {
+                RenderChannel::Final => scene.environment.sample(&rotated_direction),
+                _ => Color::black(),
+            };
+            RaySample {
+                color: sample.color + background * (1.0 - sample.coverage),
+                coverage: 1.0,
+                ..sample
+            }
+        };
+
+        if settings.chromatic_aberration == 0.0 {
+            trace(1.0)
+        } else {
+            let green = trace(1.0);
+            RaySample {
+                color: Color::from_channels(
+                    trace(1.0 + settings.chromatic_aberration).color,
+                    green.color,
+                    trace(1.0 - settings.chromatic_aberration).color,
+                ),
+                ..green
+            }
+        }
+    };
+
+    let hits_geometry = |x: f32, y: f32| {
+        let direction = camera.base_change(&camera.pixel_direction(x, y, width, height, 1.0));
+        primary_hit(&camera.eye, &direction, scene)
+    };
+
+    let trace_edge = |x: f32, y: f32| {
+        let center = trace_sample(x, y);
+        let center_hit = center.distance.is_finite();
+        let corners = [(x - 0.5, y - 0.5), (x + 0.5, y - 0.5), (x - 0.5, y + 0.5), (x + 0.5, y + 0.5)];
+        let corner_hits = corners.map(|(cx, cy)| hits_geometry(cx, cy));
+        let hits = corner_hits.iter().filter(|&&hit| hit).count() + center_hit as usize;
+        if hits == 0 || hits == corners.len() + 1 {
+            return center;
+        }
+
+        let other = corners
+            .iter()
+            .zip(corner_hits)
+            .find(|&(_, hit)| hit != center_hit)
+            .map(|(&(cx, cy), _)| trace_sample(cx, cy))
+            .unwrap_or(center);
+        let (geometry, sky) = if center_hit { (center, other) } else { (other, center) };
+        let coverage = hits as f32 / (corners.len() + 1) as f32;
+        RaySample {
+            color: sky.color.lerp(geometry.color, coverage),
+            coverage: mix(sky.coverage, geometry.coverage, coverage),
+            distance: geometry.distance,
+            passes: sky.passes.lerp(&geometry.passes, coverage),
+        }
+    };
+
+    let trace_pixel = |x: usize, y: usize| {
+        if let [(dx, dy)] = offsets[..] {
+            if settings.edge_antialiasing {
+                return trace_edge(x as f32 + dx, y as f32 + dy);
+            }
+            return trace_sample(x as f32 + dx, y as f32 + dy);
+        }
+        let traced: Vec<(RaySample, f32)> =
+            offsets.iter().zip(&weights).map(|(&(dx, dy), &weight)| (trace_sample(x as f32 + dx, y as f32 + dy), weight)).collect();
+        let passes: Vec<(Passes, f32)> = traced.iter().map(|(sample, weight)| (sample.passes, *weight)).collect();
+        RaySample {
+            color: Color::weighted_sum(traced.iter().map(|(sample, weight)| (sample.color, *weight))),
+            coverage: traced.iter().map(|(sample, weight)| sample.coverage * weight).sum::<f32>().clamp(0.0, 1.0),
+            distance: traced.iter().map(|(sample, _)| sample.distance).fold(f32::INFINITY, f32::min),
+            passes: Passes::weighted_sum(&passes),
+        }
+    };
+
+    let pixel_count = x1.saturating_sub(x0) * y1.saturating_sub(y0);
+    if pixel_count == 0 {
+        return RenderStats::default();
+    }
+    let tiles_x = (x1 - x0).div_ceil(tile_width);
+    let tile_count = tiles_x * (y1 - y0).div_ceil(tile_height);
+    let next_tile = AtomicUsize::new(0);
+    let non_finite_pixels = AtomicUsize::new(0);
+    let output = Mutex::new(framebuffer);
+
+    let work = || {
+        let mut traced = Vec::with_capacity(tile_width * tile_height);
+        loop {
+            let index = next_tile.fetch_add(1, Ordering::Relaxed);
+            if index >= tile_count {
+                break;
+            }
+            let tile_x0 = x0 + (index % tiles_x) * tile_width;
+            let tile_y0 = y0 + (index / tiles_x) * tile_height;
+            let (tile_x1, tile_y1) = ((tile_x0 + tile_width).min(x1), (tile_y0 + tile_height).min(y1));
+
+            traced.clear();
+            for y in tile_y0..tile_y1 {
+                for x in tile_x0..tile_x1 {
+                    let mut sample = trace_pixel(x, y);
+                    if CHECK_NON_FINITE && !sample.color.is_finite() {
+                        non_finite_pixels.fetch_add(1, Ordering::Relaxed);
+                        sample.color = NON_FINITE_MARKER;
+                        sample.coverage = 1.0;
+                    }
+                    traced.push(sample);
+                }
+            }
+
+            let mut framebuffer = output.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            for (offset, sample) in traced.iter().enumerate() {
+                let (x, y) = (tile_x0 + offset % (tile_x1 - tile_x0), tile_y0 + offset / (tile_x1 - tile_x0));
+                framebuffer.write_covered(x, y, sample.color, sample.coverage);
+                framebuffer.set_depth(x, y, sample.distance);
+                framebuffer.set_passes(x, y, &sample.passes);
+            }
+        }
+    };
+    if rayon::current_thread_index().is_some() {
+        rayon::broadcast(|_| work());
+    } else {
+        work();
+    }
+
+    RenderStats { pixels: pixel_count, non_finite_pixels: non_finite_pixels.into_inner() }
+}
+
+pub fn render_motion_blurred(
+    framebuffer: &mut Framebuffer,
+    scene: &mut Scene,
+    beach: &Beach,
+    camera: &Camera,
+    settings: &RenderSettings,
+    frame_start: f32,
+    frame_end: f32,
+) -> RenderStats {
+    let sub_frames = settings.motion_blur_samples + 1;
+    if sub_frames == 1 {
+        return render(framebuffer, scene, camera, settings, None);
+    }
+
+    let mut sums = vec![[0.0; 3]; framebuffer.buffer.len()];
+    let mut stats = RenderStats::default();
+    for i in 0..sub_frames {
+        let t = frame_start + (frame_end - frame_start) * (i + 1) as f32 / sub_frames as f32;
+        scene.objects = beach.objects_at(t, settings.wind_strength);
+        let sub_stats = render(framebuffer, scene, camera, settings, None);
+        stats.pixels += sub_stats.pixels;
+        stats.non_finite_pixels += sub_stats.non_finite_pixels;
+        framebuffer.accumulate(&mut sums);
+    }
+    framebuffer.resolve_average(&sums, sub_frames as u32);
+
+    stats
+}
+
+pub fn render_to_rgba(
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    width: usize,
+    height: usize,
+    backdrop: Option<&Texture>,
+) -> Vec<u8> {
+    render_offscreen(scene, camera, settings, width, height, backdrop, false).to_rgba()
+}
+
+pub fn render_offscreen(
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    width: usize,
+    height: usize,
+    backdrop: Option<&Texture>,
+    record_passes: bool,
+) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(width, height);
+    if let Some(backdrop) = backdrop {
+        framebuffer.set_backdrop(backdrop);
+    }
+    if record_passes {
+        framebuffer.record_passes();
+    }
+    configure_display(&mut framebuffer, settings);
+    render(&mut framebuffer, scene, camera, settings, None);
+    apply_post_effects(&mut framebuffer, settings);
+    framebuffer
+}
+
+pub fn apply_post_effects(framebuffer: &mut Framebuffer, settings: &RenderSettings) {
+    if settings.bloom {
+        framebuffer.bloom(settings.bloom_threshold, settings.bloom_tint, BLOOM_RADIUS, BLOOM_STRENGTH);
+    }
+    match settings.post_blur {
+        PostBlur::Off => {}
+        PostBlur::Radial => {
+            let center = (framebuffer.width as f32 * 0.5, framebuffer.height as f32 * 0.5);
+            framebuffer.radial_blur(center, RADIAL_BLUR_STRENGTH);
+        }
+        PostBlur::Directional => framebuffer.directional_blur(0.0, DIRECTIONAL_BLUR_STRENGTH),
+    }
+}
+
+pub fn configure_display(framebuffer: &mut Framebuffer, settings: &RenderSettings) {
+    if settings.channel.is_label() {
+        framebuffer.exposure = 1.0;
+        framebuffer.tone_map = ToneMap::None;
+        framebuffer.color_space = OutputColorSpace::Linear;
+    } else {
+        framebuffer.exposure = settings.exposure;
+        framebuffer.tone_map = settings.tone_map;
+        framebuffer.color_space = settings.color_space;
+    }
+}
+
+pub fn render_monitor(scene: &mut Scene, framebuffer: &mut Framebuffer, camera: &Camera, settings: &RenderSettings) {
+    configure_display(framebuffer, settings);
+    render(framebuffer, scene, camera, settings, None);
+    scene.monitors = vec![framebuffer.to_texture()];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::environment::{EnvMap, SkyGradient};
+    use crate::material::Material;
+    use crate::object::Visibility;
+    use crate::render_pool::RenderPool;
+    use crate::sampling::{hammersley, hash_index, sphere_samples};
+
+    fn mirror_scene(reflectivity: f32) -> Scene {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        let mirror = Material::new(Color::new(200, 200, 200), 10.0, [0.5, 0.0, reflectivity, 0.0], 0.0, Color::black(), false);
+        scene.objects.push(Object::Cube(Cube::new(Vec3::zeros(), 2.0, mirror), Visibility::default()));
+        scene
+    }
+
+    fn trace(scene: &Scene, throughput: f32, settings: &RenderSettings) -> RaySample {
+        let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+        let direction = Vec3::new(0.1, 0.2, -1.0).normalize();
+        cast_ray(&Vec3::new(0.0, 0.0, 5.0), &direction, scene, 0, throughput, settings, &samples, 0.0)
+    }
+
+    #[test]
+    fn reflection_cutoff_skips_faint_bounces() {
+        let scene = mirror_scene(0.02);
+        let mut settings = RenderSettings::default();
+        let traced = trace(&scene, 1.0, &settings);
+        assert_ne!(traced.passes.reflection, Color::black());
+
+        settings.reflection_cutoff = 0.05;
+        let skipped = trace(&scene, 1.0, &settings);
+        assert_eq!(skipped.passes.reflection, Color::black());
+        let difference = (traced.color.luminance() - skipped.color.luminance()).abs();
+        assert!(difference <= 0.02 * 255.0, "skipping a faint reflection changed luminance by {}", difference);
+    }
+
+    #[test]
+    fn reflection_cutoff_uses_path_throughput() {
+        let scene = mirror_scene(0.5);
+        let settings = RenderSettings { reflection_cutoff: 0.1, ..RenderSettings::default() };
+        assert_ne!(trace(&scene, 1.0, &settings).passes.reflection, Color::black());
+        assert_eq!(trace(&scene, 0.1, &settings).passes.reflection, Color::black());
+    }
+
+    #[test]
+    fn bounces_respect_the_clamps() {
+        let scene = mirror_scene(1.0);
+        let settings = RenderSettings { reflection_clamp: Some(0.05), radiance_clamp: Some(0.1), ..RenderSettings::default() };
+        let sample = trace(&scene, 1.0, &settings);
+        assert!(sample.passes.reflection.luminance() <= 0.05 * 255.0 + 1e-3);
+        assert!(sample.color.luminance() <= 0.1 * 255.0 + 1e-3);
+    }
+
+    #[test]
+    fn refraction_is_tinted_by_transmission_color() {
+        let mut scene = mirror_scene(0.0);
+        let tinted = Material::glass().with_transmission_color(Color::new(255, 0, 0));
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, tinted), Visibility::default());
+        let transmission = trace(&scene, 1.0, &RenderSettings::default()).passes.transmission;
+        assert!(transmission.red() > 0);
+        assert_eq!((transmission.green(), transmission.blue()), (0, 0));
+    }
+
+    #[test]
+    fn render_matches_across_thread_counts() {
+        let scene = mirror_scene(0.5);
+        let camera = Camera::new(Vec3::new(2.0, 1.5, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let frame = |threads: Option<usize>| {
+            let mut framebuffer = Framebuffer::new(48, 40);
+            let settings = RenderSettings::default();
+            match threads {
+                Some(threads) => RenderPool::new(threads).install(threads, || render(&mut framebuffer, &scene, &camera, &settings, None)),
+                None => render(&mut framebuffer, &scene, &camera, &settings, None),
+            };
+            framebuffer.buffer
+        };
+        let serial = frame(None);
+        assert_eq!(serial, frame(Some(1)));
+        assert_eq!(serial, frame(Some(3)));
+        assert_eq!(serial, frame(Some(0)));
+    }
+
+    #[test]
+    fn tile_shape_does_not_change_the_image() {
+        let scene = mirror_scene(0.5);
+        let camera = Camera::new(Vec3::new(2.0, 1.5, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let settings = RenderSettings::default();
+        let frame = |tile: (usize, usize)| {
+            let mut framebuffer = Framebuffer::new(45, 38);
+            let stats = RenderPool::new(2).install(2, || render_tiled(&mut framebuffer, &scene, &camera, &settings, None, tile));
+            assert_eq!(stats.pixels, 45 * 38);
+            let depths: Vec<f32> = (0..45 * 38).map(|index| framebuffer.depth(index % 45, index / 45)).collect();
+            (framebuffer.buffer, depths)
+        };
+        let tiles = frame((TILE_SIZE, TILE_SIZE));
+        assert_eq!(tiles, frame((45, 1)));
+        assert_eq!(tiles, frame((7, 5)));
+        assert_eq!(tiles, frame((1000, 1000)));
+    }
+
+    #[test]
+    fn a_convex_object_never_shadows_itself() {
+        let scene = mirror_scene(0.0);
+        let samples = sphere_samples(4);
+        for (i, light_position) in [Vec3::new(4.0, 6.0, 3.0), Vec3::new(-5.0, 0.5, 0.2), Vec3::new(0.0, -6.0, 1.0)].iter().enumerate() {
+            let light = Light::white(*light_position);
+            for step in 0..64 {
+                let origin = Vec3::new((step % 8) as f32 * 0.2 - 0.8, (step / 8) as f32 * 0.2 - 0.8, 3.0);
+                let direction = Vec3::new(0.02 * i as f32, 0.0, -1.0).normalize();
+                let hit = scene.objects[0].ray_intersect(&origin, &direction, &scene.prototypes);
+                assert!(hit.is_intersecting, "{:?} {:?}", origin, direction);
+                assert_eq!(cast_shadow(&hit, &light, 0.3, 0.0, &scene, &samples, Some(0)), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn chromatic_aberration_splits_channels_only_when_enabled() {
+        let grey = Color::new(200, 200, 200);
+        let sky = SkyGradient { sky_day: grey, sky_night: grey, below_horizon_day: grey, below_horizon_night: grey };
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(sky)));
+        scene.objects.push(Object::Cube(Cube::new(Vec3::zeros(), 2.0, Material::matte(Color::new(90, 90, 90))), Visibility::default()));
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 6.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let split_pixels = |chromatic_aberration: f32| {
+            let mut framebuffer = Framebuffer::new(64, 48);
+            render(&mut framebuffer, &scene, &camera, &RenderSettings { chromatic_aberration, ..RenderSettings::default() }, None);
+            framebuffer.buffer.iter().filter(|&&pixel| (pixel >> 16) & 0xFF != pixel & 0xFF).count()
+        };
+        assert_eq!(split_pixels(0.0), 0);
+        assert!(split_pixels(0.05) > 0);
+    }
+
+    #[test]
+    fn complementary_regions_add_up_to_the_full_frame() {
+        let scene = mirror_scene(0.5);
+        let camera = Camera::new(Vec3::new(2.0, 1.5, 5.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let settings = RenderSettings::default();
+        let mut full = Framebuffer::new(40, 30);
+        render(&mut full, &scene, &camera, &settings, None);
+
+        let mut split = Framebuffer::new(40, 30);
+        render(&mut split, &scene, &camera, &settings, Some((0, 0, 40, 13)));
+        render(&mut split, &scene, &camera, &settings, Some((0, 13, 40, 30)));
+        assert_eq!(split.buffer, full.buffer);
+    }
+
+    #[test]
+    fn monitor_faces_show_the_secondary_framebuffer() {
+        let mut feed = Framebuffer::new(4, 2);
+        for y in 0..2 {
+            for x in 0..4 {
+                feed.set_hdr_pixel(x, y, if x < 2 { [1.0, 0.0, 0.0] } else { [0.0, 0.0, 1.0] });
+            }
+        }
+        let mut scene = mirror_scene(0.0);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, Material::matte(Color::new(0, 255, 0)).unlit().with_monitor(0)), Visibility::default());
+        scene.monitors = vec![feed.to_texture()];
+
+        let settings = RenderSettings::default();
+        let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+        let texel = |x: f32| cast_ray(&Vec3::new(x, 0.0, 5.0), &Vec3::new(0.0, 0.0, -1.0), &scene, 0, 1.0, &settings, &samples, 0.0).color;
+        let (left, right) = (texel(-0.5), texel(0.5));
+        assert_eq!(left.green(), 0);
+        assert_eq!(right.green(), 0);
+        assert!((left.red() > 200 && right.blue() > 200) || (left.blue() > 200 && right.red() > 200), "{:?} / {:?}", left, right);
+    }
+
+    #[test]
+    fn rays_near_a_cube_corner_return_the_edge_color() {
+        let mut scene = mirror_scene(0.0);
+        let edged = Material::matte(Color::new(200, 200, 200)).with_edges(Color::new(10, 20, 30), 0.05);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, edged), Visibility::default());
+        let settings = RenderSettings::default();
+        let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+        let shade = |scene: &Scene, x: f32, y: f32| cast_ray(&Vec3::new(x, y, 5.0), &Vec3::new(0.0, 0.0, -1.0), scene, 0, 1.0, &settings, &samples, 0.0).color;
+        assert_eq!(shade(&scene, 0.97, 0.97), Color::new(10, 20, 30));
+        assert_ne!(shade(&scene, 0.0, 0.0), Color::new(10, 20, 30));
+
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, Material::matte(Color::new(200, 200, 200))), Visibility::default());
+        assert_ne!(shade(&scene, 0.97, 0.97), Color::new(10, 20, 30));
+    }
+
+    #[test]
+    fn sampled_lights_converge_to_all_lights() {
+        let lights = vec![
+            Light::new(Vec3::new(0.0, 4.0, 0.0), Color::new(255, 255, 255)),
+            Light::new(Vec3::new(3.0, 1.0, 0.0), Color::new(80, 60, 40)),
+            Light::new(Vec3::new(-2.0, 2.0, 1.0), Color::new(40, 40, 255)),
+        ];
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let facing = |light: &Light, point: &Vec3| normal.dot(&(light.position - point).normalize()).max(0.0) * light.color.luminance();
+
+        let points: Vec<Vec3> = (0..4000).map(|i| Vec3::new((i % 64) as f32 * 0.01, 0.0, (i / 64) as f32 * 0.01)).collect();
+        let (mut exact, mut sampled) = (0.0, 0.0);
+        for point in &points {
+            let intersect = Intersect::new(*point, normal, 1.0, Material::sand());
+            exact += lights.iter().map(|light| facing(light, point)).sum::<f32>();
+            sampled += select_lights(&lights, &intersect, 1).iter().map(|&(index, weight)| weight * facing(&lights[index], point)).sum::<f32>();
+            assert_eq!(select_lights(&lights, &intersect, 3).len(), 3);
+        }
+        let error = (sampled - exact).abs() / exact;
+        assert!(error < 0.02, "sampled estimate off by {:.1}%", error * 100.0);
+    }
+
+    #[test]
+    fn sorted_layers_match_the_over_operator() {
+        let white = Color::new(255, 255, 255);
+        let layers = [(Color::new(255, 0, 0), 0.5), (Color::new(0, 255, 0), 0.25), (Color::new(0, 0, 255), 0.4)];
+        let glass = |color: Color, opacity: f32| Material::matte(color).unlit().with_opacity(opacity).with_transmission_color(white);
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        for (depth, &(color, opacity)) in layers.iter().enumerate() {
+            let center = Vec3::new(0.0, 0.0, -3.0 * depth as f32);
+            scene.objects.push(Object::Cube(Cube::new(center, 2.0, glass(color, opacity)), Visibility::default()));
+        }
+        let settings = RenderSettings { sorted_transparency: true, ..RenderSettings::default() };
+        let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+        let shoot = |scene: &Scene| cast_ray(&Vec3::new(0.0, 0.0, 5.0), &Vec3::new(0.0, 0.0, -1.0), scene, 0, 1.0, &settings, &samples, 0.0);
+
+        let alone = shoot(&scene);
+        let expected_coverage = 1.0 - layers.iter().map(|&(_, opacity)| 1.0 - opacity).product::<f32>();
+        assert!((alone.coverage - expected_coverage).abs() < 1e-5);
+
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(0.0, 0.0, -9.0), 2.0, Material::matte(white).unlit()), Visibility::default()));
+        let expected = layers.iter().rev().fold(white, |behind, &(color, opacity)| behind.lerp(color, opacity));
+        let composited = shoot(&scene);
+        assert_eq!(composited.coverage, 1.0);
+        assert_eq!((composited.color.red(), composited.color.green(), composited.color.blue()), (expected.red(), expected.green(), expected.blue()));
+    }
+
+    #[test]
+    fn closer_occluders_cast_narrower_penumbras() {
+        let light = Light::white(Vec3::new(0.0, 10.0, 0.0));
+        let samples = sphere_samples(32);
+        let penumbra_width = |occluder_height: f32, contact_hardening: f32| {
+            let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+            scene.objects.push(Object::Cube(Cube::new(Vec3::new(-0.5, occluder_height, 0.0), 1.0, Material::sand()), Visibility::default()));
+            let receiver = |x: f32| Intersect::new(Vec3::new(x, -0.5, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand());
+            let shadow = |x: f32| cast_shadow(&receiver(x), &light, 1.0, contact_hardening, &scene, &samples, None);
+            let umbra = shadow(-0.5);
+            (0..400).map(|i| shadow(-1.0 + i as f32 * 0.01) / umbra).filter(|&shadow| shadow > 0.05 && shadow < 0.95).count()
+        };
+        assert!(penumbra_width(0.0, 1.0) < penumbra_width(4.0, 1.0));
+        assert!(penumbra_width(0.0, 1.0) < penumbra_width(0.0, 0.0), "contact hardening should tighten a contact shadow");
+    }
+
+    #[test]
+    fn blob_shadows_darken_only_beneath_objects() {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand()), Visibility::default()));
+        let light = Vec3::new(0.0, 20.0, 0.0);
+        let ground = |x: f32| Intersect::new(Vec3::new(x, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand());
+        assert!(blob_shadow(&ground(0.0), &light, &scene) > 0.5 * BLOB_DARKNESS);
+        assert_eq!(blob_shadow(&ground(10.0), &light, &scene), 0.0);
+
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand()), Visibility { casts_shadow: false, ..Visibility::default() });
+        assert_eq!(blob_shadow(&ground(0.0), &light, &scene), 0.0);
+    }
+
+    #[test]
+    fn missed_pixels_keep_the_backdrop_exactly() {
+        let (width, height) = (32, 24);
+        let pixels = (0..width * height).map(|i| Color::new((i % 251) as u8, (i * 7 % 256) as u8, 90)).collect();
+        let backdrop = Texture::from_pixels(width, height, pixels);
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.set_backdrop(&backdrop);
+        framebuffer.clear();
+
+        let mut scene = mirror_scene(0.0);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 1.0, Material::sand()), Visibility::default());
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 6.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        render(&mut framebuffer, &scene, &camera, &RenderSettings::default(), None);
+
+        let mut kept = 0;
+        for y in 0..height {
+            for x in 0..width {
+                let original = backdrop.texel(x, y).to_hex();
+                let edge = x < 4 || x >= width - 4 || y < 4 || y >= height - 4;
+                if edge {
+                    assert_eq!(framebuffer.get_pixel(x, y), Some(original), "pixel ({}, {})", x, y);
+                    kept += 1;
+                }
+            }
+        }
+        assert!(kept > 0);
+        assert_ne!(framebuffer.get_pixel(width / 2, height / 2), Some(backdrop.texel(width / 2, height / 2).to_hex()));
+    }
+
+    #[test]
+    fn skylight_converges_to_the_visible_environment_radiance() {
+        let ground = Intersect::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand());
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        let samples = hammersley(1024);
+        assert_eq!(skylight(&ground, &scene, &[], None), Color::black());
+
+        // Cosine-weighted mean of the sky's luminance over the upper hemisphere, restricted to `visible` directions,
+        // integrated on a fine latitude/longitude grid.
+        let radiance = |scene: &Scene, visible: &dyn Fn(&Vec3) -> bool| {
+            let (rings, segments) = (128, 256);
+            let mut sum = 0.0;
+            for ring in 0..rings {
+                let theta = (ring as f32 + 0.5) / rings as f32 * std::f32::consts::FRAC_PI_2;
+                for segment in 0..segments {
+                    let phi = (segment as f32 + 0.5) / segments as f32 * std::f32::consts::TAU;
+                    let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                    if visible(&direction) {
+                        sum += scene.environment.sample(&direction).luminance() * theta.cos() * theta.sin();
+                    }
+                }
+            }
+            sum * std::f32::consts::FRAC_PI_2 / rings as f32 * std::f32::consts::TAU / segments as f32 / std::f32::consts::PI
+        };
+
+        let open = skylight(&ground, &scene, &samples, None).luminance();
+        let expected = radiance(&scene, &|_| true);
+        assert!((open - expected).abs() < 0.02 * expected, "open sky gave {} expected {}", open, expected);
+        assert!((open - SKYBOX_COLOR.luminance()).abs() > 0.05 * expected, "the sky gradient should differ from the flat sky color");
+
+        // A wall filling the x > 0 half of the sky hides the light from that side.
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(1000.01, 0.0, 0.0), 2000.0, Material::sand()), Visibility::default()));
+        let half = skylight(&ground, &scene, &samples, None).luminance();
+        let expected = radiance(&scene, &|direction| direction.x < 0.0);
+        assert!((half - expected).abs() < 0.03 * expected, "half sky gave {} expected {}", half, expected);
+    }
+
+    #[test]
+    fn passes_add_up_to_the_beauty_color() {
+        let materials = [
+            Material::matte(Color::new(180, 120, 90)),
+            Material::metal(Color::new(200, 200, 220)),
+            Material::emissive(Color::new(255, 200, 100), 0.5),
+            Material::new(Color::new(200, 200, 200), 10.0, [0.5, 0.2, 0.3, 0.0], 0.0, Color::black(), false),
+        ];
+        for material in materials {
+            let mut scene = mirror_scene(0.0);
+            scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, material), Visibility::default());
+            scene.lights.push(Light::white(Vec3::new(2.0, 5.0, 4.0)));
+            let sample = trace(&scene, 1.0, &RenderSettings::default());
+            let (beauty, color) = (sample.passes.beauty(), sample.color);
+            for (pass, total) in [(beauty.red(), color.red()), (beauty.green(), color.green()), (beauty.blue(), color.blue())] {
+                assert!(pass.abs_diff(total) <= 1, "passes sum to {:?} but the sample is {:?}", beauty, color);
+            }
+            assert!(color.luminance() > 0.0);
+        }
+    }
+
+    #[test]
+    fn edge_coverage_is_full_on_geometry_empty_on_sky_and_partial_between() {
+        let (width, height) = (40, 30);
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.set_backdrop(&Texture::from_pixels(1, 1, vec![Color::black()]));
+        framebuffer.clear();
+        let mut scene = mirror_scene(0.0);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, Material::matte(Color::new(255, 255, 255)).unlit()), Visibility::default());
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 6.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        render(&mut framebuffer, &scene, &camera, &RenderSettings { edge_antialiasing: true, ..RenderSettings::default() }, None);
+
+        let coverage = |x: usize, y: usize| (framebuffer.get_pixel(x, y).unwrap() & 0xFF) as f32 / 255.0;
+        assert!(coverage(width / 2, height / 2) > 0.99);
+        assert_eq!(coverage(0, 0), 0.0);
+        let row: Vec<f32> = (0..width).map(|x| coverage(x, height / 2)).collect();
+        assert!(row.iter().any(|&c| c > 0.05 && c < 0.95), "no partial coverage along the silhouette: {:?}", row);
+    }
+
+    #[test]
+    fn green_transmission_makes_refraction_greener_than_clear_glass() {
+        let transmitted = |transmission_color: Color| {
+            let mut scene = mirror_scene(0.0);
+            let glass = Material::glass().with_transmission_color(transmission_color);
+            scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, glass), Visibility::default());
+            trace(&scene, 1.0, &RenderSettings::default()).passes.transmission
+        };
+        let (clear, green) = (transmitted(Color::new(255, 255, 255)), transmitted(Color::new(60, 255, 60)));
+        let greenness = |color: Color| color.green() as f32 / (color.red() as f32 + color.blue() as f32 + 1.0);
+        assert!(greenness(green) > greenness(clear), "green {:?} vs clear {:?}", green, clear);
+        assert_eq!(green.green(), clear.green());
+        assert_eq!(Material::glass().transmission_color, Color::new(255, 255, 255));
+    }
+
+    #[test]
+    fn clamps_bound_a_two_mirror_corridor() {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        let mirror = Material::new(Color::new(255, 255, 255), 10.0, [0.6, 0.3, 1.0, 0.0], 0.0, Color::new(120, 120, 120), true);
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(-3.0, 0.0, 0.0), 2.0, mirror), Visibility::default()));
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(3.0, 0.0, 0.0), 2.0, mirror), Visibility::default()));
+        scene.lights.push(Light::white(Vec3::new(0.0, 3.0, 0.0)));
+        let shoot = |settings: &RenderSettings| {
+            let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+            cast_ray(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(-1.0, 0.0, 0.02).normalize(), &scene, 0, 1.0, settings, &samples, 0.0)
+        };
+
+        let unclamped = shoot(&RenderSettings::default());
+        let settings = RenderSettings { reflection_clamp: Some(0.5), radiance_clamp: Some(1.0), ..RenderSettings::default() };
+        let clamped = shoot(&settings);
+        assert!(unclamped.color.luminance() > 255.0, "the corridor should blow past white, got {}", unclamped.color.luminance());
+        assert!(clamped.color.luminance() <= 255.0 + 1e-3);
+        assert!(clamped.passes.reflection.luminance() <= 0.5 * 255.0 + 1e-3);
+    }
+
+    #[test]
+    fn lights_without_shadows_never_darken_behind_an_occluder() {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(0.0, 2.0, 0.0), 1.0, Material::sand()), Visibility::default()));
+        let receiver = Intersect::new(Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand());
+        let samples = sphere_samples(16);
+        let light = Light::white(Vec3::new(0.0, 10.0, 0.0));
+
+        assert!(cast_shadow(&receiver, &light, 1.0, 0.0, &scene, &samples, None) > 0.5);
+        assert_eq!(cast_shadow(&receiver, &light.without_shadows(), 1.0, 0.0, &scene, &samples, None), 0.0);
+
+        let edge = Intersect::new(Vec3::new(0.7, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, Material::sand());
+        let hard = cast_shadow(&edge, &light.with_shadow_softness(0.0), 2.0, 0.0, &scene, &samples, None);
+        let soft = cast_shadow(&edge, &light.with_shadow_softness(1.0), 2.0, 0.0, &scene, &samples, None);
+        assert_eq!(hard, cast_shadow_sample(&edge, &light.position, &scene, None));
+        assert_ne!(hard, soft, "softness should widen this light's penumbra");
+    }
+
+    #[test]
+    fn mask_channel_is_white_only_on_masked_objects() {
+        let mut scene = mirror_scene(0.0);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::new(-1.5, 0.0, 0.0), 1.0, Material::sand()), Visibility { masked: true, ..Visibility::default() });
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(1.5, 0.0, 0.0), 1.0, Material::sand()), Visibility::default()));
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 6.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let (width, height) = (60, 30);
+        let mut framebuffer = Framebuffer::new(width, height);
+        let settings = RenderSettings { channel: RenderChannel::Mask, ..RenderSettings::default() };
+        configure_display(&mut framebuffer, &settings);
+        render(&mut framebuffer, &scene, &camera, &settings, None);
+
+        let (masked_x, _) = camera.project(&Vec3::new(-1.5, 0.0, 0.0), width as f32, height as f32).unwrap();
+        let (other_x, _) = camera.project(&Vec3::new(1.5, 0.0, 0.0), width as f32, height as f32).unwrap();
+        assert_eq!(framebuffer.get_pixel(masked_x as usize, height / 2), Some(0xFFFFFF));
+        assert_eq!(framebuffer.get_pixel(other_x as usize, height / 2), Some(0x000000));
+        assert_eq!(framebuffer.get_pixel(width / 2, height / 2), Some(0x000000));
+        assert_eq!(framebuffer.get_pixel(0, 0), Some(0x000000));
+    }
+
+    #[test]
+    fn unlit_surfaces_show_their_diffuse_under_any_lighting() {
+        let diffuse = Color::new(30, 140, 210);
+        let mut scene = mirror_scene(0.0);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, Material::matte(diffuse).unlit()), Visibility::default());
+        for lights in [vec![], vec![Light::white(Vec3::new(0.0, 5.0, 5.0))], vec![Light::white(Vec3::new(-4.0, 1.0, 2.0)), Light::white(Vec3::new(0.0, -6.0, 0.0))]] {
+            scene.lights = lights;
+            assert_eq!(trace(&scene, 1.0, &RenderSettings::default()).color, diffuse);
+        }
+    }
+
+    #[test]
+    fn object_ids_are_distinct_per_object_and_stable_across_pixels() {
+        let mut scene = mirror_scene(0.0);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::new(-1.5, 0.0, 0.0), 1.5, Material::sand()), Visibility::default());
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(1.5, 0.0, 0.0), 1.5, Material::water()), Visibility::default()));
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 6.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let (width, height) = (60, 30);
+        let mut framebuffer = Framebuffer::new(width, height);
+        let settings = RenderSettings { channel: RenderChannel::ObjectId, ..RenderSettings::default() };
+        configure_display(&mut framebuffer, &settings);
+        render(&mut framebuffer, &scene, &camera, &settings, None);
+
+        let pixel_at = |point: Vec3, dy: f32| {
+            let (x, y) = camera.project(&point, width as f32, height as f32).unwrap();
+            framebuffer.get_pixel(x as usize, (y + dy) as usize).unwrap()
+        };
+        let left = pixel_at(Vec3::new(-1.5, 0.0, 0.0), 0.0);
+        let right = pixel_at(Vec3::new(1.5, 0.0, 0.0), 0.0);
+        assert_eq!(left, id_color(0).to_hex());
+        assert_eq!(right, id_color(1).to_hex());
+        assert_ne!(left, right);
+        assert_eq!(pixel_at(Vec3::new(-1.5, 0.0, 0.0), 3.0), left);
+        assert_eq!(framebuffer.get_pixel(0, 0), Some(0x000000));
+        assert!((0..64).all(|index| id_color(index).to_hex() != 0));
+    }
+
+    #[test]
+    fn negative_lights_subtract_diffuse_without_going_below_zero() {
+        let mut scene = mirror_scene(0.0);
+        scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, Material::matte(Color::new(200, 200, 200))), Visibility::default());
+        let key = Light::white(Vec3::new(1.0, 4.0, 5.0));
+        scene.lights = vec![key];
+        let lit = trace(&scene, 1.0, &RenderSettings::default()).passes.diffuse;
+
+        scene.lights.push(Light::new(Vec3::new(0.0, 3.0, 5.0), Color::new(100, 100, 100)).negative());
+        let dimmed = trace(&scene, 1.0, &RenderSettings::default()).passes.diffuse;
+        assert!(dimmed.luminance() < lit.luminance(), "{:?} vs {:?}", dimmed, lit);
+        assert!(dimmed.luminance() > 0.0);
+
+        scene.lights.push(Light::new(Vec3::new(0.0, 3.0, 5.0), Color::new(255, 255, 255) * 10.0).negative());
+        let sample = trace(&scene, 1.0, &RenderSettings::default());
+        assert_eq!(sample.passes.diffuse, Color::black());
+        assert!(sample.color.is_finite() && sample.color.to_unit().iter().all(|&channel| channel >= 0.0));
+    }
+
+    #[test]
+    fn the_topmost_of_two_coplanar_water_cubes_wins_every_frame() {
+        let (low, high) = (Color::new(255, 0, 0), Color::new(0, 0, 255));
+        let cube = |height: f32, color: Color| Object::Cube(Cube::new(Vec3::new(0.0, height, 0.0), 2.0, Material::matte(color).unlit()), Visibility::default());
+        let settings = RenderSettings { depth_tie_break: true, ..RenderSettings::default() };
+        let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+        for order in [[0.0, 1e-5], [1e-5, 0.0]] {
+            let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+            for height in order {
+                scene.objects.push(cube(height, if height > 0.0 { high } else { low }));
+            }
+            for frame in 0..8 {
+                let origin = Vec3::new(0.1 * frame as f32 - 0.4, 4.0, 0.3);
+                let direction = Vec3::new(0.05 * frame as f32 - 0.2, -1.0, -0.1).normalize();
+                let hit = cast_ray(&origin, &direction, &scene, 0, 1.0, &settings, &samples, 0.0);
+                assert_eq!((hit.color.red(), hit.color.blue()), (high.red(), high.blue()), "frame {} with heights {:?}", frame, order);
+
+                let conflict_settings = RenderSettings { channel: RenderChannel::DepthConflict, ..settings };
+                let conflict = cast_ray(&origin, &direction, &scene, 0, 1.0, &conflict_settings, &samples, 0.0);
+                assert_eq!(conflict.color, DEPTH_CONFLICT_COLOR);
+            }
+        }
+
+        let mut apart = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        apart.objects.push(cube(0.0, low));
+        apart.objects.push(cube(0.5, high));
+        let conflict_settings = RenderSettings { channel: RenderChannel::DepthConflict, ..settings };
+        let clear = cast_ray(&Vec3::new(0.0, 4.0, 0.0), &Vec3::new(0.0, -1.0, 0.0), &apart, 0, 1.0, &conflict_settings, &samples, 0.0);
+        assert_ne!(clear.color, DEPTH_CONFLICT_COLOR);
+    }
+
+    #[test]
+    fn clear_coat_adds_a_grazing_highlight_to_a_diffuse_surface() {
+        let settings = RenderSettings::default();
+        let samples = Samples::new(settings.shadow_samples, settings.glossy_samples, settings.skylight_samples);
+        let shade = |material: Material, eye: Vec3| {
+            let mut scene = mirror_scene(0.0);
+            scene.objects[0] = Object::Cube(Cube::new(Vec3::zeros(), 2.0, material), Visibility::default());
+            scene.lights = vec![Light::white(Vec3::new(-eye.x, eye.y, eye.z))];
+            let direction = (Vec3::new(0.0, 1.0, 0.0) - eye).normalize();
+            cast_ray(&eye, &direction, &scene, 0, 1.0, &settings, &samples, 0.0)
+        };
+        let plain = Material::matte(Color::new(168, 136, 104));
+        let coated = plain.with_clear_coat(0.6, 0.1);
+        let grazing = Vec3::new(-4.0, 1.4, 0.0);
+
+        let coat_highlight = |eye: Vec3| shade(coated, eye).passes.specular.luminance() - shade(plain, eye).passes.specular.luminance();
+        let highlight = coat_highlight(grazing);
+        assert!(highlight > 0.5 * shade(plain, grazing).passes.specular.luminance(), "coat adds only {}", highlight);
+        let overhead = coat_highlight(Vec3::new(-0.5, 5.0, 0.0));
+        assert!(overhead < highlight, "overhead {} vs grazing {}", overhead, highlight);
+
+        let uncoated = Material { clear_coat: 0.0, ..coated };
+        let (reference, unchanged) = (shade(plain, grazing), shade(uncoated, grazing));
+        assert_eq!(unchanged.color, reference.color);
+    }
+
+
+
+    #[test]
+    fn russian_roulette_matches_full_recursion_on_average() {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        let mirror = Material::new(Color::new(200, 200, 200), 10.0, [0.5, 0.0, 0.5, 0.0], 0.0, Color::black(), false);
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(-3.0, 0.0, 0.0), 2.0, mirror), Visibility::default()));
+        scene.objects.push(Object::Cube(Cube::new(Vec3::new(3.0, 0.0, 0.0), 2.0, mirror), Visibility::default()));
+        scene.lights.push(Light::white(Vec3::new(0.0, 3.0, 0.0)));
+        let reference = RenderSettings::default();
+        let roulette = RenderSettings { russian_roulette: true, ..reference };
+        let samples = Samples::new(reference.shadow_samples, reference.glossy_samples, reference.skylight_samples);
+
+        let count = 2000;
+        let (mut full, mut terminated, mut differing) = (0.0, 0.0, 0);
+        for i in 0..count {
+            let (u, v) = (hash_index(1, i), hash_index(2, i));
+            let direction = Vec3::new(-1.0, 0.3 * (u - 0.5), 0.3 * (v - 0.5)).normalize();
+            let expected = cast_ray(&Vec3::zeros(), &direction, &scene, 0, 1.0, &reference, &samples, 0.0).color.luminance();
+            let estimate = cast_ray(&Vec3::zeros(), &direction, &scene, 0, 1.0, &roulette, &samples, 0.0).color.luminance();
+            full += expected;
+            terminated += estimate;
+            if (estimate - expected).abs() > 1e-3 {
+                differing += 1;
+            }
+        }
+        assert!(differing > count / 10, "roulette changed only {} of {} paths", differing, count);
+        let error = (terminated - full).abs() / full;
+        assert!(error < 0.03, "roulette mean is off by {:.1}%", error * 100.0);
+    }
+}
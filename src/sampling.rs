@@ -0,0 +1,365 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+pub struct Samples {
+    pub shadow: Vec<Vec3>,
+    pub glossy: Vec<(f32, f32)>,
+    pub skylight: Vec<(f32, f32)>,
+}
+
+impl Samples {
+    pub fn new(shadow_count: usize, glossy_count: usize, skylight_count: usize) -> Self {
+        Samples {
+            shadow: sphere_samples(shadow_count),
+            glossy: hammersley(glossy_count.max(1)),
+            skylight: hammersley(skylight_count),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplePattern {
+    /// Cell centers of a regular `n x n` grid.
+    Grid,
+    /// The same `n x n` grid rotated by `atan(1/n)` and wrapped back into the pixel, so no two samples share a row
+    /// or column.
+    RotatedGrid,
+    /// Base-2/base-3 Halton points.
+    Halton,
+}
+
+impl SamplePattern {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Grid" => Some(SamplePattern::Grid),
+            "RotatedGrid" => Some(SamplePattern::RotatedGrid),
+            "Halton" => Some(SamplePattern::Halton),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SamplePattern::Grid => SamplePattern::RotatedGrid,
+            SamplePattern::RotatedGrid => SamplePattern::Halton,
+            SamplePattern::Halton => SamplePattern::Grid,
+        }
+    }
+
+    /// Sub-pixel offsets in [0, 1)^2 for `count` samples. The grid patterns round `count` up to a square; a count
+    /// of one or less is the single offset (0, 0).
+    pub fn offsets(self, count: usize) -> Vec<(f32, f32)> {
+        if count <= 1 {
+            return vec![(0.0, 0.0)];
+        }
+
+        let side = (count as f32).sqrt().ceil() as usize;
+        let grid = (0..side * side).map(move |i| (((i % side) as f32 + 0.5) / side as f32, ((i / side) as f32 + 0.5) / side as f32));
+        match self {
+            SamplePattern::Grid => grid.collect(),
+            SamplePattern::RotatedGrid => {
+                let (sin, cos) = (1.0 / side as f32).atan().sin_cos();
+                grid.map(|(x, y)| {
+                    let (dx, dy) = (x - 0.5, y - 0.5);
+                    ((0.5 + dx * cos - dy * sin).rem_euclid(1.0), (0.5 + dx * sin + dy * cos).rem_euclid(1.0))
+                })
+                .collect()
+            }
+            SamplePattern::Halton => (1..=count as u32).map(|i| (radical_inverse_base(i, 2), radical_inverse_base(i, 3))).collect(),
+        }
+    }
+}
+
+/// Filter used to resolve sub-pixel samples into one pixel. The pixel footprint is mapped onto the filter's
+/// `[-2, 2]` support, so a sample weight depends on its offset from the pixel center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconstructionFilter {
+    Box,
+    Gaussian,
+    /// Mitchell-Netravali with `B = C = 1/3`; its negative outer lobes sharpen edges.
+    Mitchell,
+}
+
+impl ReconstructionFilter {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Box" => Some(ReconstructionFilter::Box),
+            "Gaussian" => Some(ReconstructionFilter::Gaussian),
+            "Mitchell" => Some(ReconstructionFilter::Mitchell),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ReconstructionFilter::Box => ReconstructionFilter::Gaussian,
+            ReconstructionFilter::Gaussian => ReconstructionFilter::Mitchell,
+            ReconstructionFilter::Mitchell => ReconstructionFilter::Box,
+        }
+    }
+
+    /// Weight of each offset in [0, 1)^2, normalized to sum to one. Falls back to equal weights if the filter
+    /// cancels out over the given offsets.
+    pub fn weights(self, offsets: &[(f32, f32)]) -> Vec<f32> {
+        let uniform = vec![1.0 / offsets.len() as f32; offsets.len()];
+        if self == ReconstructionFilter::Box {
+            return uniform;
+        }
+        let raw: Vec<f32> = offsets
+            .iter()
+            .map(|&(x, y)| self.evaluate(FILTER_SUPPORT * (x - 0.5)) * self.evaluate(FILTER_SUPPORT * (y - 0.5)))
+            .collect();
+        let sum: f32 = raw.iter().sum();
+        if sum.abs() < 1e-6 {
+            return uniform;
+        }
+        raw.into_iter().map(|weight| weight / sum).collect()
+    }
+
+    /// One-dimensional filter value at distance `x` from the center.
+    fn evaluate(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ReconstructionFilter::Box => 1.0,
+            ReconstructionFilter::Gaussian => (-2.0 * x * x).exp(),
+            ReconstructionFilter::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Scale from a sub-pixel offset (at most 0.5 from the center) to the filter's support radius of 2.
+const FILTER_SUPPORT: f32 = 4.0;
+
+fn radical_inverse_base(mut index: u32, base: u32) -> f32 {
+    let mut inverse = 0.0;
+    let mut scale = 1.0 / base as f32;
+    while index > 0 {
+        inverse += (index % base) as f32 * scale;
+        index /= base;
+        scale /= base as f32;
+    }
+    inverse
+}
+
+pub fn sphere_samples(count: usize) -> Vec<Vec3> {
+    if count <= 1 {
+        return vec![Vec3::zeros()];
+    }
+
+    let golden_angle = PI * (3.0 - 5.0_f32.sqrt());
+    (0..count)
+        .map(|i| {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+            let radius = (1.0 - y * y).sqrt();
+            let phi = golden_angle * i as f32;
+            Vec3::new(radius * phi.cos(), y, radius * phi.sin())
+        })
+        .collect()
+}
+
+pub fn hammersley(count: usize) -> Vec<(f32, f32)> {
+    (0..count)
+        .map(|i| (i as f32 / count as f32, radical_inverse(i as u32)))
+        .collect()
+}
+
+fn radical_inverse(mut bits: u32) -> f32 {
+    bits = bits.rotate_right(16);
+    bits = ((bits & 0x55555555) << 1) | ((bits & 0xAAAAAAAA) >> 1);
+    bits = ((bits & 0x33333333) << 2) | ((bits & 0xCCCCCCCC) >> 2);
+    bits = ((bits & 0x0F0F0F0F) << 4) | ((bits & 0xF0F0F0F0) >> 4);
+    bits = ((bits & 0x00FF00FF) << 8) | ((bits & 0xFF00FF00) >> 8);
+    bits as f32 * 2.328_306_4e-10
+}
+
+pub fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let reference = if normal.x.abs() < 0.9 {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    let tangent = normal.cross(&reference).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+pub fn ggx_half_vector(normal: &Vec3, roughness: f32, u1: f32, u2: f32) -> Vec3 {
+    let alpha = roughness * roughness;
+    let cos_theta = ((1.0 - u1) / (1.0 + (alpha * alpha - 1.0) * u1)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + normal * cos_theta).normalize()
+}
+
+/// Direction on the hemisphere around `normal` with density proportional to the cosine of its angle to it.
+pub fn cosine_hemisphere(normal: &Vec3, u1: f32, u2: f32) -> Vec3 {
+    let radius = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * (radius * phi.cos()) + bitangent * (radius * phi.sin()) + normal * (1.0 - u1).max(0.0).sqrt()).normalize()
+}
+
+pub fn hash_point(point: &Vec3, index: u32) -> f32 {
+    let bits = point.x.to_bits() ^ point.y.to_bits().rotate_left(11) ^ point.z.to_bits().rotate_left(22) ^ index.wrapping_mul(0x9E37_79B9);
+    finalize_hash(bits)
+}
+
+/// Deterministic value in [0, 1) for the pair (`seed`, `index`).
+pub fn hash_index(seed: u32, index: u32) -> f32 {
+    finalize_hash(seed.wrapping_mul(0x85EB_CA6B) ^ index.wrapping_mul(0x9E37_79B9))
+}
+
+fn finalize_hash(mut bits: u32) -> f32 {
+    bits ^= bits >> 16;
+    bits = bits.wrapping_mul(0x7FEB_352D);
+    bits ^= bits >> 15;
+    bits = bits.wrapping_mul(0x846C_A68B);
+    bits ^= bits >> 16;
+    (bits >> 8) as f32 / (1u32 << 24) as f32
+}
+
+/// Floor on the survival probability, so dim paths that do survive are not boosted without bound.
+const ROULETTE_MIN_SURVIVAL: f32 = 0.05;
+
+/// Russian roulette on a path with the given accumulated throughput: returns the probability it survived with,
+/// which the caller divides the continued contribution by, or `None` when `u` kills it.
+pub fn russian_roulette(throughput: f32, u: f32) -> Option<f32> {
+    let survival = throughput.clamp(ROULETTE_MIN_SURVIVAL, 1.0);
+    if u < survival { Some(survival) } else { None }
+}
+
+pub fn pick_weighted(weights: &[f32], total: f32, u: f32) -> usize {
+    let mut target = u * total;
+    for (index, &weight) in weights.iter().enumerate() {
+        if target < weight {
+            return index;
+        }
+        target -= weight;
+    }
+    weights.iter().rposition(|&weight| weight > 0.0).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn russian_roulette_is_unbiased() {
+        let count = 10_000;
+        for throughput in [0.01, 0.3, 0.9, 2.0] {
+            let expected: f32 = (0..count)
+                .filter_map(|i| russian_roulette(throughput, (i as f32 + 0.5) / count as f32))
+                .map(|survival| 1.0 / survival)
+                .sum::<f32>()
+                / count as f32;
+            assert!((expected - 1.0).abs() < 1e-2, "throughput {}: {}", throughput, expected);
+        }
+    }
+
+    fn min_distance(points: &[(f32, f32)]) -> f32 {
+        let mut closest = f32::INFINITY;
+        for (i, a) in points.iter().enumerate() {
+            for b in &points[i + 1..] {
+                closest = closest.min(((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt());
+            }
+        }
+        closest
+    }
+
+    #[test]
+    fn halton_points_are_well_distributed() {
+        for count in [4, 8, 16, 32, 64] {
+            let points = SamplePattern::Halton.offsets(count);
+            assert_eq!(points.len(), count);
+            assert!(points.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y)));
+            let closest = min_distance(&points);
+            assert!(closest >= 0.35 / (count as f32).sqrt(), "{} Halton points as close as {}", count, closest);
+        }
+    }
+
+    #[test]
+    fn rotated_grid_samples_never_share_a_row_or_column() {
+        for count in [4, 9, 16, 64] {
+            let points = SamplePattern::RotatedGrid.offsets(count);
+            let spacing = 0.5 / count as f32;
+            for (i, a) in points.iter().enumerate() {
+                for b in &points[i + 1..] {
+                    assert!((a.0 - b.0).abs() > spacing && (a.1 - b.1).abs() > spacing, "{:?} and {:?} line up", a, b);
+                }
+            }
+        }
+        assert_eq!(SamplePattern::Grid.offsets(5).len(), 9);
+        assert_eq!(SamplePattern::Halton.offsets(1), vec![(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn filter_weights_sum_to_one_and_mitchell_has_negative_lobes() {
+        let offsets = SamplePattern::Grid.offsets(16);
+        for filter in [ReconstructionFilter::Box, ReconstructionFilter::Gaussian, ReconstructionFilter::Mitchell] {
+            let weights = filter.weights(&offsets);
+            assert_eq!(weights.len(), offsets.len());
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-5, "{:?} weights sum to {}", filter, weights.iter().sum::<f32>());
+        }
+
+        let weight_at = |filter: ReconstructionFilter, offset: (f32, f32)| {
+            let index = offsets.iter().position(|&o| o == offset).unwrap();
+            filter.weights(&offsets)[index]
+        };
+        let (center, corner) = ((0.375, 0.375), (0.125, 0.375));
+        assert!(ReconstructionFilter::Box.weights(&offsets).iter().all(|&w| (w - 1.0 / 16.0).abs() < 1e-6));
+        assert!(weight_at(ReconstructionFilter::Gaussian, center) > weight_at(ReconstructionFilter::Gaussian, corner));
+        assert!(ReconstructionFilter::Gaussian.weights(&offsets).iter().all(|&w| w > 0.0));
+        assert!(weight_at(ReconstructionFilter::Mitchell, corner) < 0.0);
+        assert!(weight_at(ReconstructionFilter::Mitchell, center) > 0.0);
+    }
+
+    #[test]
+    fn ggx_importance_sampling_has_lower_variance_than_uniform() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let alpha = 0.5_f32 * 0.5;
+        let ggx = |cos_theta: f32| {
+            let d = cos_theta * cos_theta * (alpha * alpha - 1.0) + 1.0;
+            alpha * alpha / (PI * d * d)
+        };
+        let radiance = |direction: &Vec3| 1.0 + direction.x;
+        let (batches, per_batch) = (256, 16);
+        let estimate = |importance: bool, batch: u32| {
+            (0..per_batch)
+                .map(|i| {
+                    let index = batch * per_batch + i;
+                    let (u1, u2) = (hash_index(1, index), hash_index(2, index));
+                    if importance {
+                        radiance(&ggx_half_vector(&normal, 0.5, u1, u2))
+                    } else {
+                        let sin_theta = (1.0 - u1 * u1).sqrt();
+                        let phi = 2.0 * PI * u2;
+                        let half_vector = Vec3::new(sin_theta * phi.cos(), u1, sin_theta * phi.sin());
+                        ggx(u1) * u1 * radiance(&half_vector) * 2.0 * PI
+                    }
+                })
+                .sum::<f32>()
+                / per_batch as f32
+        };
+        let statistics = |importance: bool| {
+            let estimates: Vec<f32> = (0..batches).map(|batch| estimate(importance, batch)).collect();
+            let mean = estimates.iter().sum::<f32>() / batches as f32;
+            let variance = estimates.iter().map(|e| (e - mean) * (e - mean)).sum::<f32>() / batches as f32;
+            (mean, variance)
+        };
+        let (importance_mean, importance_variance) = statistics(true);
+        let (uniform_mean, uniform_variance) = statistics(false);
+        assert!((importance_mean - 1.0).abs() < 0.05 && (uniform_mean - 1.0).abs() < 0.1, "{} {}", importance_mean, uniform_mean);
+        assert!(importance_variance < uniform_variance, "{} vs {}", importance_variance, uniform_variance);
+    }
+}
@@ -0,0 +1,49 @@
+use nalgebra_glm::Vec3;
+use crate::cube::Cube;
+use crate::object::Object;
+use crate::sampling::hash_index;
+
+/// Nudges each object by up to `position_jitter` along every axis and rescales it about its center by a factor
+/// in `[1 - scale_jitter, 1 + scale_jitter]`. The offsets depend only on `seed` and the object's index, so the same
+/// seed always gives the same arrangement. Planes only move.
+pub fn scatter(objects: &mut [Object], prototypes: &[Cube], seed: u32, position_jitter: f32, scale_jitter: f32) {
+    for (index, object) in objects.iter_mut().enumerate() {
+        let random = |channel: u32| 2.0 * hash_index(seed, index as u32 * 4 + channel) - 1.0;
+        object.translate(Vec3::new(random(0), random(1), random(2)) * position_jitter);
+        object.resize(1.0 + random(3) * scale_jitter, prototypes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::object::Visibility;
+
+    fn rocks() -> Vec<Object> {
+        (0..50).map(|i| Object::Cube(Cube::new(Vec3::new(i as f32, 0.0, 0.0), 1.0, Material::sand()), Visibility::default())).collect()
+    }
+
+    fn extents(objects: &[Object]) -> Vec<(Vec3, f32)> {
+        objects.iter().map(|object| object.cube_extent(&[]).unwrap()).collect()
+    }
+
+    #[test]
+    fn scatter_is_deterministic_and_bounded() {
+        let original = extents(&rocks());
+        let scattered = |seed: u32| {
+            let mut objects = rocks();
+            scatter(&mut objects, &[], seed, 0.2, 0.1);
+            extents(&objects)
+        };
+        assert_eq!(scattered(7), scattered(7));
+        assert_ne!(scattered(7), scattered(8));
+
+        for ((center, half_size), (original_center, original_half)) in scattered(7).into_iter().zip(original) {
+            let offset = center - original_center;
+            assert!(offset.iter().all(|c| c.abs() <= 0.2 + 1e-6), "moved by {:?}", offset);
+            let scale = half_size / original_half;
+            assert!((0.9 - 1e-6..=1.1 + 1e-6).contains(&scale), "scaled by {}", scale);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use core::fmt;
+use nalgebra_glm::Vec3;
+use crate::cube::Cube;
+use crate::environment::Environment;
+use crate::light::Light;
+use crate::material::Material;
+use crate::object::Object;
+use crate::shadow_map::ShadowMap;
+use crate::caustics::CausticsMap;
+use crate::texture::Texture;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneWarning {
+    NonFiniteCube { object: usize },
+    NonPositiveSize { object: usize, size: f32 },
+    AlbedoOutOfRange { object: usize, albedo: [f32; 4] },
+    DuplicateCube { object: usize, duplicate_of: usize },
+    MissingPrototype { object: usize, prototype: usize },
+}
+
+impl fmt::Display for SceneWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneWarning::NonFiniteCube { object } => write!(f, "object {} has a non-finite center or size", object),
+            SceneWarning::NonPositiveSize { object, size } => write!(f, "object {} has non-positive size {}", object, size),
+            SceneWarning::AlbedoOutOfRange { object, albedo } => {
+                write!(f, "object {} has albedo {:?} outside [0, 1]", object, albedo)
+            }
+            SceneWarning::DuplicateCube { object, duplicate_of } => {
+                write!(f, "object {} coincides exactly with object {}", object, duplicate_of)
+            }
+            SceneWarning::MissingPrototype { object, prototype } => {
+                write!(f, "object {} instances missing prototype {}", object, prototype)
+            }
+        }
+    }
+}
+
+pub struct Scene {
+    pub objects: Vec<Object>,
+    pub prototypes: Vec<Cube>,
+    pub lights: Vec<Light>,
+    pub environment: Environment,
+    pub monitors: Vec<Texture>,
+    pub shadow_map: Option<(usize, ShadowMap)>,
+    /// Caustics cast by the light at the given index, lighting the ground under the water.
+    pub caustics: Option<(usize, CausticsMap)>,
+}
+
+impl Scene {
+    pub fn new(environment: Environment) -> Self {
+        Scene {
+            objects: Vec::new(),
+            prototypes: Vec::new(),
+            lights: Vec::new(),
+            environment,
+            monitors: Vec::new(),
+            shadow_map: None,
+            caustics: None,
+        }
+    }
+
+    /// Min and max corners over the cubes, instances and spheres in the scene; planes are unbounded and ignored. An empty
+    /// scene yields a degenerate box at the origin.
+    pub fn bounding_box(&self) -> (Vec3, Vec3) {
+        let extents: Vec<(Vec3, f32)> = self
+            .objects
+            .iter()
+            .filter_map(|object| object.extent(&self.prototypes))
+            .filter(|(center, half_size)| center.iter().all(|c| c.is_finite()) && half_size.is_finite())
+            .collect();
+        if extents.is_empty() {
+            return (Vec3::zeros(), Vec3::zeros());
+        }
+
+        extents.iter().fold(
+            (Vec3::repeat(f32::INFINITY), Vec3::repeat(f32::NEG_INFINITY)),
+            |(min, max), (center, half_size)| {
+                let half_extent = Vec3::repeat(half_size.abs());
+                (min.inf(&(center - half_extent)), max.sup(&(center + half_extent)))
+            },
+        )
+    }
+
+    pub fn validate(&self) -> Vec<SceneWarning> {
+        let mut warnings = Vec::new();
+        let mut cubes: Vec<(usize, Vec3, f32)> = Vec::new();
+
+        for (index, object) in self.objects.iter().enumerate() {
+            let material = match object {
+                Object::Cube(cube, _) => &cube.material,
+                Object::Plane(plane, _) => &plane.material,
+                Object::Sphere(sphere, _) => &sphere.material,
+                Object::Instance(instance, _) => match self.prototypes.get(instance.prototype) {
+                    Some(prototype) => &prototype.material,
+                    None => {
+                        warnings.push(SceneWarning::MissingPrototype { object: index, prototype: instance.prototype });
+                        continue;
+                    }
+                },
+            };
+            if !albedo_in_range(material) {
+                warnings.push(SceneWarning::AlbedoOutOfRange { object: index, albedo: material.albedo });
+            }
+
+            let (center, half_size) = match object.cube_extent(&self.prototypes) {
+                Some(extent) => extent,
+                None => continue,
+            };
+            if !center.iter().all(|c| c.is_finite()) || !half_size.is_finite() {
+                warnings.push(SceneWarning::NonFiniteCube { object: index });
+                continue;
+            }
+            if half_size <= 0.0 {
+                warnings.push(SceneWarning::NonPositiveSize { object: index, size: half_size * 2.0 });
+            }
+            if let Some(&(duplicate_of, _, _)) = cubes.iter().find(|(_, other_center, other_half)| *other_center == center && *other_half == half_size) {
+                warnings.push(SceneWarning::DuplicateCube { object: index, duplicate_of });
+            }
+            cubes.push((index, center, half_size));
+        }
+
+        warnings
+    }
+}
+
+fn albedo_in_range(material: &Material) -> bool {
+    material.albedo.iter().all(|albedo| (0.0..=1.0).contains(albedo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::environment::{EnvMap, SkyGradient};
+    use crate::instance::Instance;
+    use crate::object::Visibility;
+    use crate::transform::Transform;
+
+    fn cube(center: Vec3, size: f32, material: Material) -> Object {
+        Object::Cube(Cube::new(center, size, material), Visibility::default())
+    }
+
+    #[test]
+    fn validate_reports_each_warning_category() {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        let overbright = Material::new(Color::new(200, 200, 200), 1.0, [1.2, 0.0, 0.0, -0.1], 0.0, Color::black(), false);
+        scene.objects = vec![
+            cube(Vec3::zeros(), 1.0, Material::sand()),
+            cube(Vec3::new(f32::NAN, 0.0, 0.0), 1.0, Material::sand()),
+            cube(Vec3::new(2.0, 0.0, 0.0), 0.0, Material::sand()),
+            cube(Vec3::new(4.0, 0.0, 0.0), 1.0, overbright),
+            cube(Vec3::zeros(), 1.0, Material::sand()),
+            Object::Instance(Instance::new(3, Transform::translation(Vec3::zeros())), Visibility::default()),
+        ];
+
+        assert_eq!(scene.validate(), vec![
+            SceneWarning::NonFiniteCube { object: 1 },
+            SceneWarning::NonPositiveSize { object: 2, size: 0.0 },
+            SceneWarning::AlbedoOutOfRange { object: 3, albedo: [1.2, 0.0, 0.0, -0.1] },
+            SceneWarning::DuplicateCube { object: 4, duplicate_of: 0 },
+            SceneWarning::MissingPrototype { object: 5, prototype: 3 },
+        ]);
+    }
+
+    #[test]
+    fn a_clean_scene_has_no_warnings() {
+        let mut scene = Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default())));
+        scene.objects = vec![cube(Vec3::zeros(), 1.0, Material::sand()), cube(Vec3::new(1.0, 0.0, 0.0), 1.0, Material::water())];
+        assert!(scene.validate().is_empty());
+    }
+}
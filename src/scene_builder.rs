@@ -0,0 +1,124 @@
+use nalgebra_glm::Vec3;
+use crate::cube::Cube;
+use crate::material::Material;
+use crate::object::{Object, Visibility};
+use crate::sphere::Sphere;
+use crate::transform::Transform;
+
+pub struct SceneBuilder {
+    objects: Vec<Object>,
+    transforms: Vec<Transform>,
+}
+
+impl SceneBuilder {
+    pub fn with_capacity(capacity: usize) -> Self {
+        SceneBuilder {
+            objects: Vec::with_capacity(capacity),
+            transforms: Vec::new(),
+        }
+    }
+
+    pub fn push_transform(mut self, transform: Transform) -> Self {
+        let combined = self.current_transform().then(&transform);
+        self.transforms.push(combined);
+        self
+    }
+
+    pub fn pop_transform(mut self) -> Self {
+        self.transforms.pop();
+        self
+    }
+
+    fn current_transform(&self) -> Transform {
+        self.transforms.last().copied().unwrap_or_default()
+    }
+
+    fn place(&self, mut object: Object) -> Object {
+        if let Some(transform) = self.transforms.last() {
+            object.transform(transform);
+        }
+        object
+    }
+
+    pub fn add_object(mut self, object: Object) -> Self {
+        let object = self.place(object);
+        self.objects.push(object);
+        self
+    }
+
+    pub fn add_cube(self, center: Vec3, size: f32, material: Material) -> Self {
+        self.add_object(Object::Cube(Cube::new(center, size, material), Visibility::default()))
+    }
+
+    pub fn add_sphere(self, center: Vec3, radius: f32, material: Material) -> Self {
+        self.add_object(Object::Sphere(Sphere::new(center, radius, material), Visibility::default()))
+    }
+
+    pub fn add_grid(mut self, width: usize, depth: usize, mut cell: impl FnMut(usize, usize) -> Option<Object>) -> Self {
+        self.objects.reserve(width * depth);
+        for x in 0..width {
+            for z in 0..depth {
+                if let Some(object) = cell(x, z) {
+                    let object = self.place(object);
+                    self.objects.push(object);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn extend(mut self, objects: impl IntoIterator<Item = Object>) -> Self {
+        for object in objects {
+            let object = self.place(object);
+            self.objects.push(object);
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn build(self) -> Vec<Object> {
+        self.objects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extents(objects: &[Object]) -> Vec<Option<(Vec3, f32)>> {
+        objects.iter().map(|object| object.extent(&[])).collect()
+    }
+
+    #[test]
+    fn builder_matches_manual_assembly() {
+        let water = Material::water();
+        let cell = |x: usize, z: usize| Object::Cube(Cube::new(Vec3::new(x as f32, 0.0, z as f32), 1.0, water), Visibility::default());
+        let trunk: Vec<Object> = (0..4)
+            .map(|y| Object::Cube(Cube::new(Vec3::new(5.0, y as f32, 5.0), 0.5, Material::sand()), Visibility::default()))
+            .collect();
+
+        let mut manual = Vec::new();
+        for x in 0..3 {
+            for z in 0..2 {
+                manual.push(cell(x, z));
+            }
+        }
+        manual.extend(trunk.iter().cloned());
+        manual.push(Object::Sphere(Sphere::new(Vec3::new(5.0, 4.0, 5.0), 0.2, Material::sand()), Visibility::default()));
+
+        let built = SceneBuilder::with_capacity(manual.len())
+            .add_grid(3, 2, |x, z| Some(cell(x, z)))
+            .extend(trunk)
+            .add_sphere(Vec3::new(5.0, 4.0, 5.0), 0.2, Material::sand())
+            .build();
+        assert_eq!(built.capacity(), manual.len());
+        assert_eq!(extents(&built), extents(&manual));
+    }
+}
@@ -0,0 +1,112 @@
+use nalgebra_glm::Vec3;
+use crate::object::Object;
+use crate::cube::Cube;
+use crate::sampling::orthonormal_basis;
+
+const DEPTH_BIAS_TEXELS: f32 = 1.5;
+const PCF_RADIUS: i32 = 1;
+
+pub struct ShadowMap {
+    origin: Vec3,
+    right: Vec3,
+    up: Vec3,
+    forward: Vec3,
+    half_extent: f32,
+    resolution: usize,
+    depths: Vec<f32>,
+}
+
+impl ShadowMap {
+    /// Renders an orthographic depth map looking from `light_position` towards `target`, covering a square of
+    /// `2 * half_extent` world units on a side.
+    pub fn build(objects: &[Object], prototypes: &[Cube], light_position: Vec3, target: Vec3, half_extent: f32, resolution: usize) -> Self {
+        let forward = (target - light_position).normalize();
+        let (right, up) = orthonormal_basis(&forward);
+        let mut map = ShadowMap {
+            origin: light_position,
+            right,
+            up,
+            forward,
+            half_extent,
+            resolution,
+            depths: vec![f32::INFINITY; resolution * resolution],
+        };
+
+        for y in 0..resolution {
+            for x in 0..resolution {
+                let ray_origin = map.texel_origin(x, y);
+                map.depths[y * resolution + x] = objects
+                    .iter()
+                    .filter(|object| object.visibility().casts_shadow)
+                    .map(|object| object.ray_intersect(&ray_origin, &forward, prototypes))
+                    .filter(|intersect| intersect.is_intersecting)
+                    .map(|intersect| intersect.distance)
+                    .fold(f32::INFINITY, f32::min);
+            }
+        }
+
+        map
+    }
+
+    fn texel_size(&self) -> f32 {
+        2.0 * self.half_extent / self.resolution as f32
+    }
+
+    fn texel_origin(&self, x: usize, y: usize) -> Vec3 {
+        let texel = self.texel_size();
+        let u = (x as f32 + 0.5) * texel - self.half_extent;
+        let v = (y as f32 + 0.5) * texel - self.half_extent;
+        self.origin + self.right * u + self.up * v
+    }
+
+    /// Fraction of PCF taps around `point` that lie behind the stored depth, in [0, 1]. Points outside the map
+    /// are treated as lit.
+    pub fn shadow(&self, point: &Vec3) -> f32 {
+        let offset = point - self.origin;
+        let texel = self.texel_size();
+        let x = ((offset.dot(&self.right) + self.half_extent) / texel).floor() as i32;
+        let y = ((offset.dot(&self.up) + self.half_extent) / texel).floor() as i32;
+        let depth = offset.dot(&self.forward) - DEPTH_BIAS_TEXELS * texel;
+
+        let mut occluded = 0;
+        let mut taps = 0;
+        for dy in -PCF_RADIUS..=PCF_RADIUS {
+            for dx in -PCF_RADIUS..=PCF_RADIUS {
+                let (tx, ty) = (x + dx, y + dy);
+                if tx < 0 || ty < 0 || tx >= self.resolution as i32 || ty >= self.resolution as i32 {
+                    continue;
+                }
+                taps += 1;
+                if self.depths[ty as usize * self.resolution + tx as usize] < depth {
+                    occluded += 1;
+                }
+            }
+        }
+
+        if taps == 0 {
+            0.0
+        } else {
+            occluded as f32 / taps as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::object::Visibility;
+
+    #[test]
+    fn points_behind_an_occluder_are_shadowed() {
+        let occluder = Object::Cube(Cube::new(Vec3::new(0.0, 2.0, 0.0), 1.0, Material::sand()), Visibility::default());
+        let map = ShadowMap::build(&[occluder], &[], Vec3::new(0.0, 10.0, 0.0), Vec3::zeros(), 4.0, 64);
+
+        assert_eq!(map.shadow(&Vec3::new(0.0, 0.0, 0.0)), 1.0);
+        assert_eq!(map.shadow(&Vec3::new(3.0, 0.0, 0.0)), 0.0);
+        assert_eq!(map.shadow(&Vec3::new(0.0, 2.5, 0.0)), 0.0, "the lit top face must not shadow itself");
+        assert_eq!(map.shadow(&Vec3::new(20.0, 0.0, 0.0)), 0.0);
+        let edge = map.shadow(&Vec3::new(0.5, 0.0, 0.0));
+        assert!(edge > 0.0 && edge < 1.0, "PCF should soften the edge, got {}", edge);
+    }
+}
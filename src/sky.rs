@@ -0,0 +1,115 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::math::saturate;
+
+const SKY_LUMINANCE_SCALE: f32 = 0.06;
+const MIN_COS_THETA: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy)]
+struct Perez {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+}
+
+impl Perez {
+    fn eval(&self, cos_theta: f32, gamma: f32) -> f32 {
+        let cos_gamma = gamma.cos();
+        (1.0 + self.a * (self.b / cos_theta.max(MIN_COS_THETA)).exp())
+            * (1.0 + self.c * (self.d * gamma).exp() + self.e * cos_gamma * cos_gamma)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnalyticSky {
+    sun_direction: Vec3,
+    luminance: Perez,
+    chroma_x: Perez,
+    chroma_y: Perez,
+    zenith: (f32, f32, f32),
+    normalization: (f32, f32, f32),
+}
+
+impl AnalyticSky {
+    pub fn new(sun_direction: Vec3, turbidity: f32) -> Self {
+        let t = turbidity;
+        let sun_direction = sun_direction.normalize();
+        let theta_sun = saturate(sun_direction.y).acos();
+
+        let luminance = Perez {
+            a: 0.1787 * t - 1.4630,
+            b: -0.3554 * t + 0.4275,
+            c: -0.0227 * t + 5.3251,
+            d: 0.1206 * t - 2.5771,
+            e: -0.0670 * t + 0.3703,
+        };
+        let chroma_x = Perez {
+            a: -0.0193 * t - 0.2592,
+            b: -0.0665 * t + 0.0008,
+            c: -0.0004 * t + 0.2125,
+            d: -0.0641 * t - 0.8989,
+            e: -0.0033 * t + 0.0452,
+        };
+        let chroma_y = Perez {
+            a: -0.0167 * t - 0.2608,
+            b: -0.0950 * t + 0.0092,
+            c: -0.0079 * t + 0.2102,
+            d: -0.0441 * t - 1.6537,
+            e: -0.0109 * t + 0.0529,
+        };
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+
+        let theta2 = theta_sun * theta_sun;
+        let theta3 = theta2 * theta_sun;
+        let zenith_x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta_sun)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta_sun + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta_sun + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta_sun)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta_sun + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta_sun + 0.26688);
+
+        AnalyticSky {
+            sun_direction,
+            luminance,
+            chroma_x,
+            chroma_y,
+            zenith: (zenith_luminance, zenith_x, zenith_y),
+            normalization: (
+                luminance.eval(1.0, theta_sun),
+                chroma_x.eval(1.0, theta_sun),
+                chroma_y.eval(1.0, theta_sun),
+            ),
+        }
+    }
+
+    pub fn color(&self, direction: &Vec3) -> Color {
+        let direction = direction.normalize();
+        let cos_theta = direction.y.max(MIN_COS_THETA);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let luminance = self.zenith.0 * self.luminance.eval(cos_theta, gamma) / self.normalization.0;
+        let x = self.zenith.1 * self.chroma_x.eval(cos_theta, gamma) / self.normalization.1;
+        let y = self.zenith.2 * self.chroma_y.eval(cos_theta, gamma) / self.normalization.2;
+
+        let (r, g, b) = yxy_to_linear_srgb(luminance * SKY_LUMINANCE_SCALE, x, y);
+        Color::from_unit(r, g, b)
+    }
+}
+
+fn yxy_to_linear_srgb(luminance: f32, x: f32, y: f32) -> (f32, f32, f32) {
+    if y <= 0.0 {
+        return (0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    (
+        3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+    )
+}
@@ -0,0 +1,98 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::ray_intersect::{RayIntersect, Intersect, INTERSECTION_EPSILON};
+use crate::material::Material;
+
+#[derive(Debug, Clone)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+    pub material: Material,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32, material: Material) -> Self {
+        Sphere { center, radius, material }
+    }
+}
+
+impl RayIntersect for Sphere {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        if !(self.radius > 0.0 && self.radius.is_finite()) {
+            return Intersect::empty();
+        }
+
+        let to_origin = ray_origin - self.center;
+        let a = ray_direction.dot(ray_direction);
+        let half_b = to_origin.dot(ray_direction);
+        let c = to_origin.dot(&to_origin) - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 || a <= 0.0 {
+            return Intersect::empty();
+        }
+
+        // Like cubes, a ray starting inside leaves through the far side.
+        let epsilon = INTERSECTION_EPSILON * self.radius;
+        let root = discriminant.sqrt();
+        let near = (-half_b - root) / a;
+        let distance = if near >= epsilon { near } else { (-half_b + root) / a };
+        if distance < epsilon || !distance.is_finite() {
+            return Intersect::empty();
+        }
+
+        let point = ray_origin + ray_direction * distance;
+        let normal = (point - self.center).normalize();
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+        let v = 0.5 + normal.y.clamp(-1.0, 1.0).asin() / PI;
+        Intersect::new(point, normal, distance, self.material).with_uv(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_sphere() -> Sphere {
+        Sphere::new(Vec3::zeros(), 1.0, Material::sand())
+    }
+
+    #[test]
+    fn outside_and_inside_rays_hit_the_right_side() {
+        let sphere = unit_sphere();
+        let hit = sphere.ray_intersect(&Vec3::new(0.0, 0.0, 5.0), &Vec3::new(0.0, 0.0, -1.0));
+        assert!(hit.is_intersecting);
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-5);
+
+        let inside = sphere.ray_intersect(&Vec3::new(0.0, 0.5, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+        assert!(inside.is_intersecting);
+        assert!((inside.distance - 0.5).abs() < 1e-5);
+        assert!((inside.normal - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+
+        let scaled = sphere.ray_intersect(&Vec3::new(0.0, 0.0, 5.0), &Vec3::new(0.0, 0.0, -2.0));
+        assert!((scaled.distance - 2.0).abs() < 1e-5, "distance is in units of the direction");
+        assert!((scaled.point - hit.point).magnitude() < 1e-5);
+        assert!((0.0..=1.0).contains(&hit.uv.0) && (0.0..=1.0).contains(&hit.uv.1));
+    }
+
+    #[test]
+    fn misses_tangents_and_degenerate_input() {
+        let sphere = unit_sphere();
+        let origin = Vec3::new(0.0, 0.0, 5.0);
+        assert!(!sphere.ray_intersect(&origin, &Vec3::new(0.0, 0.0, 1.0)).is_intersecting, "sphere behind the ray");
+        assert!(!sphere.ray_intersect(&origin, &Vec3::new(0.0, 1.1, -5.0).normalize()).is_intersecting);
+        let grazing = sphere.ray_intersect(&Vec3::new(0.0, 1.0, 5.0), &Vec3::new(0.0, 0.0, -1.0));
+        assert!(!grazing.is_intersecting || (grazing.point.y - 1.0).abs() < 1e-3);
+
+        let from_surface = sphere.ray_intersect(&Vec3::new(0.0, 0.0, 1.0), &Vec3::new(0.0, 0.0, 1.0));
+        assert!(!from_surface.is_intersecting, "a ray leaving the surface must not re-hit it");
+
+        for radius in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let degenerate = Sphere::new(Vec3::zeros(), radius, Material::sand());
+            assert!(!degenerate.ray_intersect(&origin, &Vec3::new(0.0, 0.0, -1.0)).is_intersecting, "radius {}", radius);
+        }
+        for direction in [Vec3::zeros(), Vec3::new(f32::NAN, 0.0, -1.0)] {
+            assert!(!sphere.ray_intersect(&origin, &direction).is_intersecting, "direction {:?}", direction);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::math::mix;
+
+const RESET_DISTANCE: f32 = 1.0;
+const RESET_COS_ANGLE: f32 = 0.985;
+const DISOCCLUSION_TOLERANCE: f32 = 0.05;
+const SKY_DISTANCE: f32 = 1.0e4;
+
+/// Previous frame's resolved colors and depths, reprojected into the next frame through the camera motion.
+pub struct TemporalHistory {
+    camera: Option<Camera>,
+    width: usize,
+    height: usize,
+    colors: Vec<[f32; 3]>,
+    depths: Vec<f32>,
+    counts: Vec<f32>,
+}
+
+impl TemporalHistory {
+    pub fn new() -> Self {
+        TemporalHistory {
+            camera: None,
+            width: 0,
+            height: 0,
+            colors: Vec::new(),
+            depths: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.camera = None;
+    }
+
+    fn is_valid_for(&self, framebuffer: &Framebuffer, camera: &Camera) -> bool {
+        let previous = match &self.camera {
+            Some(previous) => previous,
+            None => return false,
+        };
+        self.width == framebuffer.width
+            && self.height == framebuffer.height
+            && (previous.eye - camera.eye).magnitude() <= RESET_DISTANCE
+            && previous.forward().dot(&camera.forward()) >= RESET_COS_ANGLE
+            && previous.pixel_aspect == camera.pixel_aspect
+    }
+
+    /// Blends each pixel with its reprojected history, weighting history by `n / (n + 1)` up to `max_blend` so a
+    /// static camera averages like plain accumulation. Disoccluded pixels and large camera jumps start over.
+    pub fn resolve(&mut self, framebuffer: &mut Framebuffer, camera: &Camera, max_blend: f32) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let valid = self.is_valid_for(framebuffer, camera);
+        let mut colors = Vec::with_capacity(width * height);
+        let mut depths = Vec::with_capacity(width * height);
+        let mut counts = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let current = framebuffer.hdr_pixel(x, y).unwrap_or([0.0; 3]);
+                let depth = framebuffer.depth(x, y);
+                let history = if valid { self.reproject(camera, x, y, depth) } else { None };
+
+                let (color, count) = match history {
+                    Some((previous, count)) => {
+                        let weight = (count / (count + 1.0)).min(max_blend);
+                        let blended = [
+                            mix(current[0], previous[0], weight),
+                            mix(current[1], previous[1], weight),
+                            mix(current[2], previous[2], weight),
+                        ];
+                        framebuffer.set_hdr_pixel(x, y, blended);
+                        (blended, count + 1.0)
+                    }
+                    None => (current, 1.0),
+                };
+                colors.push(color);
+                depths.push(depth);
+                counts.push(count);
+            }
+        }
+
+        *self = TemporalHistory {
+            camera: Some(camera.clone()),
+            width,
+            height,
+            colors,
+            depths,
+            counts,
+        };
+    }
+
+    fn reproject(&self, camera: &Camera, x: usize, y: usize, depth: f32) -> Option<([f32; 3], f32)> {
+        let previous_camera = self.camera.as_ref()?;
+        let (width, height) = (self.width as f32, self.height as f32);
+        let direction = camera.base_change(&camera.pixel_direction(x as f32, y as f32, width, height, 1.0));
+        let world = camera.eye + direction * depth.min(SKY_DISTANCE);
+
+        let (px, py) = previous_camera.project(&world, width, height)?;
+        let (px, py) = (px + 0.5, py + 0.5);
+        if px < 0.0 || py < 0.0 || px >= width || py >= height {
+            return None;
+        }
+        let index = py as usize * self.width + px as usize;
+
+        let previous_depth = self.depths[index];
+        if depth.is_finite() != previous_depth.is_finite() {
+            return None;
+        }
+        if depth.is_finite() {
+            let expected = (world - previous_camera.eye).magnitude();
+            if (previous_depth - expected).abs() > DISOCCLUSION_TOLERANCE * expected {
+                return None;
+            }
+        }
+
+        Some((self.colors[index], self.counts[index]))
+    }
+}
+
+impl Default for TemporalHistory {
+    fn default() -> Self {
+        TemporalHistory::new()
+    }
+}
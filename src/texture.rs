@@ -0,0 +1,158 @@
+use crate::color::Color;
+use crate::math::saturate;
+
+#[derive(Debug, Clone)]
+struct MipLevel {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+
+    fn sample(&self, u: f32, v: f32) -> Color {
+        let x = (u.rem_euclid(1.0) * self.width as f32) as usize;
+        let y = (saturate(v) * self.height as f32) as usize;
+        self.texel(x, y)
+    }
+
+    /// Blends the four texels around (u, v). Columns wrap so the left and right edges join seamlessly; rows clamp.
+    fn sample_bilinear(&self, u: f32, v: f32) -> Color {
+        let x = u.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let y = saturate(v) * self.height as f32 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let x0 = (x.floor() as isize).rem_euclid(self.width as isize) as usize;
+        let x1 = (x0 + 1) % self.width;
+        let y0 = y.floor().max(0.0) as usize;
+        let y1 = y0 + 1;
+        let top = self.texel(x0, y0).lerp(self.texel(x1, y0), fx);
+        let bottom = self.texel(x0, y1).lerp(self.texel(x1, y1), fx);
+        top.lerp(bottom, if y < 0.0 { 0.0 } else { fy })
+    }
+
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let block = [
+                    self.texel(2 * x, 2 * y),
+                    self.texel(2 * x + 1, 2 * y),
+                    self.texel(2 * x, 2 * y + 1),
+                    self.texel(2 * x + 1, 2 * y + 1),
+                ];
+                pixels.push(Color::average(block).unwrap_or_else(Color::black));
+            }
+        }
+        MipLevel { width, height, pixels }
+    }
+}
+
+/// Image texture with a box-filtered mip pyramid; level 0 is the full-resolution image.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    levels: Vec<MipLevel>,
+}
+
+impl Texture {
+    pub fn load(path: &str) -> Result<Self, image::ImageError> {
+        let image = image::open(path)?.to_rgb8();
+        let (width, height) = image.dimensions();
+        let pixels = image
+            .pixels()
+            .map(|pixel| Color::new(pixel[0], pixel[1], pixel[2]))
+            .collect();
+
+        Ok(Texture::from_pixels(width as usize, height as usize, pixels))
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        let mut levels = vec![MipLevel { width, height, pixels }];
+        while let Some(last) = levels.last().filter(|level| level.width > 1 || level.height > 1) {
+            let next = last.downsample();
+            levels.push(next);
+        }
+        Texture { width, height, levels }
+    }
+
+    pub fn texel(&self, x: usize, y: usize) -> Color {
+        self.levels[0].texel(x, y)
+    }
+
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        self.levels[0].sample(u, v)
+    }
+
+    pub fn sample_bilinear(&self, u: f32, v: f32) -> Color {
+        self.levels[0].sample_bilinear(u, v)
+    }
+
+    pub fn mip_level(&self, footprint: f32) -> usize {
+        let texels = footprint * self.width.max(self.height) as f32;
+        if texels <= 1.0 || !texels.is_finite() {
+            return 0;
+        }
+        (texels.log2().floor() as usize).min(self.levels.len() - 1)
+    }
+
+    /// Samples the mip level whose texels best match `footprint`, the sampled width in UV units.
+    pub fn sample_footprint(&self, u: f32, v: f32, footprint: f32) -> Color {
+        self.levels[self.mip_level(footprint)].sample(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkerboard(size: usize) -> Texture {
+        let pixels = (0..size * size)
+            .map(|i| if (i % size + i / size).is_multiple_of(2) { Color::new(255, 255, 255) } else { Color::black() })
+            .collect();
+        Texture::from_pixels(size, size, pixels)
+    }
+
+    #[test]
+    fn wider_cones_select_coarser_mips() {
+        let texture = checkerboard(64);
+        assert_eq!(texture.levels.len(), 7);
+        assert_eq!(texture.mip_level(0.0), 0);
+        assert_eq!(texture.mip_level(1.0 / 64.0), 0);
+        let mut previous = 0;
+        for step in 1..=64 {
+            let level = texture.mip_level(step as f32 / 64.0);
+            assert!(level >= previous);
+            previous = level;
+        }
+        assert_eq!(texture.mip_level(4.0 / 64.0), 2);
+        assert_eq!(texture.mip_level(100.0), 6);
+        assert_eq!(texture.mip_level(f32::INFINITY), 0);
+    }
+
+    #[test]
+    fn coarse_mips_average_the_checkerboard() {
+        let texture = checkerboard(8);
+        let neighbours = (texture.sample_footprint(0.3, 0.6, 0.0).red(), texture.sample_footprint(0.3 + 1.0 / 8.0, 0.6, 0.0).red());
+        assert!(neighbours == (0, 255) || neighbours == (255, 0), "{:?}", neighbours);
+        let gray = texture.sample_footprint(0.3, 0.6, 1.0);
+        assert!((gray.red() as i32 - 128).abs() <= 1, "top mip should be mid-gray, got {:?}", gray);
+    }
+
+    #[test]
+    fn bilinear_sampling_interpolates_and_wraps_the_seam() {
+        let texture = Texture::from_pixels(2, 1, vec![Color::black(), Color::new(255, 255, 255)]);
+        let halfway = texture.sample_bilinear(0.5, 0.5);
+        assert!((halfway.red() as i32 - 128).abs() <= 1, "got {:?}", halfway);
+        assert_eq!(texture.sample_bilinear(0.25, 0.5), Color::black());
+
+        let (left, right) = (texture.sample_bilinear(1e-4, 0.5), texture.sample_bilinear(1.0 - 1e-4, 0.5));
+        assert!(left.red().abs_diff(right.red()) <= 1, "seam jumps from {:?} to {:?}", right, left);
+        assert!((texture.sample_bilinear(1.0, 0.5).red() as i32 - 128).abs() <= 1);
+    }
+}
@@ -0,0 +1,32 @@
+//! Monotonic timer used for frame timing and animation. Native builds use `std::time::Instant`; under wasm32, where
+//! `Instant::now` panics, it reads `performance.now()` through an `env.performance_now` import the page provides.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+pub use web::Instant;
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::time::Duration;
+
+    #[link(wasm_import_module = "env")]
+    extern "C" {
+        fn performance_now() -> f64;
+    }
+
+    /// Milliseconds since page load, as reported by `performance.now()`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Instant(f64);
+
+    impl Instant {
+        pub fn now() -> Self {
+            Instant(unsafe { performance_now() })
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::from_secs_f64((Instant::now().0 - self.0).max(0.0) / 1000.0)
+        }
+    }
+}
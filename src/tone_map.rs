@@ -0,0 +1,122 @@
+use crate::color::Color;
+use crate::math::saturate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMap {
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl ToneMap {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "None" => Some(ToneMap::None),
+            "Reinhard" => Some(ToneMap::Reinhard),
+            "Aces" => Some(ToneMap::Aces),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ToneMap::None => ToneMap::Reinhard,
+            ToneMap::Reinhard => ToneMap::Aces,
+            ToneMap::Aces => ToneMap::None,
+        }
+    }
+
+    pub fn apply(self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        match self {
+            ToneMap::None => [r, g, b],
+            ToneMap::Reinhard => {
+                let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                if luminance <= 0.0 {
+                    return [0.0, 0.0, 0.0];
+                }
+                let scale = 1.0 / (1.0 + luminance);
+                [r * scale, g * scale, b * scale]
+            }
+            ToneMap::Aces => [aces(r), aces(g), aces(b)],
+        }
+    }
+}
+
+fn aces(x: f32) -> f32 {
+    let x = x.max(0.0);
+    saturate((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14))
+}
+
+/// Scales `color` by `scalar`, rolling each channel above `knee` (a fraction of white) off smoothly towards white
+/// instead of clipping it.
+pub fn scale_soft_clipped(color: Color, scalar: f32, knee: f32) -> Color {
+    let knee = saturate(knee) * 255.0;
+    color.map(|channel| soft_knee(channel * scalar, knee))
+}
+
+fn soft_knee(value: f32, knee: f32) -> f32 {
+    if value <= knee {
+        return value.max(0.0);
+    }
+    let headroom = 255.0 - knee;
+    if headroom <= 0.0 {
+        return 255.0;
+    }
+    knee + headroom * (1.0 - (-(value - knee) / headroom).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soft_clip_is_monotonic_and_stays_below_white() {
+        let knee = 0.8;
+        let mut previous = 0.0;
+        for step in 0..200 {
+            let scalar = step as f32 * 0.05;
+            let value = scale_soft_clipped(Color::new(255, 255, 255), scalar, knee).to_unit()[0] * 255.0;
+            assert!(value >= previous, "not monotonic at scale {}", scalar);
+            assert!(value <= 255.0, "exceeded white at scale {}", scalar);
+            previous = value;
+        }
+        assert!(previous > 254.0, "should approach white, got {}", previous);
+    }
+
+    #[test]
+    fn soft_clip_leaves_values_below_the_knee_alone() {
+        let color = Color::new(100, 50, 25);
+        assert_eq!(scale_soft_clipped(color, 1.0, 0.8), color);
+    }
+
+    #[test]
+    fn curves_are_monotonic_and_map_black_to_black() {
+        for tone_map in [ToneMap::Reinhard, ToneMap::Aces] {
+            assert_eq!(tone_map.apply([0.0; 3]), [0.0; 3]);
+            let mut previous = 0.0;
+            for step in 1..400 {
+                let value = tone_map.apply([step as f32 * 0.05; 3])[0];
+                assert!(value >= previous && value <= 1.0, "{:?} at {}", tone_map, step);
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    fn aces_keeps_highlights_brighter_than_reinhard() {
+        for input in [0.18, 1.0, 4.0, 16.0] {
+            let reinhard = ToneMap::Reinhard.apply([input; 3])[0];
+            let aces = ToneMap::Aces.apply([input; 3])[0];
+            assert!(aces > reinhard, "at {}: ACES {} vs Reinhard {}", input, aces, reinhard);
+        }
+        assert!(ToneMap::Aces.apply([16.0; 3])[0] > 0.99);
+    }
+
+    #[test]
+    fn reinhard_keeps_channel_ratios_while_aces_rolls_them_towards_white() {
+        let [r, g, b] = ToneMap::Reinhard.apply([4.0, 1.0, 0.5]);
+        assert!((r / g - 4.0).abs() < 1e-4 && (g / b - 2.0).abs() < 1e-4);
+        let [r, g, b] = ToneMap::Aces.apply([4.0, 1.0, 0.5]);
+        assert!(r > g && g > b && r / b < 2.0);
+    }
+}
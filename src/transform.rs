@@ -0,0 +1,31 @@
+use nalgebra_glm::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::new(Vec3::new(0.0, 0.0, 0.0), 1.0)
+    }
+}
+
+impl Transform {
+    pub fn new(translation: Vec3, scale: f32) -> Self {
+        Transform { translation, scale }
+    }
+
+    pub fn translation(translation: Vec3) -> Self {
+        Transform::new(translation, 1.0)
+    }
+
+    pub fn apply_point(&self, point: &Vec3) -> Vec3 {
+        self.translation + point * self.scale
+    }
+
+    pub fn then(&self, inner: &Transform) -> Transform {
+        Transform::new(self.apply_point(&inner.translation), self.scale * inner.scale)
+    }
+}
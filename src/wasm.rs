@@ -0,0 +1,71 @@
+//! Browser entry points, exported as plain C-ABI functions so the module loads without generated bindings. A page
+//! calls `render_to_rgba` each frame and copies `frame_len()` bytes starting at the returned pointer out of the
+//! module's memory into an `ImageData`.
+
+use std::cell::RefCell;
+use nalgebra_glm::Vec3;
+use crate::beach::Beach;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::environment::{EnvMap, Environment, SkyGradient};
+use crate::render_settings::RenderSettings;
+use crate::renderer::{calculate_light_intensity, render_to_rgba as render_rgba};
+use crate::scene::Scene;
+
+const SUN_ORBIT_RADIUS: f32 = 15.0;
+
+/// Directions `move_camera` accepts, indexed by the page's direction code.
+const MOVES: [&str; 4] = ["forward", "backward", "left", "right"];
+
+struct Session {
+    beach: Beach,
+    scene: Scene,
+    camera: Camera,
+    settings: RenderSettings,
+    frame: Vec<u8>,
+}
+
+thread_local! {
+    static SESSION: RefCell<Session> = RefCell::new(Session {
+        beach: Beach::new(),
+        scene: Scene::new(Environment::new(EnvMap::Gradient(SkyGradient::default()))),
+        camera: Camera::new(Vec3::new(5.0, 7.0, 10.0), Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        settings: RenderSettings::default(),
+        frame: Vec::new(),
+    });
+}
+
+/// Renders the beach at animation `time` with the sun at `sun_angle` radians and returns a pointer to the
+/// `width * height` RGBA pixels. The pointer stays valid until the next call.
+#[no_mangle]
+pub extern "C" fn render_to_rgba(width: u32, height: u32, time: f32, sun_angle: f32) -> *const u8 {
+    SESSION.with(|session| {
+        let session = &mut *session.borrow_mut();
+        let sun = Vec3::new(SUN_ORBIT_RADIUS * sun_angle.cos(), SUN_ORBIT_RADIUS * sun_angle.sin(), 0.0);
+        session.scene.lights = session.beach.lights(sun, Color::new(255, 255, 255));
+        session.scene.objects = session.beach.objects_at(time, session.settings.wind_strength);
+        session.scene.prototypes.clone_from(&session.beach.prototypes);
+        session.scene.environment.light_intensity = calculate_light_intensity(&sun);
+        session.frame = render_rgba(&session.scene, &session.camera, &session.settings, width as usize, height as usize, None);
+        session.frame.as_ptr()
+    })
+}
+
+/// Byte length of the frame the last `render_to_rgba` returned.
+#[no_mangle]
+pub extern "C" fn frame_len() -> usize {
+    SESSION.with(|session| session.borrow().frame.len())
+}
+
+/// Moves the camera one step: 0 forward, 1 backward, 2 left, 3 right. Other codes are ignored.
+#[no_mangle]
+pub extern "C" fn move_camera(direction: u32) {
+    if let Some(direction) = MOVES.get(direction as usize) {
+        SESSION.with(|session| session.borrow_mut().camera.move_camera(direction));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn orbit_camera(delta_yaw: f32, delta_pitch: f32) {
+    SESSION.with(|session| session.borrow_mut().camera.orbit(delta_yaw, delta_pitch));
+}
@@ -0,0 +1,230 @@
+use core::fmt;
+use crate::math::mix;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r: r as f32, g: g as f32, b: b as f32 }
+    }
+
+    pub const fn from_hex(hex: u32) -> Self {
+        let r = ((hex >> 16) & 0xFF) as u8;
+        let g = ((hex >> 8) & 0xFF) as u8;
+        let b = (hex & 0xFF) as u8;
+        Color::new(r, g, b)
+    }
+
+    pub fn from_unit(r: f32, g: f32, b: f32) -> Self {
+        Color {
+            r: r.clamp(0.0, 1.0) * 255.0,
+            g: g.clamp(0.0, 1.0) * 255.0,
+            b: b.clamp(0.0, 1.0) * 255.0,
+        }
+    }
+
+    pub const fn black() -> Self {
+        Color { r: 0.0, g: 0.0, b: 0.0 }
+    }
+
+    pub fn to_unit(self) -> [f32; 3] {
+        [self.r / 255.0, self.g / 255.0, self.b / 255.0]
+    }
+
+    pub fn to_hex(self) -> u32 {
+        ((self.red() as u32) << 16) | ((self.green() as u32) << 8) | (self.blue() as u32)
+    }
+
+    pub fn red(&self) -> u8 {
+        self.r.clamp(0.0, 255.0) as u8
+    }
+
+    pub fn green(&self) -> u8 {
+        self.g.clamp(0.0, 255.0) as u8
+    }
+
+    pub fn blue(&self) -> u8 {
+        self.b.clamp(0.0, 255.0) as u8
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.r.is_finite() && self.g.is_finite() && self.b.is_finite()
+    }
+
+    pub fn luminance(self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Scales the color down uniformly so its luminance is at most `limit`, in the same 0-255 units.
+    pub fn with_max_luminance(self, limit: f32) -> Color {
+        let luminance = self.luminance();
+        if luminance <= limit || luminance <= 0.0 {
+            return self;
+        }
+        self * (limit / luminance)
+    }
+
+    pub fn average<I: IntoIterator<Item = Color>>(colors: I) -> Option<Color> {
+        Color::average_clamped(colors, None)
+    }
+
+    pub fn average_clamped<I: IntoIterator<Item = Color>>(colors: I, clamp_multiple: Option<f32>) -> Option<Color> {
+        let mut sum = (0.0, 0.0, 0.0);
+        let mut luminance_sum = 0.0;
+        let mut count = 0;
+        for mut color in colors {
+            if let Some(multiple) = clamp_multiple {
+                let luminance = color.luminance();
+                if count > 0 {
+                    let limit = multiple * luminance_sum / count as f32;
+                    if luminance > limit && luminance > 0.0 {
+                        let scale = limit / luminance;
+                        color = Color { r: color.r * scale, g: color.g * scale, b: color.b * scale };
+                    }
+                }
+                luminance_sum += color.luminance();
+            }
+            sum.0 += color.r;
+            sum.1 += color.g;
+            sum.2 += color.b;
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        let count = count as f32;
+        Some(Color { r: sum.0 / count, g: sum.1 / count, b: sum.2 / count })
+    }
+
+    /// Sum of `color * weight` pairs, clamped at zero only once at the end so negative weights can cancel
+    /// positive ones.
+    pub fn weighted_sum<I: IntoIterator<Item = (Color, f32)>>(terms: I) -> Color {
+        let mut sum = (0.0, 0.0, 0.0);
+        for (color, weight) in terms {
+            sum.0 += color.r * weight;
+            sum.1 += color.g * weight;
+            sum.2 += color.b * weight;
+        }
+        Color { r: floor_zero(sum.0), g: floor_zero(sum.1), b: floor_zero(sum.2) }
+    }
+
+    pub fn from_channels(red: Color, green: Color, blue: Color) -> Color {
+        Color { r: red.r, g: green.g, b: blue.b }
+    }
+
+    pub fn lerp(self, other: Color, factor: f32) -> Color {
+        Color {
+            r: mix(self.r, other.r, factor),
+            g: mix(self.g, other.g, factor),
+            b: mix(self.b, other.b, factor),
+        }
+    }
+
+    /// Applies `f` to each channel, in the same 0-255 units the color is stored in.
+    pub fn map(self, f: impl Fn(f32) -> f32) -> Color {
+        Color { r: f(self.r), g: f(self.g), b: f(self.b) }
+    }
+}
+
+/// Clamps negatives to zero but, unlike `f32::max`, lets NaN through so broken samples stay visible.
+fn floor_zero(value: f32) -> f32 {
+    if value < 0.0 { 0.0 } else { value }
+}
+
+use core::ops::Add;
+
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, other: Color) -> Color {
+        Color {
+            r: floor_zero(self.r + other.r),
+            g: floor_zero(self.g + other.g),
+            b: floor_zero(self.b + other.b),
+        }
+    }
+}
+
+use core::ops::Sub;
+
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        Color {
+            r: floor_zero(self.r - other.r),
+            g: floor_zero(self.g - other.g),
+            b: floor_zero(self.b - other.b),
+        }
+    }
+}
+
+use core::ops::Mul;
+
+impl Mul<f32> for Color {
+    type Output = Color;
+
+    fn mul(self, scalar: f32) -> Color {
+        Color {
+            r: floor_zero(self.r * scalar),
+            g: floor_zero(self.g * scalar),
+            b: floor_zero(self.b * scalar),
+        }
+    }
+}
+
+impl Mul<Color> for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color {
+            r: self.r * other.r / 255.0,
+            g: self.g * other.g / 255.0,
+            b: self.b * other.b / 255.0,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_floor_negatives_at_zero() {
+        let dim = Color::new(10, 20, 30);
+        assert_eq!(dim - Color::new(20, 10, 40), Color::new(0, 10, 0));
+        assert_eq!(dim * -1.0, Color::black());
+        assert_eq!(Color::weighted_sum([(dim, 1.0), (Color::new(20, 20, 20), -1.0)]), Color::new(0, 0, 10));
+    }
+
+    #[test]
+    fn operators_keep_nan_visible() {
+        let broken = Color::new(10, 20, 30) * f32::NAN;
+        assert!(!broken.is_finite());
+        assert!(!(broken + Color::new(1, 1, 1)).is_finite());
+        assert!(!(Color::new(1, 1, 1) - broken).is_finite());
+        assert!(!Color::weighted_sum([(broken, 0.5), (Color::black(), 0.5)]).is_finite());
+    }
+
+    #[test]
+    fn firefly_clamp_suppresses_an_outlier() {
+        let mut samples = [Color::new(40, 40, 40); 8];
+        samples[5] = Color::new(255, 255, 255) * 50.0;
+        let unbiased = Color::average_clamped(samples, None).unwrap();
+        let clamped = Color::average_clamped(samples, Some(4.0)).unwrap();
+        assert!(unbiased.luminance() > 1000.0);
+        assert!(clamped.luminance() < 40.0 * 1.5, "outlier left at {}", clamped.luminance());
+        assert_eq!(Color::average_clamped(samples, Some(f32::INFINITY)), Some(unbiased));
+    }
+}
@@ -0,0 +1,11 @@
+//! Math shared by the tracer that only needs `core`: colors, scalar helpers, reflection, Fresnel and ray/box slabs.
+//! The `libm` feature adds what needs float functions `core` lacks, such as `optics::refract`.
+//!
+//! Unit tests link `std`, so they do not show the crate builds without it. `cargo no-std` builds it for
+//! `x86_64-unknown-none`, a target that has no `std` at all (`rustup target add x86_64-unknown-none` once).
+#![cfg_attr(not(test), no_std)]
+
+pub mod color;
+pub mod math;
+pub mod optics;
+pub mod ray_box;
@@ -0,0 +1,48 @@
+pub fn clamp(x: f32, lo: f32, hi: f32) -> f32 {
+    x.max(lo).min(hi)
+}
+
+pub fn saturate(x: f32) -> f32 {
+    clamp(x, 0.0, 1.0)
+}
+
+pub fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a * (1.0 - t) + b * t
+}
+
+/// Hermite step from 0 at `edge0` to 1 at `edge1`; returns a hard step when the edges coincide.
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge1 == edge0 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = saturate((x - edge0) / (edge1 - edge0));
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoothstep_hits_its_boundaries_and_midpoint() {
+        assert_eq!(smoothstep(1.0, 3.0, 1.0), 0.0);
+        assert_eq!(smoothstep(1.0, 3.0, 3.0), 1.0);
+        assert_eq!(smoothstep(1.0, 3.0, 2.0), 0.5);
+        assert_eq!(smoothstep(1.0, 3.0, -5.0), 0.0);
+        assert_eq!(smoothstep(1.0, 3.0, 9.0), 1.0);
+        assert_eq!(smoothstep(2.0, 2.0, 1.9), 0.0);
+        assert_eq!(smoothstep(2.0, 2.0, 2.0), 1.0);
+        assert!((smoothstep(3.0, 1.0, 1.5) - 0.84375).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clamp_saturate_and_mix() {
+        assert_eq!(clamp(5.0, -1.0, 2.0), 2.0);
+        assert_eq!(clamp(-5.0, -1.0, 2.0), -1.0);
+        assert_eq!(saturate(0.25), 0.25);
+        assert_eq!(saturate(f32::NAN), 0.0);
+        assert_eq!(mix(2.0, 6.0, 0.0), 2.0);
+        assert_eq!(mix(2.0, 6.0, 1.0), 6.0);
+        assert_eq!(mix(2.0, 6.0, 0.25), 3.0);
+    }
+}
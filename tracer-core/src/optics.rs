@@ -0,0 +1,44 @@
+use nalgebra_glm::Vec3;
+
+pub fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
+    incident - 2.0 * incident.dot(normal) * normal
+}
+
+pub fn fresnel(cos_theta: f32, refractive_index: f32) -> f32 {
+    let r = (1.0 - refractive_index) / (1.0 + refractive_index);
+    let r0 = r * r;
+    let m = 1.0 - cos_theta;
+    r0 + (1.0 - r0) * (m * m * m * m * m)
+}
+
+/// Snell refraction of a unit `incident` direction through a surface with unit `normal` facing the incident side,
+/// where `eta` is the ratio of refractive indices (from / to). `None` on total internal reflection.
+#[cfg(feature = "libm")]
+pub fn refract(incident: &Vec3, normal: &Vec3, eta: f32) -> Option<Vec3> {
+    let cos_incident = -incident.dot(normal);
+    let k = 1.0 - eta * eta * (1.0 - cos_incident * cos_incident);
+    if k < 0.0 {
+        return None;
+    }
+    Some(incident * eta + normal * (eta * cos_incident - libm::sqrtf(k)))
+}
+
+#[cfg(all(test, feature = "libm"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refraction_follows_snells_law_until_total_internal_reflection() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let (sin_in, cos_in) = (0.6, 0.8);
+        let incident = Vec3::new(sin_in, -cos_in, 0.0);
+        let eta = 1.0 / 1.5;
+        let refracted = refract(&incident, &normal, eta).unwrap();
+        assert!((refracted.x - sin_in * eta).abs() < 1e-6);
+        assert!((refracted.dot(&refracted) - 1.0).abs() < 1e-6);
+        assert!(refracted.y < 0.0);
+
+        assert_eq!(refract(&Vec3::new(0.0, -1.0, 0.0), &normal, eta), Some(Vec3::new(0.0, -1.0, 0.0)));
+        assert_eq!(refract(&incident, &normal, 1.8), None);
+    }
+}
@@ -0,0 +1,80 @@
+use nalgebra_glm::Vec3;
+
+/// Distances at which a ray enters and leaves the axis-aligned box from `min_bound` to `max_bound`, or `None` if it
+/// misses. The slabs are allowed to miss each other by `epsilon` so rays across an edge are not lost between faces.
+/// Either distance may be behind the origin; the caller decides which one it can use.
+pub fn ray_box(ray_origin: &Vec3, ray_direction: &Vec3, min_bound: &Vec3, max_bound: &Vec3, epsilon: f32) -> Option<(f32, f32)> {
+    ray_box_axes(ray_origin, ray_direction, min_bound, max_bound, epsilon).map(|((near, _), (far, _))| (near, far))
+}
+
+/// `ray_box` with the axis, 0 for x to 2 for z, whose slab each distance comes from, so the caller knows which face
+/// the ray crosses there. Ties go to the lower axis.
+pub fn ray_box_axes(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    min_bound: &Vec3,
+    max_bound: &Vec3,
+    epsilon: f32,
+) -> Option<((f32, usize), (f32, usize))> {
+    let slab = |axis: usize| {
+        let t_min = (min_bound[axis] - ray_origin[axis]) / ray_direction[axis];
+        let t_max = (max_bound[axis] - ray_origin[axis]) / ray_direction[axis];
+        if t_min > t_max {
+            (t_max, t_min)
+        } else {
+            (t_min, t_max)
+        }
+    };
+
+    let (t_min, t_max) = slab(0);
+    let (mut near, mut far) = ((t_min, 0), (t_max, 0));
+    for axis in 1..3 {
+        let (t_min, t_max) = slab(axis);
+        if (near.0 > t_max + epsilon) || (t_min > far.0 + epsilon) {
+            return None;
+        }
+        if t_min > near.0 {
+            near = (t_min, axis);
+        }
+        if t_max < far.0 {
+            far = (t_max, axis);
+        }
+    }
+
+    Some((near, far))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(origin: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+        ray_box(&origin, &direction, &Vec3::new(-1.0, -1.0, -1.0), &Vec3::new(1.0, 1.0, 1.0), 0.0)
+    }
+
+    #[test]
+    fn axis_aligned_ray_enters_and_leaves() {
+        assert_eq!(unit_box(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), Some((4.0, 6.0)));
+    }
+
+    #[test]
+    fn parallel_ray_outside_a_slab_misses() {
+        assert_eq!(unit_box(Vec3::new(0.0, 2.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), None);
+    }
+
+    #[test]
+    fn ray_from_inside_starts_behind_the_origin() {
+        let (near, far) = unit_box(Vec3::zeros(), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!(near < 0.0);
+        assert_eq!(far, 1.0);
+    }
+
+    #[test]
+    fn axes_name_the_slabs_the_ray_crosses() {
+        let (min_bound, max_bound) = (Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let hit = |origin: Vec3, direction: Vec3| ray_box_axes(&origin, &direction, &min_bound, &max_bound, 0.0);
+        assert_eq!(hit(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), Some(((4.0, 2), (6.0, 2))));
+        assert_eq!(hit(Vec3::new(0.0, 3.0, -0.5), Vec3::new(0.0, -1.0, 0.5)), Some(((2.0, 1), (3.0, 2))));
+        assert_eq!(hit(Vec3::new(3.0, 3.0, 0.0), Vec3::new(-1.0, -1.0, 0.0)), Some(((2.0, 0), (4.0, 0))));
+    }
+}